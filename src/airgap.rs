@@ -0,0 +1,624 @@
+use crate::classify::{self, PayloadKind};
+use crate::config;
+use crate::error::{AppError, Result};
+use crate::gpg_cli;
+use crate::gpg_ops;
+use crate::qr_utils;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use zeroize::{Zeroize, Zeroizing};
+
+// Air-gapped request/response protocol, in the spirit of PSBT: the online machine creates a
+// request QR, the offline machine shows exactly what it is asked to do, performs it and answers
+// with a response QR bound to the request nonce. Payloads are a prefix followed by JSON.
+
+pub const SIGN_REQUEST_PREFIX: &str = "SGSIGREQ1:";
+pub const SIGN_RESPONSE_PREFIX: &str = "SGSIGRES1:";
+pub const DECRYPT_REQUEST_PREFIX: &str = "SGDECREQ1:";
+pub const DECRYPT_RESPONSE_PREFIX: &str = "SGDECRES1:";
+// Version 2 of signing requests names the document and requester; decryption is unchanged
+const SIGN_PROTOCOL_VERSION: u32 = 2;
+const DECRYPT_PROTOCOL_VERSION: u32 = 1;
+// Signature notation carrying the request nonce, so the signature itself is bound to the request
+pub const NONCE_NOTATION: &str = "nonce@secure-gpg-qr";
+// [timeouts] airgap_secs, a day by default
+fn pending_ttl() -> Duration {
+    config::timeouts().airgap()
+}
+const EPHEMERAL_KEY_UID: &str = "Secure-GPG-QR one-time key";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SignRequestMode {
+    Clearsign,
+    Detach,
+    Normal,
+}
+
+impl SignRequestMode {
+    pub fn parse(mode: &str) -> Result<Self> {
+        match mode.trim().to_ascii_lowercase().as_str() {
+            "clearsign" => Ok(SignRequestMode::Clearsign),
+            "detach" => Ok(SignRequestMode::Detach),
+            "normal" => Ok(SignRequestMode::Normal),
+            other => Err(AppError::InvalidInput(format!(
+                "Unknown signing mode '{}'. Use clearsign, detach or normal.",
+                other
+            ))),
+        }
+    }
+
+    fn gpgme_mode(self) -> gpgme::SignMode {
+        match self {
+            SignRequestMode::Clearsign => gpgme::SignMode::Clear,
+            SignRequestMode::Detach => gpgme::SignMode::Detach,
+            SignRequestMode::Normal => gpgme::SignMode::Normal,
+        }
+    }
+}
+
+// Hash of a document that stays on the online machine; the signer signs a statement naming it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DocumentDigest {
+    pub sha256: String, // uppercase hex
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SignRequest {
+    pub version: u32,
+    pub nonce: String,  // 32 hex digits
+    pub signer: String, // primary key fingerprint
+    pub mode: SignRequestMode,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest: Option<DocumentDigest>,
+    pub document: String,  // document name; successive versions of a document share it
+    pub requester: String, // who asked for the signature
+    pub description: String,
+    pub created: u64, // seconds since the epoch
+}
+
+impl SignRequest {
+    // Exactly the bytes that get signed: the content, or a statement naming the document digest
+    pub fn signed_text(&self) -> Result<String> {
+        match (&self.content, &self.digest) {
+            (Some(content), None) => Ok(content.clone()),
+            (None, Some(digest)) => Ok(format!(
+                "Secure-GPG-QR signing request {}\nDocument: {}\nSHA256: {}\n",
+                self.nonce, self.document, digest.sha256
+            )),
+            _ => Err(AppError::InvalidInput(
+                "A signing request needs either content or a document digest.".to_string(),
+            )),
+        }
+    }
+
+    pub fn encode(&self) -> Result<String> {
+        Ok(format!("{}{}", SIGN_REQUEST_PREFIX, serde_json::to_string(self)?))
+    }
+
+    // What the operator has to see before approving, one line each
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("Signing request {} from {}", self.nonce, gpg_ops::format_date(self.created)),
+            format!("Requested by: {}", self.requester),
+            format!("Document: {}", self.document),
+            format!("Signer: {}", self.signer),
+            format!("Mode: {:?}", self.mode),
+        ];
+        if !self.description.is_empty() {
+            lines.push(format!("Description: {}", self.description));
+        }
+        match (&self.content, &self.digest) {
+            (Some(content), _) => lines.push(format!("Content to sign ({} bytes):\n{}", content.len(), content)),
+            (_, Some(digest)) => lines.push(format!(
+                "Document digest to sign: SHA256 {} (a statement naming this digest is signed)",
+                digest.sha256
+            )),
+            _ => {}
+        }
+        lines
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SignResponse {
+    pub version: u32,
+    pub nonce: String,
+    pub signer: String, // primary fingerprint of the key that signed
+    pub mode: SignRequestMode,
+    pub signature: String, // armored; for clearsign/normal it contains the signed text
+}
+
+impl SignResponse {
+    pub fn encode(&self) -> Result<String> {
+        Ok(format!("{}{}", SIGN_RESPONSE_PREFIX, serde_json::to_string(self)?))
+    }
+}
+
+// The version is checked before the other fields: other versions have other fields
+fn decode<T: for<'de> Deserialize<'de>>(payload: &str, prefix: &str, what: &str, version: u32) -> Result<T> {
+    #[derive(Deserialize)]
+    struct Versioned {
+        version: u32,
+    }
+    let json = payload
+        .trim()
+        .strip_prefix(prefix)
+        .ok_or_else(|| AppError::InvalidInput(format!("Not a {}.", what)))?;
+    let malformed = |e: serde_json::Error| AppError::InvalidInput(format!("Malformed {}: {}", what, e));
+    let found = serde_json::from_str::<Versioned>(json).map_err(malformed)?.version;
+    if found != version {
+        return Err(AppError::InvalidInput(format!(
+            "Unsupported {} version {} (this version of Secure-GPG-QR speaks {}).",
+            what, found, version
+        )));
+    }
+    serde_json::from_str(json).map_err(malformed)
+}
+
+pub fn parse_sign_request(payload: &str) -> Result<SignRequest> {
+    let request: SignRequest = decode(payload, SIGN_REQUEST_PREFIX, "signing request", SIGN_PROTOCOL_VERSION)?;
+    check_nonce(&request.nonce, "signing request")?;
+    request.signed_text()?;
+    Ok(request)
+}
+
+pub fn parse_sign_response(payload: &str) -> Result<SignResponse> {
+    decode(payload, SIGN_RESPONSE_PREFIX, "signing response", SIGN_PROTOCOL_VERSION)
+}
+
+fn check_nonce(nonce: &str, what: &str) -> Result<()> {
+    if nonce.len() != 32 || !nonce.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(AppError::InvalidInput(format!("The {} has an invalid nonce.", what)));
+    }
+    Ok(())
+}
+
+fn new_nonce() -> String {
+    format!("{:032x}", rand::random::<u128>())
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// --- Online side ---
+
+lazy_static::lazy_static! {
+    // Requests handed out by this instance and not answered yet, by nonce
+    static ref PENDING_SIGN_REQUESTS: Mutex<HashMap<String, (SignRequest, Instant)>> = Mutex::new(HashMap::new());
+}
+
+pub enum SignInput<'a> {
+    Content(&'a str),
+    Digest(&'a [u8]), // only the SHA-256 leaves the online machine
+}
+
+// Who asks for a signature and on what; shown to the offline operator
+pub struct RequestInfo<'a> {
+    pub document: &'a str,
+    pub requester: &'a str,
+    pub description: &'a str,
+}
+
+// Creates a signing request for `signer` and remembers it until the response arrives
+pub fn create_sign_request(
+    input: SignInput<'_>,
+    signer: &str,
+    mode: SignRequestMode,
+    info: RequestInfo<'_>,
+) -> Result<SignRequest> {
+    if info.document.trim().is_empty() {
+        return Err(AppError::InvalidInput("A document name is required.".to_string()));
+    }
+    let (content, digest) = match input {
+        SignInput::Content(content) if !content.is_empty() => (Some(content.to_string()), None),
+        SignInput::Content(_) => return Err(AppError::InvalidInput("Nothing to sign.".to_string())),
+        SignInput::Digest(data) => (None, Some(DocumentDigest { sha256: gpg_ops::sha256_hex(data) })),
+    };
+    let request = SignRequest {
+        version: SIGN_PROTOCOL_VERSION,
+        nonce: new_nonce(),
+        signer: gpg_ops::key_fingerprint(signer)?, // the public key is enough here
+        mode,
+        content,
+        digest,
+        document: info.document.trim().to_string(),
+        requester: info.requester.trim().to_string(),
+        description: info.description.to_string(),
+        created: now_secs(),
+    };
+    let mut pending = PENDING_SIGN_REQUESTS.lock();
+    pending.retain(|_, (_, created)| created.elapsed() < pending_ttl());
+    pending.insert(request.nonce.clone(), (request.clone(), Instant::now()));
+    Ok(request)
+}
+
+pub fn has_pending_sign_request(nonce: &str) -> bool {
+    PENDING_SIGN_REQUESTS
+        .lock()
+        .get(nonce)
+        .map(|(_, created)| created.elapsed() < pending_ttl())
+        .unwrap_or(false)
+}
+
+#[derive(Debug)]
+pub struct AcceptedSignature {
+    pub signature: String, // armored, ready to publish next to the document
+    pub notes: Vec<String>,
+}
+
+// Checks a response against its request: nonce, signer, mode, the signed bytes and the nonce
+// notation inside the signature. `request` is looked up among the pending requests if not given.
+pub fn check_sign_response(payload: &str, request: Option<SignRequest>) -> Result<AcceptedSignature> {
+    let response = parse_sign_response(payload)?;
+    let from_pending = request.is_none();
+    let request = match request {
+        Some(request) => request,
+        None => PENDING_SIGN_REQUESTS
+            .lock()
+            .get(&response.nonce)
+            .filter(|(_, created)| created.elapsed() < pending_ttl())
+            .map(|(request, _)| request.clone())
+            .ok_or_else(|| {
+                AppError::InvalidInput(format!(
+                    "No pending signing request with nonce {} (not created here, already answered or expired).",
+                    response.nonce
+                ))
+            })?,
+    };
+    if response.nonce != request.nonce {
+        return Err(AppError::InvalidInput("Response nonce does not match the request.".to_string()));
+    }
+    if response.mode != request.mode {
+        return Err(AppError::InvalidInput("Response uses a different signing mode than requested.".to_string()));
+    }
+
+    let signed_text = request.signed_text()?;
+    let check = match request.mode {
+        SignRequestMode::Detach => gpg_ops::check_signature(response.signature.as_bytes(), Some(signed_text.as_bytes()))?,
+        _ => gpg_ops::check_signature(response.signature.as_bytes(), None)?,
+    };
+    if !check.valid {
+        return Err(AppError::InvalidInput(format!("Signature is not valid: {}", check.status)));
+    }
+    if check.primary_fingerprint.as_deref() != Some(request.signer.as_str()) {
+        return Err(AppError::InvalidInput(format!(
+            "Signed by {} instead of the requested {}.",
+            check.primary_fingerprint.as_deref().unwrap_or("an unknown key"),
+            request.signer
+        )));
+    }
+    if let Some(plaintext) = &check.plaintext {
+        // Cleartext signatures drop trailing whitespace, so compare line by line without it
+        let normalize = |text: &str| text.lines().map(str::trim_end).collect::<Vec<_>>().join("\n").trim_end().to_string();
+        if normalize(&String::from_utf8_lossy(plaintext)) != normalize(&signed_text) {
+            return Err(AppError::InvalidInput("The signed content differs from the requested content.".to_string()));
+        }
+    }
+    if !check.notations.iter().any(|(name, value)| name == NONCE_NOTATION && *value == request.nonce) {
+        return Err(AppError::InvalidInput(
+            "The signature does not carry the request nonce (replayed or foreign signature).".to_string(),
+        ));
+    }
+
+    if from_pending {
+        PENDING_SIGN_REQUESTS.lock().remove(&request.nonce);
+    }
+    let notes = vec![
+        format!("{} by {}", check.status, request.signer),
+        format!("Bound to request {} ({:?}).", request.nonce, request.mode),
+    ];
+    Ok(AcceptedSignature { signature: response.signature, notes })
+}
+
+// --- Offline side ---
+
+// Signs what the request asks for with the requested key and returns the response payload.
+// Only called once the operator approved the request (see sign_queue).
+pub fn answer_sign_request(request: &SignRequest) -> Result<String> {
+    if gpg_ops::find_secret_key_uid(&request.signer)?.is_none() {
+        return Err(AppError::InvalidInput(format!(
+            "No secret key for the requested signer {}.",
+            request.signer
+        )));
+    }
+    let signed_text = request.signed_text()?;
+    let signature = gpg_ops::sign_with_notations(
+        signed_text.as_bytes(),
+        &request.signer,
+        request.mode.gpgme_mode(),
+        &[(NONCE_NOTATION, &request.nonce)],
+    )?;
+    SignResponse {
+        version: SIGN_PROTOCOL_VERSION,
+        nonce: request.nonce.clone(),
+        signer: request.signer.clone(),
+        mode: request.mode,
+        signature,
+    }
+    .encode()
+}
+
+// --- Decryption requests ---
+// The result never travels in clear: the request carries either the public half of a one-time
+// key generated on the online machine or a session passphrase, and the offline machine
+// re-encrypts the plaintext to it.
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseProtection {
+    EphemeralKey { public_key: String },
+    // Anyone who sees both the request and the response can read the result
+    Passphrase { passphrase: String },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DecryptRequest {
+    pub version: u32,
+    pub nonce: String,
+    pub ciphertext: String, // armored PGP MESSAGE
+    pub protection: ResponseProtection,
+    pub description: String,
+    pub created: u64,
+}
+
+impl DecryptRequest {
+    pub fn encode(&self) -> Result<String> {
+        Ok(format!("{}{}", DECRYPT_REQUEST_PREFIX, serde_json::to_string(self)?))
+    }
+
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("Decryption request {} from {}", self.nonce, gpg_ops::format_date(self.created)),
+            format!("Ciphertext: {} bytes", self.ciphertext.len()),
+            match self.protection {
+                ResponseProtection::EphemeralKey { .. } => {
+                    "The result is re-encrypted to a one-time key of the requesting machine.".to_string()
+                }
+                ResponseProtection::Passphrase { .. } => {
+                    "The result is re-encrypted with the session passphrase carried in the request.".to_string()
+                }
+            },
+        ];
+        if !self.description.is_empty() {
+            lines.push(format!("Description: {}", self.description));
+        }
+        lines
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DecryptResponse {
+    pub version: u32,
+    pub nonce: String,
+    pub message: String, // the plaintext, encrypted as the request asked
+}
+
+impl DecryptResponse {
+    pub fn encode(&self) -> Result<String> {
+        Ok(format!("{}{}", DECRYPT_RESPONSE_PREFIX, serde_json::to_string(self)?))
+    }
+}
+
+pub fn parse_decrypt_request(payload: &str) -> Result<DecryptRequest> {
+    let request: DecryptRequest =
+        decode(payload, DECRYPT_REQUEST_PREFIX, "decryption request", DECRYPT_PROTOCOL_VERSION)?;
+    check_nonce(&request.nonce, "decryption request")?;
+    Ok(request)
+}
+
+pub fn parse_decrypt_response(payload: &str) -> Result<DecryptResponse> {
+    let response: DecryptResponse =
+        decode(payload, DECRYPT_RESPONSE_PREFIX, "decryption response", DECRYPT_PROTOCOL_VERSION)?;
+    check_nonce(&response.nonce, "decryption response")?;
+    Ok(response)
+}
+
+// Private throwaway GnuPG home directory, removed with everything in it when dropped
+struct EphemeralHome {
+    path: PathBuf,
+}
+
+impl EphemeralHome {
+    fn create() -> Result<Self> {
+        let path = std::env::temp_dir().join(format!("secure-gpg-qr-{}", new_nonce()));
+        std::fs::create_dir(&path)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o700))?;
+        }
+        Ok(Self { path })
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for EphemeralHome {
+    fn drop(&mut self) {
+        // gpg-agent notices its home directory is gone and exits
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+// What the online machine keeps to open the response: the one-time secret key or the passphrase.
+// Held in memory by the web server, written to a file by the CLI.
+#[derive(Serialize, Deserialize)]
+pub struct DecryptSession {
+    pub nonce: String,
+    #[serde(flatten)]
+    secret: SessionSecret,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum SessionSecret {
+    EphemeralKey { secret_key: String },
+    Passphrase { passphrase: String },
+}
+
+impl Drop for DecryptSession {
+    fn drop(&mut self) {
+        match &mut self.secret {
+            SessionSecret::EphemeralKey { secret_key } => secret_key.zeroize(),
+            SessionSecret::Passphrase { passphrase } => passphrase.zeroize(),
+        }
+    }
+}
+
+impl DecryptSession {
+    pub fn encode(&self) -> Result<Zeroizing<String>> {
+        Ok(Zeroizing::new(serde_json::to_string(self)?))
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        serde_json::from_str(text.trim())
+            .map_err(|e| AppError::InvalidInput(format!("Malformed decryption session: {}", e)))
+    }
+
+    // Decrypts the response message with the one-time key or the session passphrase
+    fn open(&self, message: &str) -> Result<Zeroizing<Vec<u8>>> {
+        match &self.secret {
+            SessionSecret::Passphrase { passphrase } => gpg_ops::decrypt_symmetric(message.as_bytes(), passphrase),
+            SessionSecret::EphemeralKey { secret_key } => {
+                let home = EphemeralHome::create()?;
+                gpg_cli::run_gpg_in_home(home.path(), &["--import"], Some(secret_key.as_bytes()))?;
+                let output = gpg_cli::run_gpg_in_home(home.path(), &["--decrypt"], Some(message.as_bytes()))?;
+                Ok(Zeroizing::new(output.stdout))
+            }
+        }
+    }
+}
+
+// Generates an unprotected one-time key pair in a throwaway home, returning (public, secret)
+fn generate_ephemeral_key(nonce: &str) -> Result<(String, Zeroizing<String>)> {
+    let home = EphemeralHome::create()?;
+    let uid = format!("{} {}", EPHEMERAL_KEY_UID, nonce);
+    gpg_cli::run_gpg_in_home(
+        home.path(),
+        &["--passphrase", "", "--quick-gen-key", &uid, "future-default", "default", "1d"],
+        None,
+    )?;
+    let public_key = gpg_cli::run_gpg_in_home(home.path(), &["--armor", "--export"], None)?.stdout;
+    let secret_key = gpg_cli::run_gpg_in_home(home.path(), &["--armor", "--export-secret-keys"], None)?.stdout;
+    Ok((into_text(Zeroizing::new(public_key))?.to_string(), into_text(Zeroizing::new(secret_key))?))
+}
+
+fn into_text(data: Zeroizing<Vec<u8>>) -> Result<Zeroizing<String>> {
+    std::str::from_utf8(&data)
+        .map(|text| Zeroizing::new(text.to_string()))
+        .map_err(|_| AppError::Operation("Decrypted data is not text.".to_string()))
+}
+
+lazy_static::lazy_static! {
+    static ref PENDING_DECRYPT_REQUESTS: Mutex<HashMap<String, (DecryptSession, Instant)>> = Mutex::new(HashMap::new());
+}
+
+// Online side: packages a ciphertext for the offline key holder. The returned session opens
+// the response; it is also kept here so a scanned response can be opened directly.
+pub fn create_decrypt_request(ciphertext: &str, use_passphrase: bool, description: &str) -> Result<(DecryptRequest, DecryptSession)> {
+    if !classify::classify(ciphertext).iter().any(|block| block.kind == PayloadKind::EncryptedMessage) {
+        return Err(AppError::InvalidInput("No encrypted OpenPGP message found.".to_string()));
+    }
+    let nonce = new_nonce();
+    let (protection, secret) = if use_passphrase {
+        let mut random = [0u8; 20];
+        rand::RngCore::fill_bytes(&mut rand::rngs::OsRng, &mut random);
+        let passphrase = qr_utils::encode_base32(&random);
+        random.zeroize();
+        (
+            ResponseProtection::Passphrase { passphrase: passphrase.clone() },
+            SessionSecret::Passphrase { passphrase },
+        )
+    } else {
+        let (public_key, secret_key) = generate_ephemeral_key(&nonce)?;
+        (
+            ResponseProtection::EphemeralKey { public_key },
+            SessionSecret::EphemeralKey { secret_key: secret_key.to_string() }, // wiped with the session
+        )
+    };
+    let request = DecryptRequest {
+        version: DECRYPT_PROTOCOL_VERSION,
+        nonce: nonce.clone(),
+        ciphertext: ciphertext.trim().to_string(),
+        protection,
+        description: description.to_string(),
+        created: now_secs(),
+    };
+    Ok((request, DecryptSession { nonce, secret }))
+}
+
+// Keeps a session in memory until its response is scanned (web server)
+pub fn remember_decrypt_session(session: DecryptSession) {
+    let mut pending = PENDING_DECRYPT_REQUESTS.lock();
+    pending.retain(|_, (_, created)| created.elapsed() < pending_ttl());
+    pending.insert(session.nonce.clone(), (session, Instant::now()));
+}
+
+pub fn has_pending_decrypt_request(nonce: &str) -> bool {
+    PENDING_DECRYPT_REQUESTS
+        .lock()
+        .get(nonce)
+        .map(|(_, created)| created.elapsed() < pending_ttl())
+        .unwrap_or(false)
+}
+
+// Online side: opens a response with the given session, or with the pending one for its nonce
+pub fn open_decrypt_response(payload: &str, session: Option<&DecryptSession>) -> Result<Zeroizing<String>> {
+    let response = parse_decrypt_response(payload)?;
+    let plaintext = match session {
+        Some(session) if session.nonce != response.nonce => {
+            return Err(AppError::InvalidInput("Response nonce does not match the decryption session.".to_string()));
+        }
+        Some(session) => session.open(&response.message)?,
+        None => {
+            let pending = PENDING_DECRYPT_REQUESTS.lock();
+            match pending.get(&response.nonce) {
+                Some((session, created)) if created.elapsed() < pending_ttl() => session.open(&response.message)?,
+                _ => {
+                    return Err(AppError::InvalidInput(format!(
+                        "No pending decryption request with nonce {} (not created here, already opened or expired).",
+                        response.nonce
+                    )))
+                }
+            }
+        }
+    };
+    if session.is_none() {
+        PENDING_DECRYPT_REQUESTS.lock().remove(&response.nonce);
+    }
+    into_text(plaintext)
+}
+
+// Offline side: decrypts and re-encrypts as requested. The plaintext only exists in memory.
+pub fn answer_decrypt_request(payload: &str) -> Result<String> {
+    let request = parse_decrypt_request(payload)?;
+    let plaintext = gpg_ops::decrypt_to_bytes(request.ciphertext.as_bytes())?;
+    let message = match &request.protection {
+        ResponseProtection::Passphrase { passphrase } => gpg_ops::encrypt_symmetric(&plaintext, passphrase)?,
+        ResponseProtection::EphemeralKey { public_key } => {
+            let home = EphemeralHome::create()?;
+            let key_file = home.path().join("one-time-key.asc");
+            std::fs::write(&key_file, public_key)?;
+            gpg_ops::encrypt_to_key_file(&plaintext, &key_file)?
+        }
+    };
+    DecryptResponse { version: DECRYPT_PROTOCOL_VERSION, nonce: request.nonce, message }.encode()
+}
+
+// Offline side, only when the operator explicitly asks to see the plaintext
+pub fn reveal_decrypt_request(payload: &str) -> Result<Zeroizing<String>> {
+    let request = parse_decrypt_request(payload)?;
+    into_text(gpg_ops::decrypt_to_bytes(request.ciphertext.as_bytes())?)
+}
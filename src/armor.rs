@@ -0,0 +1,176 @@
+use crate::error::{AppError, Result};
+use base64::{engine::general_purpose::STANDARD as Base64Engine, Engine as _};
+
+// Minimal OpenPGP ASCII armor reader (RFC 4880, section 6).
+// Used by the scan pipeline to look inside scanned data without handing it to GnuPG.
+
+#[derive(Debug, Clone)]
+pub struct ArmorBlock {
+    pub label: String,                  // e.g. "PUBLIC KEY BLOCK", "MESSAGE"
+    pub headers: Vec<(String, String)>, // armor headers such as Version, Comment, Hash
+    pub data: Vec<u8>,                  // dearmored binary packets
+    pub cleartext: Option<String>,      // only set for cleartext signed messages
+    pub checksum_valid: Option<bool>,   // None if the (optional) CRC24 line is absent
+}
+
+const BEGIN_PREFIX: &str = "-----BEGIN PGP ";
+const END_PREFIX: &str = "-----END PGP ";
+const DASHES: &str = "-----";
+
+// CRC24 as used by the armor checksum line (RFC 4880, section 6.1)
+const CRC24_INIT: u32 = 0xB704CE;
+const CRC24_POLY: u32 = 0x1864CFB;
+
+pub fn crc24(data: &[u8]) -> u32 {
+    let mut crc = CRC24_INIT;
+    for &byte in data {
+        crc ^= (byte as u32) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x1000000 != 0 {
+                crc ^= CRC24_POLY;
+            }
+        }
+    }
+    crc & 0xFFFFFF
+}
+
+// Finds every complete armored block in `text` and returns the slices.
+// BEGIN/END lines only count at the start of a line, so prose that merely quotes
+// an armor header (or a header with no matching END line) is not picked up.
+pub fn find_armor_blocks(text: &str) -> Vec<&str> {
+    let mut blocks = Vec::new();
+    let mut offset = 0;
+    let mut open: Option<(usize, String)> = None;
+
+    for line in text.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        let trimmed = line.trim_end();
+
+        if let Some(label) = armor_label(trimmed, BEGIN_PREFIX) {
+            match &open {
+                // The signature inside a cleartext signed message belongs to that message
+                Some((_, open_label)) if open_label == "SIGNED MESSAGE" && label == "SIGNATURE" => {}
+                _ => open = Some((line_start, label)),
+            }
+        } else if let Some(label) = armor_label(trimmed, END_PREFIX) {
+            if let Some((start, open_label)) = &open {
+                let expected_end = if open_label == "SIGNED MESSAGE" { "SIGNATURE" } else { open_label.as_str() };
+                if label == expected_end {
+                    blocks.push(&text[*start..line_start + trimmed.len()]);
+                    open = None;
+                }
+            }
+        }
+    }
+    blocks
+}
+
+fn armor_label(line: &str, prefix: &str) -> Option<String> {
+    line.trim_end()
+        .strip_prefix(prefix)?
+        .strip_suffix(DASHES)
+        .map(str::to_string)
+}
+
+// Parses the first armored block in `text`.
+// For cleartext signed messages the signed text and the following signature block are combined.
+pub fn parse_armor(text: &str) -> Result<ArmorBlock> {
+    let mut lines = text.lines();
+    let label = lines
+        .by_ref()
+        .find_map(|line| armor_label(line, BEGIN_PREFIX))
+        .ok_or_else(|| AppError::InvalidInput("No OpenPGP armor header found.".to_string()))?;
+
+    let headers = parse_headers(&mut lines)?;
+
+    if label == "SIGNED MESSAGE" {
+        // Cleartext framework: dash-escaped text, then a SIGNATURE armor block
+        let mut cleartext_lines = Vec::new();
+        let mut found_signature = false;
+        for line in lines.by_ref() {
+            if armor_label(line, BEGIN_PREFIX).as_deref() == Some("SIGNATURE") {
+                found_signature = true;
+                break;
+            }
+            cleartext_lines.push(line.strip_prefix("- ").unwrap_or(line));
+        }
+        if !found_signature {
+            return Err(AppError::InvalidInput("Signed message has no signature block.".to_string()));
+        }
+        let _signature_headers = parse_headers(&mut lines)?;
+        let (data, checksum_valid) = decode_body(&mut lines, "SIGNATURE")?;
+        return Ok(ArmorBlock {
+            label,
+            headers,
+            data,
+            cleartext: Some(cleartext_lines.join("\n")),
+            checksum_valid,
+        });
+    }
+
+    let (data, checksum_valid) = decode_body(&mut lines, &label)?;
+    Ok(ArmorBlock { label, headers, data, cleartext: None, checksum_valid })
+}
+
+fn parse_headers<'a, I: Iterator<Item = &'a str>>(lines: &mut I) -> Result<Vec<(String, String)>> {
+    let mut headers = Vec::new();
+    for line in lines {
+        let line = line.trim_end();
+        if line.is_empty() {
+            return Ok(headers);
+        }
+        match line.split_once(": ") {
+            Some((key, value)) => headers.push((key.to_string(), value.to_string())),
+            None => return Err(AppError::InvalidInput(format!("Malformed armor header line: {}", line))),
+        }
+    }
+    Err(AppError::InvalidInput("Armor ended inside the header section.".to_string()))
+}
+
+fn decode_body<'a, I: Iterator<Item = &'a str>>(lines: &mut I, label: &str) -> Result<(Vec<u8>, Option<bool>)> {
+    let mut body = String::new();
+    let mut checksum = None;
+    for line in lines {
+        let line = line.trim();
+        if let Some(end_label) = armor_label(line, END_PREFIX) {
+            if end_label != label {
+                return Err(AppError::InvalidInput(format!(
+                    "Armor END line '{}' does not match BEGIN '{}'.",
+                    end_label, label
+                )));
+            }
+            let data = Base64Engine.decode(body.as_bytes())?;
+            let checksum_valid = match checksum {
+                Some(encoded) => {
+                    let crc = Base64Engine.decode(encoded)?;
+                    let expected = crc24(&data);
+                    Some(crc.len() == 3 && crc == expected.to_be_bytes()[1..])
+                }
+                None => None,
+            };
+            return Ok((data, checksum_valid));
+        }
+        if let Some(encoded) = line.strip_prefix('=').filter(|c| c.len() == 4) {
+            checksum = Some(encoded); // CRC24 checksum line
+            continue;
+        }
+        body.push_str(line);
+    }
+    Err(AppError::InvalidInput(format!("Missing END line for armor block '{}'.", label)))
+}
+
+// Armors binary packets (64-column base64 plus CRC24 line), e.g. a key rebuilt from a paper backup
+pub fn encode_armor(label: &str, data: &[u8]) -> String {
+    let encoded = Base64Engine.encode(data);
+    let mut text = format!("{}{}{}\n\n", BEGIN_PREFIX, label, DASHES);
+    for chunk in encoded.as_bytes().chunks(64) {
+        text.push_str(std::str::from_utf8(chunk).unwrap_or_default());
+        text.push('\n');
+    }
+    text.push('=');
+    text.push_str(&Base64Engine.encode(&crc24(data).to_be_bytes()[1..]));
+    text.push_str(&format!("\n{}{}{}\n", END_PREFIX, label, DASHES));
+    text
+}
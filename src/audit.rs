@@ -0,0 +1,313 @@
+use crate::error::{AppError, Result};
+use crate::gpg_ops;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+// Tamper-evident audit log of GPG operations. Each entry is one JSON line holding the operation,
+// the keys involved, SHA-256 hashes of input and output (never the data itself), the time and
+// the client. Every entry includes the hash of the previous one, so changing or removing an
+// entry breaks the chain. Every CHECKPOINT_INTERVAL entries the chain head is signed with the
+// designated audit key and the signature becomes an entry itself.
+
+const AUDIT_LOG: &str = "secure-gpg-qr-audit.log";
+const CHECKPOINT_INTERVAL: u64 = 100;
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+// The last entry is searched for in this much of the end of the file
+const TAIL_BYTES: u64 = 64 * 1024;
+
+tokio::task_local! {
+    // Address of the web client whose request is being handled (set by web_server)
+    pub static CLIENT: String;
+}
+
+lazy_static::lazy_static! {
+    // Serialises appends so the chain stays linear within this process
+    static ref LOG_LOCK: Mutex<()> = Mutex::new(());
+    static ref SIGNING_KEY: Mutex<Option<String>> = Mutex::new(None);
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Checkpoint {
+    pub seq: u64,          // entry whose hash is signed
+    pub hash: String,      // its hash
+    pub signature: String, // detached signature over checkpoint_statement(seq, hash)
+}
+
+// Everything that is hashed; field order is fixed, so re-serialising gives the same bytes
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EntryBody {
+    pub seq: u64,
+    pub time: u64,
+    pub operation: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keys: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_sha256: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_sha256: Option<String>,
+    pub client: String,
+    pub outcome: String, // "ok" or the error
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checkpoint: Option<Checkpoint>,
+    pub prev: String, // hash of the previous entry
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Entry {
+    #[serde(flatten)]
+    pub body: EntryBody,
+    pub hash: String,
+}
+
+fn entry_hash(body: &EntryBody) -> Result<String> {
+    Ok(gpg_ops::sha256_hex(serde_json::to_string(body)?.as_bytes()))
+}
+
+fn checkpoint_statement(seq: u64, hash: &str) -> String {
+    format!("Secure-GPG-QR audit log checkpoint\nEntry: {}\nHash: {}\n", seq, hash)
+}
+
+fn log_path() -> Result<PathBuf> {
+    Ok(gpg_ops::effective_home_dir()?.join(AUDIT_LOG))
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// Key used for checkpoints; without one the log is only hash-chained
+pub fn set_signing_key(key_id: Option<String>) {
+    *SIGNING_KEY.lock() = key_id;
+}
+
+fn last_entry() -> Result<Option<Entry>> {
+    let path = log_path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let mut file = std::fs::File::open(&path)?;
+    let length = file.metadata()?.len();
+    file.seek(SeekFrom::Start(length.saturating_sub(TAIL_BYTES)))?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail)?;
+    let tail = String::from_utf8_lossy(&tail);
+    match tail.lines().rev().find(|line| !line.trim().is_empty()) {
+        Some(line) => serde_json::from_str(line)
+            .map(Some)
+            .map_err(|e| AppError::Operation(format!("Last audit log entry is unreadable: {}", e))),
+        None => Ok(None),
+    }
+}
+
+fn append(mut body: EntryBody) -> Result<Entry> {
+    let _guard = LOG_LOCK.lock();
+    let (seq, prev) = match last_entry()? {
+        Some(last) => (last.body.seq + 1, last.hash),
+        None => (1, GENESIS_HASH.to_string()),
+    };
+    body.seq = seq;
+    body.prev = prev;
+    let entry = Entry { hash: entry_hash(&body)?, body };
+    let mut log = std::fs::OpenOptions::new().create(true).append(true).open(log_path()?)?;
+    writeln!(log, "{}", serde_json::to_string(&entry)?)?;
+    Ok(entry)
+}
+
+fn current_client() -> String {
+    CLIENT.try_with(|client| client.clone()).unwrap_or_else(|_| "cli".to_string())
+}
+
+// Records one operation. `outcome` carries the output (hashed) or the error. Logging problems
+// are reported but never make the operation itself fail.
+pub fn record(operation: &str, keys: &[&str], input: Option<&[u8]>, outcome: std::result::Result<Option<&[u8]>, &AppError>) {
+    let body = EntryBody {
+        seq: 0,
+        time: now_secs(),
+        operation: operation.to_string(),
+        // Key IDs and user IDs are resolved, so the log always names fingerprints
+        keys: keys.iter().map(|key| gpg_ops::key_fingerprint(key).unwrap_or_else(|_| key.to_string())).collect(),
+        input_sha256: input.map(gpg_ops::sha256_hex),
+        output_sha256: outcome.as_ref().ok().copied().flatten().map(gpg_ops::sha256_hex),
+        client: current_client(),
+        outcome: match &outcome {
+            Ok(_) => "ok".to_string(),
+            Err(e) => e.to_string(),
+        },
+        checkpoint: None,
+        prev: String::new(),
+    };
+    match append(body) {
+        Ok(entry) if entry.body.seq % CHECKPOINT_INTERVAL == 0 && SIGNING_KEY.lock().is_some() => {
+            if let Err(e) = checkpoint() {
+                tracing::warn!("Audit log checkpoint failed: {}", e);
+            }
+        }
+        Ok(_) => {}
+        Err(e) => tracing::warn!("Could not write audit log entry for {}: {}", operation, e),
+    }
+}
+
+// Signs the current chain head with the designated key and appends the signature
+pub fn checkpoint() -> Result<Entry> {
+    let key = SIGNING_KEY
+        .lock()
+        .clone()
+        .ok_or_else(|| AppError::Config("No audit signing key is configured.".to_string()))?;
+    let head = last_entry()?.ok_or_else(|| AppError::InvalidInput("The audit log is empty.".to_string()))?;
+    let statement = checkpoint_statement(head.body.seq, &head.hash);
+    let signature = gpg_ops::sign_audit_checkpoint(statement.as_bytes(), &key)?;
+    append(EntryBody {
+        seq: 0,
+        time: now_secs(),
+        operation: "checkpoint".to_string(),
+        keys: vec![gpg_ops::key_fingerprint(&key)?],
+        input_sha256: None,
+        output_sha256: None,
+        client: current_client(),
+        outcome: "ok".to_string(),
+        checkpoint: Some(Checkpoint { seq: head.body.seq, hash: head.hash, signature }),
+        prev: String::new(),
+    })
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct VerifyReport {
+    pub first_seq: Option<u64>,
+    pub last_seq: Option<u64>,
+    pub checkpoints: usize,
+    pub last_checkpoint: Option<u64>, // last entry covered by a valid signature
+    pub signers: Vec<String>,
+    pub problems: Vec<String>,
+}
+
+impl VerifyReport {
+    pub fn is_intact(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    pub fn describe(&self) -> String {
+        let mut lines = vec![match (self.first_seq, self.last_seq) {
+            (Some(first), Some(last)) => format!("Entries {} to {}.", first, last),
+            _ => "The audit log is empty.".to_string(),
+        }];
+        lines.push(format!(
+            "{} checkpoint(s), signed by: {}",
+            self.checkpoints,
+            if self.signers.is_empty() { "-".to_string() } else { self.signers.join(", ") }
+        ));
+        match (self.last_checkpoint, self.last_seq) {
+            (Some(signed), Some(last)) if signed < last => lines.push(format!(
+                "Entries after {} are only protected by the hash chain (a cut-off end cannot be detected).",
+                signed
+            )),
+            (None, Some(_)) => lines.push("No signed checkpoint: the whole log could have been rewritten.".to_string()),
+            _ => {}
+        }
+        if self.is_intact() {
+            lines.push("Hash chain intact.".to_string());
+        } else {
+            lines.extend(self.problems.iter().map(|p| format!("Problem: {}", p)));
+        }
+        lines.join("\n")
+    }
+}
+
+// Verifies log text (the whole log or an export starting at any entry). With `signing_key`,
+// checkpoints must be signed by that key.
+pub fn verify_text(text: &str, signing_key: Option<&str>) -> Result<VerifyReport> {
+    let expected_signer = signing_key.map(gpg_ops::key_fingerprint).transpose()?;
+    let mut report = VerifyReport::default();
+    let mut hashes: HashMap<u64, String> = HashMap::new();
+    let mut previous: Option<(u64, String)> = None;
+    for (number, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let entry: Entry = match serde_json::from_str(line) {
+            Ok(entry) => entry,
+            Err(e) => {
+                report.problems.push(format!("Line {}: unreadable entry ({}).", number + 1, e));
+                previous = None;
+                continue;
+            }
+        };
+        let seq = entry.body.seq;
+        report.first_seq.get_or_insert(seq);
+        report.last_seq = Some(seq);
+        if entry_hash(&entry.body)? != entry.hash {
+            report.problems.push(format!("Entry {}: content does not match its hash (modified).", seq));
+        }
+        match &previous {
+            Some((prev_seq, prev_hash)) => {
+                if seq != prev_seq + 1 {
+                    report.problems.push(format!("Entry {}: follows entry {} (entries missing or reordered).", seq, prev_seq));
+                }
+                if entry.body.prev != *prev_hash {
+                    report.problems.push(format!("Entry {}: does not chain to the entry before it.", seq));
+                }
+            }
+            None if report.first_seq == Some(seq) && seq == 1 && entry.body.prev != GENESIS_HASH => {
+                report.problems.push("Entry 1: does not start the chain.".to_string());
+            }
+            None => {}
+        }
+        if let Some(checkpoint) = &entry.body.checkpoint {
+            report.checkpoints += 1;
+            match hashes.get(&checkpoint.seq) {
+                Some(hash) if *hash == checkpoint.hash => {}
+                Some(_) => report.problems.push(format!("Checkpoint {}: signed hash of entry {} differs.", seq, checkpoint.seq)),
+                // The signed entry is before the start of an export: its hash is taken as given
+                None => {}
+            }
+            let statement = checkpoint_statement(checkpoint.seq, &checkpoint.hash);
+            let check = gpg_ops::check_signature(checkpoint.signature.as_bytes(), Some(statement.as_bytes()))?;
+            let signer = check.primary_fingerprint.clone().unwrap_or_else(|| "unknown key".to_string());
+            if !check.valid {
+                report.problems.push(format!("Checkpoint {}: {}", seq, check.status));
+            } else if expected_signer.as_ref().is_some_and(|expected| *expected != signer) {
+                report.problems.push(format!("Checkpoint {}: signed by {} instead of the audit key.", seq, signer));
+            } else {
+                report.last_checkpoint = Some(checkpoint.seq);
+                if !report.signers.contains(&signer) {
+                    report.signers.push(signer);
+                }
+            }
+        }
+        hashes.insert(seq, entry.hash.clone());
+        previous = Some((seq, entry.hash));
+    }
+    Ok(report)
+}
+
+pub fn verify_log(signing_key: Option<&str>) -> Result<VerifyReport> {
+    let path = log_path()?;
+    if !path.exists() {
+        return Ok(VerifyReport::default());
+    }
+    verify_text(&std::fs::read_to_string(path)?, signing_key)
+}
+
+// Entries from `since` on, as stored; an export can be verified on its own with verify_text
+pub fn export(since: u64) -> Result<String> {
+    let path = log_path()?;
+    if !path.exists() {
+        return Err(AppError::InvalidInput("The audit log is empty.".to_string()));
+    }
+    let text = std::fs::read_to_string(path)?;
+    let lines: Vec<&str> = text
+        .lines()
+        .filter(|line| {
+            serde_json::from_str::<EntryBody>(line)
+                .map(|body| body.seq >= since)
+                .unwrap_or(true) // damaged lines stay in, so verification reports them
+        })
+        .collect();
+    if lines.is_empty() {
+        return Err(AppError::InvalidInput(format!("No audit log entries from {} on.", since)));
+    }
+    Ok(lines.join("\n") + "\n")
+}
@@ -0,0 +1,247 @@
+use crate::audit;
+use crate::classify::{self, PayloadKind};
+use crate::error::{AppError, Result};
+use crate::gpg_cli;
+use crate::gpg_ops::{self, ImportOptions, ImportReport};
+use crate::policy::{self, Operation};
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+use zeroize::{Zeroize, Zeroizing};
+
+// Full keyring backup: public keys, secret keys, ownertrust and stored revocation
+// certificates of one profile in a single passphrase-encrypted archive, e.g. to
+// rebuild an air-gapped signer after a hardware failure.
+
+pub const BACKUP_VERSION: u32 = 1;
+pub const BACKUP_COMMENT: &str = "Secure-GPG-QR keyring backup";
+
+// Describes what the archive must contain; checked before anything is restored
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BackupManifest {
+    pub version: u32,
+    pub created: u64,                  // seconds since the epoch
+    pub public_keys: Vec<String>,      // fingerprints
+    pub secret_keys: Vec<String>,      // fingerprints of keys with secret material
+    pub ownertrust_entries: usize,
+    pub revocation_certs: Vec<String>, // fingerprints with a stored revocation certificate
+}
+
+impl BackupManifest {
+    pub fn describe(&self) -> String {
+        format!(
+            "Backup v{} from {}: {} public key(s), {} secret key(s), {} ownertrust entries, {} revocation certificate(s).",
+            self.version,
+            gpg_ops::format_date(self.created),
+            self.public_keys.len(),
+            self.secret_keys.len(),
+            self.ownertrust_entries,
+            self.revocation_certs.len()
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct BackupArchive {
+    manifest: BackupManifest,
+    public_keys: String,
+    secret_keys: String,
+    ownertrust: String,
+    revocation_certs: Vec<(String, String)>, // (fingerprint, armored certificate)
+}
+
+impl Drop for BackupArchive {
+    fn drop(&mut self) {
+        self.secret_keys.zeroize();
+    }
+}
+
+fn export_all(args: &[&str]) -> Result<String> {
+    let output = gpg_cli::run_gpg(args, None)?;
+    String::from_utf8(output.stdout).map_err(|e| AppError::Operation(format!("UTF8 Error: {}", e)))
+}
+
+// Marks the armored archive so scans recognise it (armor headers are outside the CRC)
+fn add_armor_comment(armored: &str) -> String {
+    match armored.split_once('\n') {
+        Some((begin, rest)) => format!("{}\nComment: {}\n{}", begin, BACKUP_COMMENT, rest),
+        None => armored.to_string(),
+    }
+}
+
+pub fn is_backup_archive(armor_headers: &[(String, String)]) -> bool {
+    armor_headers.iter().any(|(name, value)| name == "Comment" && value == BACKUP_COMMENT)
+}
+
+// Creates the encrypted archive; returns it armored together with its manifest
+pub fn create_backup(passphrase: &str) -> Result<(String, BackupManifest)> {
+    policy::check(Operation::ExportSecret)?;
+    if passphrase.chars().count() < 12 {
+        return Err(AppError::InvalidInput("Backup passphrase must have at least 12 characters.".to_string()));
+    }
+    let public_keys = export_all(&["--armor", "--export", "--export-options", "backup"])?;
+    let secret_keys = Zeroizing::new(export_all(&["--armor", "--export-secret-keys", "--export-options", "backup"])?);
+    let public = gpg_ops::preview_keys(public_keys.as_bytes())?;
+    if public.is_empty() {
+        return Err(AppError::InvalidInput("The keyring is empty, nothing to back up.".to_string()));
+    }
+    let secret: Vec<String> = if secret_keys.trim().is_empty() {
+        Vec::new()
+    } else {
+        gpg_ops::preview_keys(secret_keys.as_bytes())?
+            .into_iter()
+            .filter(|key| key.has_secret)
+            .map(|key| key.fingerprint)
+            .collect()
+    };
+    let ownertrust = gpg_ops::export_ownertrust()?;
+    let revocation_certs: Vec<(String, String)> = secret
+        .iter()
+        .filter_map(|fpr| gpg_ops::stored_revocation_cert(fpr).ok().map(|cert| (fpr.clone(), cert)))
+        .collect();
+
+    let manifest = BackupManifest {
+        version: BACKUP_VERSION,
+        created: SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        public_keys: public.into_iter().map(|key| key.fingerprint).collect(),
+        secret_keys: secret,
+        ownertrust_entries: gpg_ops::ownertrust_entry_count(&ownertrust),
+        revocation_certs: revocation_certs.iter().map(|(fpr, _)| fpr.clone()).collect(),
+    };
+    let archive = BackupArchive {
+        manifest: manifest.clone(),
+        public_keys,
+        secret_keys: secret_keys.to_string(),
+        ownertrust,
+        revocation_certs,
+    };
+    let plaintext = Zeroizing::new(serde_json::to_vec(&archive)?);
+    let encrypted = gpg_ops::encrypt_symmetric(&plaintext, passphrase)?;
+    let secret_fprs: Vec<&str> = manifest.secret_keys.iter().map(String::as_str).collect();
+    audit::record("backup", &secret_fprs, None, Ok(Some(encrypted.as_bytes())));
+    Ok((add_armor_comment(&encrypted), manifest))
+}
+
+#[derive(Serialize, Debug)]
+pub struct RestoreReport {
+    pub manifest: BackupManifest,
+    pub problems: Vec<String>,        // completeness check failures; nothing is restored if any
+    pub restored: bool,
+    pub import: Option<ImportReport>,
+    pub ownertrust: Option<String>,
+    pub revocation_certs_stored: usize,
+}
+
+// Compares the archive contents with its manifest
+fn validate(archive: &BackupArchive) -> Result<Vec<String>> {
+    let manifest = &archive.manifest;
+    let mut problems = Vec::new();
+    if manifest.version != BACKUP_VERSION {
+        problems.push(format!("Unsupported backup version {}.", manifest.version));
+        return Ok(problems);
+    }
+
+    let public: Vec<String> = gpg_ops::preview_keys(archive.public_keys.as_bytes())?
+        .into_iter()
+        .map(|key| key.fingerprint)
+        .collect();
+    let secret: Vec<String> = if archive.secret_keys.trim().is_empty() {
+        Vec::new()
+    } else {
+        gpg_ops::preview_keys(archive.secret_keys.as_bytes())?
+            .into_iter()
+            .filter(|key| key.has_secret)
+            .map(|key| key.fingerprint)
+            .collect()
+    };
+    for (label, expected, found) in [("public", &manifest.public_keys, &public), ("secret", &manifest.secret_keys, &secret)] {
+        for fpr in expected.iter().filter(|fpr| !found.contains(fpr)) {
+            problems.push(format!("Missing {} key {}.", label, fpr));
+        }
+        for fpr in found.iter().filter(|fpr| !expected.contains(fpr)) {
+            problems.push(format!("Unlisted {} key {} in archive.", label, fpr));
+        }
+    }
+    for fpr in secret.iter().filter(|fpr| !public.contains(fpr)) {
+        problems.push(format!("Secret key {} has no public key in the archive.", fpr));
+    }
+
+    let entries = gpg_ops::ownertrust_entry_count(&archive.ownertrust);
+    if entries != manifest.ownertrust_entries {
+        problems.push(format!(
+            "Ownertrust has {} entries, manifest lists {}.",
+            entries, manifest.ownertrust_entries
+        ));
+    }
+
+    for fpr in &manifest.revocation_certs {
+        match archive.revocation_certs.iter().find(|(cert_fpr, _)| cert_fpr == fpr) {
+            None => problems.push(format!("Missing revocation certificate for {}.", fpr)),
+            Some((_, cert)) => {
+                let valid = classify::classify(cert).iter().any(|block| {
+                    block.kind == PayloadKind::RevocationCertificate
+                        && block.packets.first().and_then(classify::signature_issuer).as_deref() == Some(fpr.as_str())
+                });
+                if !valid {
+                    problems.push(format!("Revocation certificate for {} is invalid.", fpr));
+                }
+            }
+        }
+    }
+    for (fpr, _) in archive.revocation_certs.iter().filter(|(fpr, _)| !manifest.revocation_certs.contains(fpr)) {
+        problems.push(format!("Unlisted revocation certificate {:?} in archive.", fpr));
+    }
+    Ok(problems)
+}
+
+// Decrypts and checks an archive; unless `validate_only`, restores it into the current profile.
+// Revocation certificates are stored, never applied.
+pub fn restore_backup(armored: &str, passphrase: &str, validate_only: bool) -> Result<RestoreReport> {
+    let plaintext = gpg_ops::decrypt_symmetric(armored.as_bytes(), passphrase)?;
+    let archive: BackupArchive = serde_json::from_slice(&plaintext)
+        .map_err(|e| AppError::InvalidInput(format!("Not a keyring backup archive: {}", e)))?;
+    let problems = validate(&archive)?;
+    let mut report = RestoreReport {
+        manifest: archive.manifest.clone(),
+        problems,
+        restored: false,
+        import: None,
+        ownertrust: None,
+        revocation_certs_stored: 0,
+    };
+    if validate_only || !report.problems.is_empty() {
+        return Ok(report);
+    }
+
+    let options = ImportOptions { restore: true, ..Default::default() };
+    let mut import = gpg_ops::import_keys(&archive.public_keys, &options)?;
+    if !archive.secret_keys.trim().is_empty() {
+        let secret_import = gpg_ops::import_keys(&archive.secret_keys, &options)?;
+        for status in secret_import.keys {
+            match import.keys.iter_mut().find(|k| k.fingerprint == status.fingerprint) {
+                Some(existing) => {
+                    existing.changes.extend(status.changes);
+                    existing.error = existing.error.take().or(status.error);
+                }
+                None => import.keys.push(status),
+            }
+        }
+    }
+    report.import = Some(import);
+    if report.manifest.ownertrust_entries > 0 {
+        report.ownertrust = Some(gpg_ops::import_ownertrust(&archive.ownertrust)?);
+    }
+    // Only the certificates validate() checked against the manifest
+    for fpr in &report.manifest.revocation_certs {
+        let Some((_, cert)) = archive.revocation_certs.iter().find(|(cert_fpr, _)| cert_fpr == fpr) else {
+            continue;
+        };
+        if gpg_ops::store_revocation_cert(fpr, cert)? {
+            report.revocation_certs_stored += 1;
+        }
+    }
+    report.restored = true;
+    Ok(report)
+}
@@ -0,0 +1,407 @@
+use crate::armor;
+use crate::qr_utils;
+use serde::Serialize;
+
+// OpenPGP payload classification for scanned data.
+// Finds every armored block embedded in surrounding text, checks the armor CRC24,
+// identifies the packets inside and recognises openpgp4fpr: URIs and raw binary packets.
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadKind {
+    PublicKey,
+    SecretKey,
+    EncryptedMessage,
+    SignedMessage,       // inline signed (one-pass signature + literal data)
+    CleartextSigned,     // -----BEGIN PGP SIGNED MESSAGE-----
+    DetachedSignature,
+    RevocationCertificate, // standalone key revocation signature (type 0x20)
+    FingerprintUri,      // OPENPGP4FPR:<fingerprint>
+    Invalid,             // looks like OpenPGP but fails validation
+    PlainText,
+}
+
+impl PayloadKind {
+    pub fn description(self) -> &'static str {
+        match self {
+            PayloadKind::PublicKey => "PGP Public Key",
+            PayloadKind::SecretKey => "PGP Private Key",
+            PayloadKind::EncryptedMessage => "PGP Encrypted Message",
+            PayloadKind::SignedMessage => "PGP Signed Message",
+            PayloadKind::CleartextSigned => "PGP Signed Message",
+            PayloadKind::DetachedSignature => "PGP Detached Signature",
+            PayloadKind::RevocationCertificate => "PGP Revocation Certificate",
+            PayloadKind::FingerprintUri => "OpenPGP Fingerprint (openpgp4fpr)",
+            PayloadKind::Invalid => "Invalid / Corrupted OpenPGP Data",
+            PayloadKind::PlainText => "Unknown / Plain Text",
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PacketType {
+    PublicKeyEncryptedSessionKey,
+    Signature,
+    SymmetricKeyEncryptedSessionKey,
+    OnePassSignature,
+    SecretKey,
+    PublicKey,
+    SecretSubkey,
+    CompressedData,
+    SymmetricallyEncryptedData,
+    Marker,
+    LiteralData,
+    Trust,
+    UserId,
+    PublicSubkey,
+    UserAttribute,
+    SymEncryptedIntegrityProtectedData,
+    ModificationDetectionCode,
+    AeadEncryptedData,
+    Padding,
+    Unknown,
+}
+
+impl PacketType {
+    pub fn from_tag(tag: u8) -> Self {
+        match tag {
+            1 => PacketType::PublicKeyEncryptedSessionKey,
+            2 => PacketType::Signature,
+            3 => PacketType::SymmetricKeyEncryptedSessionKey,
+            4 => PacketType::OnePassSignature,
+            5 => PacketType::SecretKey,
+            6 => PacketType::PublicKey,
+            7 => PacketType::SecretSubkey,
+            8 => PacketType::CompressedData,
+            9 => PacketType::SymmetricallyEncryptedData,
+            10 => PacketType::Marker,
+            11 => PacketType::LiteralData,
+            12 => PacketType::Trust,
+            13 => PacketType::UserId,
+            14 => PacketType::PublicSubkey,
+            17 => PacketType::UserAttribute,
+            18 => PacketType::SymEncryptedIntegrityProtectedData,
+            19 => PacketType::ModificationDetectionCode,
+            20 => PacketType::AeadEncryptedData,
+            21 => PacketType::Padding,
+            _ => PacketType::Unknown,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PacketInfo {
+    pub tag: u8,
+    pub packet_type: PacketType,
+    pub length: Option<usize>, // None for partial/indeterminate lengths
+    #[serde(skip)]
+    pub header_len: usize,
+    #[serde(skip)]
+    pub body: Vec<u8>,         // complete body, or the first chunk for partial lengths
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ClassifiedBlock {
+    pub kind: PayloadKind,
+    pub armor_label: Option<String>,
+    pub armor_headers: Vec<(String, String)>,
+    pub checksum_valid: Option<bool>,
+    pub packets: Vec<PacketInfo>,
+    pub fingerprint: Option<String>, // only for openpgp4fpr URIs
+    pub notes: Vec<String>,
+    #[serde(skip)]
+    pub text: String,                // the block itself, ready for GnuPG
+}
+
+impl ClassifiedBlock {
+    fn new(kind: PayloadKind, text: String) -> Self {
+        ClassifiedBlock {
+            kind,
+            armor_label: None,
+            armor_headers: Vec::new(),
+            checksum_valid: None,
+            packets: Vec::new(),
+            fingerprint: None,
+            notes: Vec::new(),
+            text,
+        }
+    }
+}
+
+// Classifies a scanned payload. Returns one entry per OpenPGP block found;
+// text without any OpenPGP content yields a single PlainText entry.
+pub fn classify(data: &str) -> Vec<ClassifiedBlock> {
+    if let Some(fingerprint) = parse_openpgp4fpr(data) {
+        let mut block = ClassifiedBlock::new(PayloadKind::FingerprintUri, data.trim().to_string());
+        block.fingerprint = Some(fingerprint);
+        return vec![block];
+    }
+
+    let blocks: Vec<ClassifiedBlock> = armor::find_armor_blocks(data)
+        .into_iter()
+        .map(classify_armored)
+        .collect();
+    if !blocks.is_empty() {
+        return blocks;
+    }
+
+    // Binary QR payloads carry raw packets rather than armor; the decoder hands them
+    // over as Latin-1 text. They are armored here so actions get what gpg expects.
+    if let Some(bytes) = qr_utils::latin1_bytes(data) {
+        let packets = parse_packets(&bytes);
+        if let Some(kind) = kind_from_packets(&packets).filter(|_| covers_input(&packets, bytes.len())) {
+            let mut block = ClassifiedBlock::new(kind, armor::encode_armor(armor_label_for(kind), &bytes));
+            block.packets = packets;
+            block.notes.push("Binary (unarmored) OpenPGP packets.".to_string());
+            return vec![block];
+        }
+    }
+
+    let mut block = ClassifiedBlock::new(PayloadKind::PlainText, data.to_string());
+    if data.contains("-----BEGIN PGP ") {
+        block.notes.push("Text mentions an armor header but contains no complete armored block.".to_string());
+    }
+    vec![block]
+}
+
+fn classify_armored(text: &str) -> ClassifiedBlock {
+    let mut block = ClassifiedBlock::new(PayloadKind::Invalid, text.to_string());
+    let parsed = match armor::parse_armor(text) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            block.notes.push(format!("Armor could not be decoded: {}", e));
+            return block;
+        }
+    };
+    block.armor_label = Some(parsed.label.clone());
+    block.armor_headers = parsed.headers;
+    block.checksum_valid = parsed.checksum_valid;
+    block.packets = parse_packets(&parsed.data);
+
+    if parsed.checksum_valid == Some(false) {
+        block.notes.push("Armor CRC24 checksum mismatch: data is corrupted.".to_string());
+        return block;
+    }
+    if block.packets.is_empty() {
+        block.notes.push("Armor contains no OpenPGP packets.".to_string());
+        return block;
+    }
+
+    let from_label = match parsed.label.as_str() {
+        "PUBLIC KEY BLOCK" => Some(PayloadKind::PublicKey),
+        "PRIVATE KEY BLOCK" | "SECRET KEY BLOCK" => Some(PayloadKind::SecretKey),
+        "MESSAGE" => None, // encrypted or signed, decided by the packets
+        "SIGNED MESSAGE" => Some(PayloadKind::CleartextSigned),
+        "SIGNATURE" => Some(PayloadKind::DetachedSignature),
+        other => {
+            block.notes.push(format!("Unknown armor type: {}", other));
+            return block;
+        }
+    };
+    let from_packets = kind_from_packets(&block.packets);
+
+    block.kind = match (from_label, from_packets) {
+        (Some(PayloadKind::CleartextSigned), Some(PayloadKind::DetachedSignature)) => PayloadKind::CleartextSigned,
+        // gpg armors revocation certificates as public key blocks
+        (Some(PayloadKind::PublicKey), Some(PayloadKind::RevocationCertificate)) => PayloadKind::RevocationCertificate,
+        (Some(label_kind), Some(packet_kind)) if label_kind == packet_kind => label_kind,
+        (None, Some(kind @ (PayloadKind::EncryptedMessage | PayloadKind::SignedMessage))) => kind,
+        (_, packet_kind) => {
+            block.notes.push(format!(
+                "Armor label '{}' does not match its contents ({}).",
+                parsed.label,
+                packet_kind.map(PayloadKind::description).unwrap_or("unrecognised packets")
+            ));
+            PayloadKind::Invalid
+        }
+    };
+    block
+}
+
+// Decides what a packet sequence is from its leading packets
+fn kind_from_packets(packets: &[PacketInfo]) -> Option<PayloadKind> {
+    let first = packets.iter().find(|p| p.packet_type != PacketType::Marker)?;
+    match first.packet_type {
+        PacketType::PublicKey => Some(PayloadKind::PublicKey),
+        PacketType::SecretKey => Some(PayloadKind::SecretKey),
+        PacketType::PublicKeyEncryptedSessionKey
+        | PacketType::SymmetricKeyEncryptedSessionKey
+        | PacketType::SymmetricallyEncryptedData
+        | PacketType::SymEncryptedIntegrityProtectedData
+        | PacketType::AeadEncryptedData => Some(PayloadKind::EncryptedMessage),
+        PacketType::OnePassSignature | PacketType::CompressedData | PacketType::LiteralData => {
+            Some(PayloadKind::SignedMessage)
+        }
+        PacketType::Signature => {
+            if packets.iter().any(|p| p.packet_type == PacketType::LiteralData) {
+                Some(PayloadKind::SignedMessage)
+            } else if signature_type(first) == Some(SIG_KEY_REVOCATION) {
+                Some(PayloadKind::RevocationCertificate)
+            } else {
+                Some(PayloadKind::DetachedSignature)
+            }
+        }
+        _ => None,
+    }
+}
+
+fn armor_label_for(kind: PayloadKind) -> &'static str {
+    match kind {
+        PayloadKind::SecretKey => "PRIVATE KEY BLOCK",
+        PayloadKind::PublicKey | PayloadKind::RevocationCertificate => "PUBLIC KEY BLOCK", // as gpg armors them
+        PayloadKind::DetachedSignature => "SIGNATURE",
+        _ => "MESSAGE",
+    }
+}
+
+fn covers_input(packets: &[PacketInfo], input_len: usize) -> bool {
+    // Every packet must have a definite length and together they must span the whole input
+    let mut consumed = 0;
+    for packet in packets {
+        match packet.length {
+            Some(len) => consumed += packet.header_len + len,
+            None => return false,
+        }
+    }
+    consumed == input_len
+}
+
+// Parses consecutive OpenPGP packet headers (old and new format), stopping at anything malformed.
+// Partial-length packets are reported with their first chunk and end the walk.
+pub fn parse_packets(mut data: &[u8]) -> Vec<PacketInfo> {
+    let mut packets = Vec::new();
+    while let Some(&first) = data.first() {
+        if first & 0x80 == 0 {
+            break;
+        }
+        let (tag, header_len, body_len, partial) = if first & 0x40 != 0 {
+            // New format header
+            let tag = first & 0x3f;
+            match data.get(1..) {
+                Some([o1, ..]) if *o1 < 192 => (tag, 2, *o1 as usize, false),
+                Some([o1, o2, ..]) if *o1 < 224 => (tag, 3, ((*o1 as usize - 192) << 8) + *o2 as usize + 192, false),
+                Some([255, b @ ..]) if b.len() >= 4 => {
+                    (tag, 6, u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize, false)
+                }
+                Some([o1, ..]) if *o1 < 255 => (tag, 2, 1usize << (o1 & 0x1f), true),
+                _ => break,
+            }
+        } else {
+            // Old format header
+            let tag = (first >> 2) & 0x0f;
+            match (first & 0x03, data.get(1..)) {
+                (0, Some([l, ..])) => (tag, 2, *l as usize, false),
+                (1, Some([a, b, ..])) => (tag, 3, u16::from_be_bytes([*a, *b]) as usize, false),
+                (2, Some([a, b, c, d, ..])) => (tag, 5, u32::from_be_bytes([*a, *b, *c, *d]) as usize, false),
+                (3, Some(rest)) => (tag, 1, rest.len(), true), // indeterminate: runs to the end
+                _ => break,
+            }
+        };
+        let Some(body) = data.get(header_len..header_len + body_len) else {
+            break; // truncated packet
+        };
+        packets.push(PacketInfo {
+            tag,
+            packet_type: PacketType::from_tag(tag),
+            length: if partial { None } else { Some(body_len) },
+            header_len,
+            body: body.to_vec(),
+        });
+        if partial {
+            break;
+        }
+        data = &data[header_len + body_len..];
+    }
+    packets
+}
+
+// Key ID of a version 3 public-key encrypted session key packet
+pub fn pkesk_key_id(packet: &PacketInfo) -> Option<String> {
+    match (packet.packet_type, packet.body.as_slice()) {
+        (PacketType::PublicKeyEncryptedSessionKey, [3, key_id @ ..]) if key_id.len() >= 8 => {
+            Some(to_hex(&key_id[..8]))
+        }
+        _ => None,
+    }
+}
+
+const SIG_KEY_REVOCATION: u8 = 0x20;
+
+// Signature type of a v3/v4/v6 signature packet
+pub fn signature_type(packet: &PacketInfo) -> Option<u8> {
+    match (packet.packet_type, packet.body.as_slice()) {
+        (PacketType::Signature, [3, _, sig_type, ..]) => Some(*sig_type),
+        (PacketType::Signature, [4 | 6, sig_type, ..]) => Some(*sig_type),
+        _ => None,
+    }
+}
+
+// Issuer of a v4 signature: the issuer fingerprint subpacket if present, else the issuer key ID.
+// Both the hashed and unhashed subpacket areas are searched.
+pub fn signature_issuer(packet: &PacketInfo) -> Option<String> {
+    let body = match (packet.packet_type, packet.body.as_slice()) {
+        (PacketType::Signature, body @ [4, ..]) => body,
+        _ => return None,
+    };
+    let hashed_len = u16::from_be_bytes([*body.get(4)?, *body.get(5)?]) as usize;
+    let hashed = body.get(6..6 + hashed_len)?;
+    let unhashed_start = 6 + hashed_len;
+    let unhashed_len = u16::from_be_bytes([*body.get(unhashed_start)?, *body.get(unhashed_start + 1)?]) as usize;
+    let unhashed = body.get(unhashed_start + 2..unhashed_start + 2 + unhashed_len)?;
+
+    let mut key_id = None;
+    for area in [hashed, unhashed] {
+        for (subpacket_type, data) in subpackets(area) {
+            match (subpacket_type, data) {
+                (33, [4, fpr @ ..]) if fpr.len() == 20 => return Some(to_hex(fpr)),
+                (16, id) if id.len() == 8 => key_id = key_id.or_else(|| Some(to_hex(id))),
+                _ => {}
+            }
+        }
+    }
+    key_id
+}
+
+// Iterates (type, data) over a signature subpacket area, stopping at anything malformed
+fn subpackets(mut area: &[u8]) -> Vec<(u8, &[u8])> {
+    let mut result = Vec::new();
+    while let Some(&first) = area.first() {
+        let (len_size, len) = match first {
+            0..=191 => (1, first as usize),
+            192..=254 => match area.get(1) {
+                Some(&second) => (2, ((first as usize - 192) << 8) + second as usize + 192),
+                None => break,
+            },
+            255 => match area.get(1..5) {
+                Some(b) => (5, u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize),
+                None => break,
+            },
+        };
+        let Some(subpacket) = area.get(len_size..len_size + len).filter(|s| !s.is_empty()) else {
+            break;
+        };
+        result.push((subpacket[0] & 0x7f, &subpacket[1..])); // high bit is the "critical" flag
+        area = &area[len_size + len..];
+    }
+    result
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+// Parses an `OPENPGP4FPR:<fingerprint>` URI (scheme is case-insensitive, as used by OpenKeychain).
+// Accepts v4 (40 hex digits) and v6 (64 hex digits) fingerprints; returns them upper-cased.
+pub fn parse_openpgp4fpr(data: &str) -> Option<String> {
+    let data = data.trim();
+    let scheme_len = "openpgp4fpr:".len();
+    // get() rather than slicing: byte 12 may fall inside a multibyte character
+    if !data.get(..scheme_len).is_some_and(|scheme| scheme.eq_ignore_ascii_case("openpgp4fpr:")) {
+        return None;
+    }
+    // OpenKeychain may append parameters (e.g. #a=<address>)
+    let fingerprint = data[scheme_len..].split(['#', '?']).next().unwrap_or("");
+    let valid = matches!(fingerprint.len(), 40 | 64) && fingerprint.chars().all(|c| c.is_ascii_hexdigit());
+    valid.then(|| fingerprint.to_ascii_uppercase())
+}
@@ -1,10 +1,57 @@
 use clap::Parser;
+use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct CliArgs {
     #[command(subcommand)]
     pub command: Commands,
+    /// Configuration file (default: $XDG_CONFIG_HOME/secure-gpg-qr/config.toml if present)
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+    /// Configuration profile whose GPG settings to use
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+    /// Operation policy file (TOML): disabled operations, allowed signing/decryption keys
+    #[arg(long, global = true)]
+    pub policy: Option<PathBuf>,
+    /// Refuse every operation that changes the keyring or trust database
+    #[arg(long, global = true)]
+    pub read_only: bool,
+    #[command(flatten)]
+    pub qr: QrArgs,
+}
+
+// QR code parameters, overriding the [qr] table of the config file
+#[derive(clap::Args, Debug)]
+pub struct QrArgs {
+    /// QR error correction level (High for printed codes, Low for large screen transfers)
+    #[arg(long, global = true, value_enum)]
+    pub qr_ecc: Option<QrEccArg>,
+    /// Smallest QR version (1-40)
+    #[arg(long, global = true)]
+    pub qr_min_version: Option<u8>,
+    /// Largest QR version (1-40); larger data is split into more frames
+    #[arg(long, global = true)]
+    pub qr_max_version: Option<u8>,
+    /// QR mask pattern (0-7, default: chosen automatically)
+    #[arg(long, global = true)]
+    pub qr_mask: Option<u8>,
+    /// Quiet zone around QR codes, in modules
+    #[arg(long, global = true)]
+    pub qr_border: Option<i32>,
+    /// Size of a QR module in px
+    #[arg(long, global = true)]
+    pub qr_module_size: Option<i32>,
+    /// QR foreground color (#RGB or #RRGGBB)
+    #[arg(long, global = true)]
+    pub qr_fg: Option<String>,
+    /// QR background color (#RGB or #RRGGBB)
+    #[arg(long, global = true)]
+    pub qr_bg: Option<String>,
+    /// Put armored OpenPGP data into QR codes as compact base45 or binary (re-armored when scanned)
+    #[arg(long, global = true, value_enum)]
+    pub qr_compact: Option<QrCompactArg>,
 }
 
 #[derive(clap::Subcommand, Debug)]
@@ -15,8 +62,544 @@ pub enum Commands {
         #[arg(short, long)]
         port: Option<u16>,
         /// IP address to bind to (default: 127.0.0.1)
-        #[arg(short, long, default_value = "127.0.0.1")]
-        bind: String,
+        #[arg(short, long)]
+        bind: Option<String>,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+        /// Key that signs audit log checkpoints (every 100 entries)
+        #[arg(long)]
+        audit_key: Option<String>,
+        /// Serve HTTPS with this PEM certificate chain
+        #[arg(long, requires = "tls_key")]
+        tls_cert: Option<PathBuf>,
+        /// PEM private key for --tls-cert
+        #[arg(long, requires = "tls_cert")]
+        tls_key: Option<PathBuf>,
+    },
+    /// Decode QR codes from PNG/JPEG images (files or folders of photos) and classify the data
+    ScanImage {
+        /// Image files or folders containing a frame sequence
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Print only the decoded data, without type information
+        #[arg(long)]
+        raw: bool,
+        /// GPG Home directory override (used to check keys and recipients)
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Certify (sign) another key, e.g. after verifying its fingerprint in person
+    Certify {
+        /// Key to certify (fingerprint recommended)
+        key_id: String,
+        /// Certifying secret key (default: the configured signer, else gpg's default key)
+        #[arg(long)]
+        signer: Option<String>,
+        /// User ID to certify (repeatable, default: all user IDs)
+        #[arg(long = "uid")]
+        user_ids: Vec<String>,
+        /// Create a local (non-exportable) signature
+        #[arg(long)]
+        local: bool,
+        /// Signature expiry, e.g. 0, 30d, 1y
+        #[arg(long, default_value = "0")]
+        expires: String,
+        /// Make a trust signature with this trust level
+        #[arg(long, value_enum)]
+        trust: Option<TrustLevelArg>,
+        /// Trust signature depth
+        #[arg(long, default_value_t = 1)]
+        trust_depth: u8,
+        /// Restrict a trust signature to user IDs in this mail domain
+        #[arg(long)]
+        trust_domain: Option<String>,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// List the certifications on each user ID of a key
+    Certifications {
+        key_id: String,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Show ownertrust and per-UID validity of all keys, and the trust model
+    ListTrust {
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Set the ownertrust of a key (undefined, never, marginal, full, ultimate)
+    SetOwnertrust {
+        key_id: String,
+        trust: String,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Print the ownertrust database (gpg --export-ownertrust format)
+    ExportOwnertrust {
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Import an ownertrust database from a file
+    ImportOwnertrust {
+        file: PathBuf,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Show or set the trust model of the profile (pgp, tofu, tofu+pgp, always, ...)
+    TrustModel {
+        /// New trust model, omit to show the current one
+        model: Option<String>,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Import keys from a file, listing what will change and asking first
+    Import {
+        file: PathBuf,
+        /// Only show the preview, do not modify the keyring
+        #[arg(long)]
+        dry_run: bool,
+        /// Import without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+        /// Don't change ownertrust values (keep-ownertrust)
+        #[arg(long)]
+        keep_ownertrust: bool,
+        /// Drop unusable signatures and user IDs (import-clean)
+        #[arg(long)]
+        clean: bool,
+        /// Restore a backup (import-restore)
+        #[arg(long)]
+        restore: bool,
+        /// Import only this user ID (repeatable)
+        #[arg(long = "keep-uid")]
+        keep_user_ids: Vec<String>,
+        /// Import only the key with this fingerprint (repeatable)
+        #[arg(long = "fingerprint")]
+        fingerprints: Vec<String>,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Export a public key reduced to fit a QR code (minimal, clean, selected user IDs and subkeys)
+    ExportKey {
+        key_id: String,
+        /// Only the newest self-signatures, no third-party certifications
+        #[arg(long)]
+        minimal: bool,
+        /// Drop unusable signatures and user IDs
+        #[arg(long)]
+        clean: bool,
+        /// User ID to leave out (repeatable)
+        #[arg(long = "drop-uid")]
+        drop_user_ids: Vec<String>,
+        /// Subkey fingerprint to keep (repeatable); default: all subkeys
+        #[arg(long = "subkey")]
+        subkeys: Vec<String>,
+        /// Only print the size estimate
+        #[arg(long)]
+        estimate: bool,
+        /// Write the armored key to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Export only the secret subkeys (primary secret key stripped) for a daily-use machine
+    ExportSecretSubkeys {
+        key_id: String,
+        /// Subkey fingerprint to export (repeatable); default: all secret subkeys
+        #[arg(long = "subkey")]
+        subkeys: Vec<String>,
+        /// Encrypt the export to this recipient key (default: to an export passphrase)
+        #[arg(long)]
+        recipient: Option<String>,
+        /// Read the export passphrase from this file (first line) instead of stdin
+        #[arg(long, conflicts_with = "recipient")]
+        export_passphrase_file: Option<PathBuf>,
+        /// Read the key's passphrase from this file (first line) instead of stdin
+        #[arg(long)]
+        passphrase_file: Option<PathBuf>,
+        /// Write the armored export to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Also write the export as a multi-frame QR set (SVG files next to --output)
+        #[arg(long, requires = "output")]
+        qr: bool,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Show a key's user IDs with their validity, its subkeys and trust explanation
+    KeyDetails {
+        key_id: String,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Add a user ID to an own key, e.g. after an email address change
+    AddUid {
+        key_id: String,
+        /// New user ID, e.g. "Name <new@example.org>"
+        user_id: String,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Make a user ID the primary one
+    SetPrimaryUid {
+        key_id: String,
+        /// Exact user ID
+        user_id: String,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// List the primary key and subkeys of a key with usage, expiry and status
+    Subkeys {
+        key_id: String,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Add a subkey (requires the primary secret key)
+    AddSubkey {
+        key_id: String,
+        /// sign, encrypt or auth
+        #[arg(long)]
+        usage: String,
+        /// Algorithm such as ed25519, cv25519 or rsa4096 (default: GnuPG's default)
+        #[arg(long, default_value = "")]
+        algorithm: String,
+        /// Expiry: 0 for never, or e.g. 1y, 6m, 30d
+        #[arg(long, default_value = "1y")]
+        expires: String,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Set or extend the expiry of the primary key or of selected subkeys
+    SetExpiry {
+        key_id: String,
+        /// Expiry: 0 for never, or e.g. 1y, 6m, 30d
+        expires: String,
+        /// Subkey fingerprint (repeatable); without it the primary key is changed
+        #[arg(long = "subkey")]
+        subkeys: Vec<String>,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Generate a revocation certificate for an own key without applying it
+    GenRevoke {
+        key_id: String,
+        /// Write the armored certificate to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Revocation reason: none, compromised, superseded, no_longer_used
+        #[arg(long, default_value = "none")]
+        reason: String,
+        /// Free text explaining the revocation
+        #[arg(long, default_value = "")]
+        description: String,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Revoke an own key (generates and imports a revocation certificate)
+    RevokeKey {
+        key_id: String,
+        /// Revocation reason: none, compromised, superseded, no_longer_used
+        #[arg(long, default_value = "none")]
+        reason: String,
+        /// Free text explaining the revocation
+        #[arg(long, default_value = "")]
+        description: String,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Revoke a single subkey
+    RevokeSubkey {
+        key_id: String,
+        /// Fingerprint of the subkey to revoke
+        subkey: String,
+        /// Revocation reason: none, compromised, superseded, no_longer_used
+        #[arg(long, default_value = "none")]
+        reason: String,
+        /// Free text explaining the revocation
+        #[arg(long, default_value = "")]
+        description: String,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Revoke a user ID
+    RevokeUid {
+        key_id: String,
+        /// Exact user ID to revoke
+        user_id: String,
+        /// Revocation reason: none, uid_invalid
+        #[arg(long, default_value = "none")]
+        reason: String,
+        /// Free text explaining the revocation
+        #[arg(long, default_value = "")]
+        description: String,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Write an encrypted backup of the whole keyring (keys, ownertrust, revocation certificates)
+    Backup {
+        /// Output file for the armored archive
+        output: PathBuf,
+        /// Also write the archive as a multi-frame QR set (SVG files next to the output)
+        #[arg(long)]
+        qr: bool,
+        /// Read the backup passphrase from this file (first line) instead of stdin
+        #[arg(long)]
+        passphrase_file: Option<PathBuf>,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Check a keyring backup for completeness and restore it
+    RestoreBackup {
+        /// Armored backup archive
+        file: PathBuf,
+        /// Only decrypt and check the archive, restore nothing
+        #[arg(long)]
+        validate_only: bool,
+        /// Read the backup passphrase from this file (first line) instead of stdin
+        #[arg(long)]
+        passphrase_file: Option<PathBuf>,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Print a paperkey-style backup of a secret key (secret parts only, line checksums, QR codes)
+    PaperBackup {
+        key_id: String,
+        /// Text encoding of the printed lines: base16 or base32
+        #[arg(long, default_value = "base16")]
+        format: String,
+        /// Write the printable HTML page to this file instead of printing the text version
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Read the key's passphrase from this file (first line) instead of stdin
+        #[arg(long)]
+        passphrase_file: Option<PathBuf>,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Restore a secret key from a paper backup (typed text or scanned QR payload)
+    PaperRestore {
+        /// File with the typed paper backup text or the scanned QR payload
+        file: PathBuf,
+        /// Armored public key to combine with; default: the key from the keyring
+        #[arg(long)]
+        public_key: Option<PathBuf>,
+        /// Write the restored secret key to this file instead of importing it
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Split a secret key (or a keyring backup archive) into Shamir shares on printable QR cards
+    SplitShares {
+        /// Secret key to split (omit when using --archive)
+        #[arg(required_unless_present = "archive")]
+        key_id: Option<String>,
+        /// Split this keyring backup archive instead of a key
+        #[arg(long, conflicts_with = "key_id")]
+        archive: Option<PathBuf>,
+        /// Read the key's passphrase from this file (first line) instead of stdin
+        #[arg(long, conflicts_with = "archive")]
+        passphrase_file: Option<PathBuf>,
+        /// Number of shares needed to restore
+        #[arg(short = 'k', long)]
+        threshold: u8,
+        /// Number of shares to create
+        #[arg(short = 'n', long)]
+        shares: u8,
+        /// Printable HTML page with one card per share
+        #[arg(short, long)]
+        output: PathBuf,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Combine Shamir shares (text files with one share payload per line) and import the key
+    CombineShares {
+        /// Files containing the scanned share payloads
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Write the restored secret key or backup archive to this file instead of importing
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Create an air-gapped signing request for the offline signer
+    SignRequest {
+        /// Requested signer (key ID or fingerprint, public key is enough; default: the configured signer)
+        #[arg(long)]
+        signer: Option<String>,
+        /// Signature mode: clearsign, detach or normal
+        #[arg(long, default_value = "clearsign")]
+        mode: String,
+        /// Document to sign
+        file: PathBuf,
+        /// Send only the SHA-256 of the document, not its content
+        #[arg(long)]
+        hash_only: bool,
+        /// Document name shown to the signer (default: the file name)
+        #[arg(long)]
+        document: Option<String>,
+        /// Who asks for the signature (default: $USER)
+        #[arg(long)]
+        requester: Option<String>,
+        /// Description shown to the offline operator
+        #[arg(long, default_value = "")]
+        description: String,
+        /// Write the request payload here (needed again by check-sign-response)
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Also write the request as QR frames (<output>.qrN.svg)
+        #[arg(long)]
+        qr: bool,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Offline side: show a signing request, sign it after confirmation and write the response
+    AnswerSignRequest {
+        /// File containing the scanned request payload
+        file: PathBuf,
+        /// Sign without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+        /// Write the response payload here
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Also write the response as QR frames (<output>.qrN.svg)
+        #[arg(long)]
+        qr: bool,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Check a signing response against its request and write the signature
+    CheckSignResponse {
+        /// File containing the scanned response payload
+        file: PathBuf,
+        /// The request file written by sign-request
+        #[arg(long)]
+        request: PathBuf,
+        /// Write the signature here instead of printing it
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Package a ciphertext as an air-gapped decryption request for the offline key holder
+    DecryptRequest {
+        /// Encrypted message (armored)
+        file: PathBuf,
+        /// Carry a session passphrase in the request instead of a one-time public key
+        #[arg(long)]
+        use_passphrase: bool,
+        /// Description shown to the offline operator
+        #[arg(long, default_value = "")]
+        description: String,
+        /// Write the request payload here
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Write the secret that opens the response here (needed by open-decrypt-response)
+        #[arg(long)]
+        session: PathBuf,
+        /// Also write the request as QR frames (<output>.qrN.svg)
+        #[arg(long)]
+        qr: bool,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Offline side: decrypt a request and re-encrypt the result for the requester
+    AnswerDecryptRequest {
+        /// File containing the scanned request payload
+        file: PathBuf,
+        /// Write the response payload here
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Also write the response as QR frames (<output>.qrN.svg)
+        #[arg(long)]
+        qr: bool,
+        /// Print the plaintext on this machine instead of answering
+        #[arg(long)]
+        show_plaintext: bool,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Open a decryption response with the session written by decrypt-request
+    OpenDecryptResponse {
+        /// File containing the scanned response payload
+        file: PathBuf,
+        /// Session file written by decrypt-request
+        #[arg(long)]
+        session: PathBuf,
+        /// Write the plaintext here instead of printing it
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Verify the hash chain and checkpoint signatures of the audit log (or of an export)
+    AuditVerify {
+        /// Exported audit log to verify instead of the profile's log
+        #[arg(long)]
+        file: Option<PathBuf>,
+        /// Require checkpoints to be signed by this key
+        #[arg(long)]
+        key: Option<String>,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Sign the current head of the audit log
+    AuditCheckpoint {
+        /// Audit signing key
+        #[arg(long)]
+        key: String,
+        /// GPG Home directory override
+        #[arg(long)]
+        gpg_dir: Option<String>,
+    },
+    /// Export audit log entries, e.g. as QR frames from the air-gapped machine
+    AuditExport {
+        /// First entry to export
+        #[arg(long, default_value_t = 1)]
+        since: u64,
+        /// Write the entries here
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Also write the entries as QR frames (<output>.qrN.svg)
+        #[arg(long)]
+        qr: bool,
         /// GPG Home directory override
         #[arg(long)]
         gpg_dir: Option<String>,
@@ -24,4 +607,25 @@ pub enum Commands {
     // Add other direct CLI commands later if needed
     // KeyGen { ... },
     // Encrypt { ... },
-}
\ No newline at end of file
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum TrustLevelArg {
+    Marginal,
+    Full,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum QrEccArg {
+    Low,
+    Medium,
+    Quartile,
+    High,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum QrCompactArg {
+    None,
+    Base45,
+    Binary,
+}
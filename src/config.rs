@@ -0,0 +1,256 @@
+use crate::error::{AppError, Result};
+use crate::policy::Policy;
+use crate::qr_utils::QrSettings;
+use parking_lot::RwLock;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// Configuration file (TOML), from --config or else the first existing
+// $XDG_CONFIG_HOME/secure-gpg-qr/config.toml (~/.config/...) or
+// <$XDG_CONFIG_DIRS entry, /etc/xdg>/secure-gpg-qr/config.toml. Command line flags win. E.g.
+//
+//   profile = "office"                 # --profile overrides
+//   [server]
+//   bind = "127.0.0.1"
+//   port = 8443
+//   tls = { cert = "/etc/sgqr/cert.pem", key = "/etc/sgqr/key.pem" }
+//   [gpg]
+//   home = "/media/offline/gnupg"
+//   signer = "<fingerprint>"           # default signing and certifying key
+//   recipients = ["<fingerprint>"]     # default encryption recipients
+//   audit_key = "<fingerprint>"
+//   [qr]
+//   ecc = "high"                       # low, medium, quartile, high
+//   compact = "base45"                 # none, base45, binary
+//   [policy]                           # as in a --policy file
+//   disabled = ["export_secret"]
+//   [log]
+//   level = "info"                     # tracing filter, e.g. "info,tower_http=debug"
+//   file = "/var/log/sgqr.log"
+//   [timeouts]
+//   request_secs = 120                 # 0 = no limit
+//   [profiles.office]                  # [gpg] settings replaced by this profile's
+//   home = "/home/me/.gnupg-office"
+
+const APP_DIR: &str = "secure-gpg-qr";
+const FILE_NAME: &str = "config.toml";
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub profile: Option<String>,
+    pub server: ServerConfig,
+    pub gpg: GpgConfig,
+    pub qr: QrSettings,
+    pub policy: Policy,
+    pub log: LogConfig,
+    pub timeouts: Timeouts,
+    pub profiles: BTreeMap<String, GpgConfig>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind: Option<String>, // default 127.0.0.1
+    pub port: Option<u16>,    // default: random high port
+    pub tls: Option<TlsConfig>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    pub cert: PathBuf, // PEM certificate chain
+    pub key: PathBuf,  // PEM private key
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct GpgConfig {
+    pub home: Option<String>,
+    pub signer: Option<String>,
+    pub recipients: Vec<String>,
+    pub audit_key: Option<String>,
+}
+
+impl GpgConfig {
+    // A profile's settings replace the general ones where it has them
+    fn overlay(&mut self, profile: &GpgConfig) {
+        if profile.home.is_some() {
+            self.home = profile.home.clone();
+        }
+        if profile.signer.is_some() {
+            self.signer = profile.signer.clone();
+        }
+        if !profile.recipients.is_empty() {
+            self.recipients = profile.recipients.clone();
+        }
+        if profile.audit_key.is_some() {
+            self.audit_key = profile.audit_key.clone();
+        }
+    }
+
+    fn validate(&self) -> std::result::Result<(), String> {
+        if self.recipients.iter().any(|r| r.trim().is_empty()) {
+            return Err("empty entry in recipients".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    pub level: String,
+    pub file: Option<PathBuf>, // default: stderr
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig { level: "info".to_string(), file: None }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(default, deny_unknown_fields)]
+pub struct Timeouts {
+    pub request_secs: u64,    // a whole HTTP request, 0 = no limit
+    pub scan_secs: u64,       // scans held for a follow-up action
+    pub sign_queue_secs: u64, // signing requests awaiting approval
+    pub airgap_secs: u64,     // air-gapped requests awaiting their response
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Timeouts { request_secs: 120, scan_secs: 15 * 60, sign_queue_secs: 24 * 60 * 60, airgap_secs: 24 * 60 * 60 }
+    }
+}
+
+impl Timeouts {
+    pub fn request(&self) -> Option<Duration> {
+        (self.request_secs > 0).then(|| Duration::from_secs(self.request_secs))
+    }
+
+    pub fn scan(&self) -> Duration {
+        Duration::from_secs(self.scan_secs)
+    }
+
+    pub fn sign_queue(&self) -> Duration {
+        Duration::from_secs(self.sign_queue_secs)
+    }
+
+    pub fn airgap(&self) -> Duration {
+        Duration::from_secs(self.airgap_secs)
+    }
+}
+
+lazy_static::lazy_static! {
+    // Built-in defaults until main installs the loaded configuration
+    static ref CONFIG: RwLock<Config> = RwLock::new(Config::default());
+}
+
+impl Config {
+    // `path` is --config; without it a missing file just means the defaults
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let Some(path) = path.map(Path::to_path_buf).or_else(default_path) else {
+            return Ok(Config::default());
+        };
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| AppError::Config(format!("Cannot read config file {}: {}", path.display(), e)))?;
+        let config: Config = toml::from_str(&text)
+            .map_err(|e| AppError::Config(format!("Invalid config file {}: {}", path.display(), e)))?;
+        config.validate().map_err(|e| AppError::Config(format!("Invalid config file {}: {}", path.display(), e)))?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> std::result::Result<(), String> {
+        if let Some(bind) = &self.server.bind {
+            bind.parse::<IpAddr>().map_err(|_| format!("server.bind is not an IP address: {}", bind))?;
+        }
+        if let Some(tls) = &self.server.tls {
+            validate_tls(tls)?;
+        }
+        self.gpg.validate().map_err(|e| format!("gpg: {}", e))?;
+        for (name, profile) in &self.profiles {
+            profile.validate().map_err(|e| format!("profiles.{}: {}", name, e))?;
+        }
+        if let Some(name) = &self.profile {
+            if !self.profiles.contains_key(name) {
+                return Err(format!("profile \"{}\" has no [profiles.{}] table", name, name));
+            }
+        }
+        self.qr.validate().map_err(|e| format!("qr: {}", e))?;
+        tracing_subscriber::EnvFilter::try_new(&self.log.level)
+            .map_err(|e| format!("log.level \"{}\": {}", self.log.level, e))?;
+        let timeouts = &self.timeouts;
+        if timeouts.scan_secs == 0 || timeouts.sign_queue_secs == 0 || timeouts.airgap_secs == 0 {
+            return Err("timeouts other than request_secs must be at least 1 second".to_string());
+        }
+        Ok(())
+    }
+
+    // Applies a profile's GPG settings: --profile, else the file's `profile`
+    pub fn select_profile(&mut self, name: Option<&str>) -> Result<()> {
+        let Some(name) = name.map(str::to_string).or_else(|| self.profile.clone()) else {
+            return Ok(());
+        };
+        let profile = self.profiles.get(&name).cloned().ok_or_else(|| {
+            let known: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            AppError::Config(format!("Unknown profile \"{}\" (configured: {}).", name, known.join(", ")))
+        })?;
+        self.gpg.overlay(&profile);
+        self.profile = Some(name);
+        Ok(())
+    }
+}
+
+pub fn validate_tls(tls: &TlsConfig) -> std::result::Result<(), String> {
+    for (what, path) in [("certificate", &tls.cert), ("key", &tls.key)] {
+        if !path.is_file() {
+            return Err(format!("TLS {} {} does not exist", what, path.display()));
+        }
+    }
+    Ok(())
+}
+
+// First existing config file in the XDG user, then system, config directories
+fn default_path() -> Option<PathBuf> {
+    let env_dir = |name: &str| std::env::var_os(name).map(PathBuf::from).filter(|dir| dir.is_absolute());
+    let user_dir = env_dir("XDG_CONFIG_HOME").or_else(|| env_dir("HOME").map(|home| home.join(".config")));
+    let system_dirs = std::env::var("XDG_CONFIG_DIRS").ok().filter(|dirs| !dirs.is_empty());
+    let system_dirs: Vec<PathBuf> = system_dirs
+        .as_deref()
+        .unwrap_or("/etc/xdg")
+        .split(':')
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .collect();
+    user_dir
+        .into_iter()
+        .chain(system_dirs)
+        .map(|dir| dir.join(APP_DIR).join(FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+pub fn set_config(config: Config) {
+    *CONFIG.write() = config;
+}
+
+pub fn current() -> Config {
+    CONFIG.read().clone()
+}
+
+pub fn timeouts() -> Timeouts {
+    CONFIG.read().timeouts
+}
+
+// The signer to use: the one given, else the configured default
+pub fn signer_or_default(signer: Option<&str>) -> Option<String> {
+    signer.map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).or_else(|| CONFIG.read().gpg.signer.clone())
+}
+
+pub fn default_recipients() -> Vec<String> {
+    CONFIG.read().gpg.recipients.clone()
+}
@@ -26,6 +26,9 @@ pub enum AppError {
     #[error("QR Code Generation Error: {0}")]
     QrCodeGen(String), // qrcodegen uses integers typically
 
+    #[error("QR Code Decoding Error: {0}")]
+    QrDecode(String),
+
     #[error("Configuration Error: {0}")]
     Config(String),
 
@@ -35,6 +38,9 @@ pub enum AppError {
     #[error("Invalid Input: {0}")]
     InvalidInput(String),
 
+    #[error("Denied by policy: {0}")]
+    PolicyDenied(String),
+
     #[error("Port unavailable: {0}")]
     PortUnavailable(u16),
 
@@ -57,6 +63,14 @@ impl axum::response::IntoResponse for AppError {
             AppError::InvalidInput(msg) => (
                 axum::http::StatusCode::BAD_REQUEST,
                 format!("Invalid Input: {}", msg),
+            ),
+            AppError::QrDecode(msg) => (
+                axum::http::StatusCode::BAD_REQUEST,
+                format!("QR Decoding Failed: {}", msg),
+            ),
+            AppError::PolicyDenied(msg) => (
+                axum::http::StatusCode::FORBIDDEN,
+                format!("Denied by policy: {}", msg),
             ),
              AppError::Operation(msg) => (
                  axum::http::StatusCode::INTERNAL_SERVER_ERROR,
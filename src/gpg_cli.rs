@@ -0,0 +1,94 @@
+use crate::error::{AppError, Result};
+use crate::gpg_ops;
+use std::ffi::OsStr;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+// Direct invocation of the gpg binary for the few operations gpgme has no API for
+// (ownertrust database, export/import filters, ...). Always uses the same gpg
+// executable and home directory as the gpgme context.
+
+pub struct GpgOutput {
+    pub stdout: Vec<u8>,
+    pub status: Vec<String>, // --status-fd lines without the "[GNUPG:] " prefix
+    pub success: bool,       // exit code was zero
+}
+
+pub fn run_gpg(args: &[&str], stdin: Option<&[u8]>) -> Result<GpgOutput> {
+    run(None, args, stdin, true, true)
+}
+
+// Like run_gpg, but in another home directory (e.g. a throwaway one holding a one-time key)
+pub fn run_gpg_in_home(home: &Path, args: &[&str], stdin: Option<&[u8]>) -> Result<GpgOutput> {
+    run(Some(home), args, stdin, true, true)
+}
+
+// Like run_gpg, but a non-zero exit is not an error. For commands such as --import
+// that report partial failures per key in the status lines.
+pub fn run_gpg_unchecked(args: &[&str], stdin: Option<&[u8]>) -> Result<GpgOutput> {
+    run(None, args, stdin, true, false)
+}
+
+// For commands gpg refuses in batch mode (e.g. --gen-revoke): prompts are answered
+// from `answers`, one per line, via --command-fd. Passphrases still go through gpg-agent.
+pub fn run_gpg_with_answers(args: &[&str], answers: &[String]) -> Result<GpgOutput> {
+    let mut input = answers.join("\n");
+    input.push('\n');
+    let mut full_args = vec!["--command-fd", "0"];
+    full_args.extend_from_slice(args);
+    run(None, &full_args, Some(input.as_bytes()), false, true)
+}
+
+fn run(home: Option<&Path>, args: &[&str], stdin: Option<&[u8]>, batch: bool, check: bool) -> Result<GpgOutput> {
+    let engine = gpg_ops::engine_paths()?;
+    let mut command = Command::new(&engine.gpg_path);
+    // An explicit home directory wins over the one the gpgme context uses
+    let home_dir = home.map(Path::as_os_str).or(engine.home_dir.as_deref().map(OsStr::new));
+    if let Some(home_dir) = home_dir {
+        command.arg("--homedir").arg(home_dir);
+    }
+    if batch {
+        command.arg("--batch");
+    }
+    command
+        .args(["--no-tty", "--status-fd", "2"])
+        .args(args)
+        .stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let mut child = command
+        .spawn()
+        .map_err(|e| AppError::Operation(format!("Could not run {}: {}", engine.gpg_path, e)))?;
+    // gpg writes output while still reading input (--encrypt, --decrypt, status lines of
+    // --import), so stdin is fed from another thread while stdout and stderr are drained
+    let child_stdin = child.stdin.take();
+    let (output, written) = std::thread::scope(|scope| {
+        let writer = stdin.zip(child_stdin).map(|(input, mut child_stdin)| {
+            // stdin is closed when dropped at the end of the thread, so gpg sees EOF
+            scope.spawn(move || child_stdin.write_all(input))
+        });
+        let output = child.wait_with_output();
+        let written = writer.map_or(Ok(()), |writer| writer.join().unwrap_or(Ok(())));
+        (output, written)
+    });
+    let output = output?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let status: Vec<String> = stderr
+        .lines()
+        .filter_map(|line| line.strip_prefix("[GNUPG:] "))
+        .map(str::to_string)
+        .collect();
+    if check && !output.status.success() {
+        let messages: Vec<&str> = stderr.lines().filter(|l| !l.starts_with("[GNUPG:] ")).collect();
+        return Err(AppError::Operation(format!(
+            "gpg {} failed: {}",
+            args.iter().find(|a| a.starts_with("--") && **a != "--command-fd").unwrap_or(&""),
+            messages.join(" ").trim()
+        )));
+    }
+    written?;
+    Ok(GpgOutput { stdout: output.stdout, status, success: output.status.success() })
+}
@@ -0,0 +1,112 @@
+use crate::error::{AppError, Result};
+use gpgme::{Context, Data, Interactor, InteractionStatus, Key};
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::io::Write;
+use std::sync::Arc;
+
+// Scripted `gpg --edit-key` sessions through gpgme's interact API.
+// Used for the few key edits that have no dedicated gpgme operation
+// (trust signatures, ownertrust, ...). Each step answers one prompt;
+// anything unexpected aborts the session instead of guessing.
+
+const STATUS_GET_LINE: &str = "GET_LINE";
+const STATUS_GET_BOOL: &str = "GET_BOOL";
+const STATUS_GET_HIDDEN: &str = "GET_HIDDEN";
+const PROMPT_KEYEDIT: &str = "keyedit.prompt";
+
+#[derive(Default)]
+pub struct EditScript {
+    steps: VecDeque<(&'static str, String)>,  // (expected prompt, answer)
+    optional: Vec<(&'static str, String)>,    // prompts gpg may or may not ask, with their answers
+    error: Option<String>,
+}
+
+impl EditScript {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Answer the next occurrence of `prompt` with `answer`
+    pub fn step(mut self, prompt: &'static str, answer: impl Into<String>) -> Self {
+        self.steps.push_back((prompt, answer.into()));
+        self
+    }
+
+    // Shorthand for a command at the main `gpg>` prompt
+    pub fn command(self, command: impl Into<String>) -> Self {
+        self.step(PROMPT_KEYEDIT, command)
+    }
+
+    // Answer `prompt` whenever gpg asks it (depends on gpg.conf / key state)
+    pub fn optional(mut self, prompt: &'static str, answer: impl Into<String>) -> Self {
+        self.optional.push((prompt, answer.into()));
+        self
+    }
+
+    fn answer_for(&mut self, prompt: &str) -> Option<String> {
+        if self.steps.front().map(|(p, _)| *p == prompt).unwrap_or(false) {
+            return self.steps.pop_front().map(|(_, answer)| answer);
+        }
+        if let Some((_, answer)) = self.optional.iter().find(|(p, _)| *p == prompt) {
+            return Some(answer.clone());
+        }
+        match prompt {
+            // Script finished: leave the editor, saving changes
+            PROMPT_KEYEDIT if self.steps.is_empty() => Some("save".to_string()),
+            "keyedit.save.okay" => Some("y".to_string()),
+            _ => None,
+        }
+    }
+
+    fn interact<W: Write>(&mut self, status: InteractionStatus<'_>, out: Option<W>) -> gpgme::Result<()> {
+        let keyword = status.keyword().unwrap_or("");
+        if ![STATUS_GET_LINE, STATUS_GET_BOOL, STATUS_GET_HIDDEN].contains(&keyword) {
+            return Ok(()); // informational status lines
+        }
+        let prompt = status.args().unwrap_or("");
+        let Some(mut out) = out else {
+            return Ok(());
+        };
+        match self.answer_for(prompt) {
+            Some(answer) => {
+                out.write_all(answer.as_bytes()).map_err(gpgme::Error::from)?;
+                out.write_all(b"\n").map_err(gpgme::Error::from)?;
+                Ok(())
+            }
+            None => {
+                self.error = Some(format!("Unexpected gpg prompt '{}' during key edit.", prompt));
+                Err(gpgme::Error::CANCELED)
+            }
+        }
+    }
+}
+
+// The interactor is moved into gpgme, so the script is shared to read its state afterwards
+struct SharedScript(Arc<Mutex<EditScript>>);
+
+impl Interactor for SharedScript {
+    fn interact<W: Write>(&mut self, status: InteractionStatus<'_>, out: Option<W>) -> gpgme::Result<()> {
+        self.0.lock().interact(status, out)
+    }
+}
+
+// Runs an edit script against `key`. Steps left unanswered mean gpg took a
+// different path than expected (e.g. the key already had such a signature).
+pub fn run(ctx: &mut Context, key: &Key, script: EditScript) -> Result<()> {
+    let shared = Arc::new(Mutex::new(script));
+    let mut output = Vec::new();
+    let result = ctx.interact(key, SharedScript(shared.clone()), Data::from_writer(&mut output)?);
+    let mut script = shared.lock();
+    if let Some(error) = script.error.take() {
+        return Err(AppError::Operation(error));
+    }
+    result?;
+    if let Some((prompt, _)) = script.steps.front() {
+        return Err(AppError::Operation(format!(
+            "Key edit ended before gpg asked '{}'.",
+            prompt
+        )));
+    }
+    Ok(())
+}
@@ -1,7 +1,15 @@
+use crate::armor;
+use crate::audit;
+use crate::classify;
 use crate::error::{AppError, Result};
-use gpgme::{Context, Data, Key, Protocol};
+use crate::gpg_cli;
+use crate::gpg_edit;
+use crate::policy::{self, Operation};
+use gpgme::{Context, Data, Key, Protocol, Validity};
+use serde::Serialize;
 use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 use zeroize::Zeroizing; // Import the trait
 
 // --- Configuration ---
@@ -49,6 +57,61 @@ where
     }
 }
 
+// Audit-logs an operation whose result is data; the log only gets SHA-256 hashes
+fn audited<T: AsRef<[u8]>>(operation: &str, keys: &[&str], input: Option<&[u8]>, result: Result<T>) -> Result<T> {
+    audit::record(operation, keys, input, result.as_ref().map(|output| Some(output.as_ref())));
+    result
+}
+
+// Audit-logs an operation whose result is a status message, not data
+fn audited_action<T>(operation: &str, keys: &[&str], input: Option<&[u8]>, result: Result<T>) -> Result<T> {
+    audit::record(operation, keys, input, result.as_ref().map(|_| None));
+    result
+}
+
+pub struct EnginePaths {
+    pub gpg_path: String,
+    pub home_dir: Option<String>, // None = GnuPG's default home directory
+}
+
+// The gpg executable and home directory the gpgme context uses
+pub fn engine_paths() -> Result<EnginePaths> {
+    with_gpg_ctx(|ctx| {
+        let info = ctx.engine_info();
+        let gpg_path = info
+            .path()
+            .map_err(|_| AppError::Config("gpg engine path is not valid UTF-8.".to_string()))?
+            .to_string();
+        let home_dir = info.home_dir().ok().map(str::to_string);
+        Ok(EnginePaths { gpg_path, home_dir })
+    })
+}
+
+// Home directory actually in use, resolving GnuPG's default
+pub fn effective_home_dir() -> Result<PathBuf> {
+    if let Some(home) = engine_paths()?.home_dir {
+        return Ok(PathBuf::from(home));
+    }
+    if let Ok(home) = std::env::var("GNUPGHOME") {
+        return Ok(PathBuf::from(home));
+    }
+    std::env::var("HOME")
+        .map(|home| PathBuf::from(home).join(".gnupg"))
+        .map_err(|_| AppError::Config("Cannot determine GnuPG home directory.".to_string()))
+}
+
+// Runs `f` with additional key list mode flags, restoring the previous mode afterwards
+fn with_key_list_mode<F, R>(ctx: &mut Context, extra: gpgme::KeyListMode, f: F) -> Result<R>
+where
+    F: FnOnce(&mut Context) -> Result<R>,
+{
+    let previous_mode = ctx.key_list_mode();
+    ctx.set_key_list_mode(previous_mode | extra)?;
+    let result = f(ctx);
+    ctx.set_key_list_mode(previous_mode)?;
+    result
+}
+
 // --- Key Management ---
 
 pub fn list_keys(secret_only: bool) -> Result<Vec<String>> {
@@ -68,32 +131,1341 @@ pub fn list_keys(secret_only: bool) -> Result<Vec<String>> {
                 .map(|uid| uid.id().unwrap_or("<no uid>"))
                 .unwrap_or("<no user id>");
             let fpr = key.fingerprint().unwrap_or("<no fpr>");
-            keys.push(format!("{} {}", fpr, uid));
+            let validity = key.user_ids().next().map(|uid| uid.validity()).unwrap_or(Validity::Unknown);
+            let subkeys: Vec<String> = key
+                .subkeys()
+                .skip(1)
+                .map(|sk| {
+                    let info = subkey_info(&sk, false);
+                    format!("{} {} [{}] {}", info.key_id, info.algorithm, info.usage, subkey_status(&info))
+                })
+                .collect();
+            keys.push(format!(
+                "{} {} [ownertrust: {}, validity: {}]{}",
+                fpr,
+                uid,
+                validity_name(key.owner_trust()),
+                validity_name(validity),
+                if subkeys.is_empty() { String::new() } else { format!(" subkeys: {}", subkeys.join("; ")) }
+            ));
         }
         Ok(keys)
     })
 }
 
-pub fn export_key(key_id: &str, secret: bool) -> Result<String> {
+#[derive(Serialize, Debug, Clone)]
+pub struct KeySummary {
+    pub fingerprint: String,
+    pub user_ids: Vec<String>,
+    pub has_secret: bool,  // payload (or keyring entry) carries secret key material
+    pub in_keyring: bool,  // a key with this fingerprint is already in our keyring
+}
+
+// What importing a key would do, worked out without touching the keyring
+#[derive(Serialize, Debug, Clone)]
+pub struct KeyPreview {
+    pub fingerprint: String,
+    pub user_ids: Vec<String>,
+    pub subkeys: Vec<SubkeyInfo>,   // primary key first
+    pub has_secret: bool,           // payload carries secret key material
+    pub in_keyring: bool,           // a key with this fingerprint is already in our keyring
+    pub new_user_ids: Vec<String>,  // user IDs the keyring does not have yet
+    pub new_subkeys: Vec<String>,   // fingerprints of subkeys the keyring does not have yet
+    pub new_secret: bool,           // secret material for a key we only hold publicly (or not at all)
+}
+
+impl KeyPreview {
+    // One-line summary, e.g. for scan notes and the CLI
+    pub fn describe(&self) -> String {
+        let mut changes = Vec::new();
+        if !self.in_keyring {
+            changes.push(format!("new key with {} subkey(s)", self.subkeys.len().saturating_sub(1)));
+        } else {
+            if !self.new_user_ids.is_empty() {
+                changes.push(format!("new user IDs: {}", self.new_user_ids.join(", ")));
+            }
+            if !self.new_subkeys.is_empty() {
+                changes.push(format!("new subkeys: {}", self.new_subkeys.join(", ")));
+            }
+        }
+        if self.new_secret {
+            changes.push("SECRET KEY MATERIAL".to_string());
+        }
+        if changes.is_empty() {
+            changes.push("already in keyring (signatures may be updated)".to_string());
+        }
+        format!(
+            "{} {}: {}",
+            self.fingerprint,
+            self.user_ids.first().map(String::as_str).unwrap_or("<no user id>"),
+            changes.join("; ")
+        )
+    }
+}
+
+// Lists the keys in key data and compares them with the keyring, without importing
+// (gpgme reads the data with gpg's import-show/dry-run)
+pub fn preview_keys(key_data: &[u8]) -> Result<Vec<KeyPreview>> {
     with_gpg_ctx(|ctx| {
-        let mut output = Vec::new();
-        let mut armored_output = Data::from_armor_writer(&mut output)?;
+        let mut parsed = Vec::new();
+        for key_result in ctx.read_keys(key_data)? {
+            let key = key_result?;
+            parsed.push(KeyPreview {
+                fingerprint: key.fingerprint().unwrap_or("<no fpr>").to_string(),
+                user_ids: key
+                    .user_ids()
+                    .map(|uid| uid.id().unwrap_or("<invalid uid>").to_string())
+                    .collect(),
+                subkeys: key.subkeys().enumerate().map(|(i, sk)| subkey_info(&sk, i == 0)).collect(),
+                has_secret: key.has_secret(),
+                in_keyring: false,
+                new_user_ids: Vec::new(),
+                new_subkeys: Vec::new(),
+                new_secret: false,
+            });
+        }
+        for preview in parsed.iter_mut() {
+            let existing = match ctx.get_key(&preview.fingerprint) {
+                Ok(key) => key,
+                Err(_) => {
+                    preview.new_user_ids = preview.user_ids.clone();
+                    preview.new_subkeys = preview.subkeys.iter().skip(1).map(|sk| sk.fingerprint.clone()).collect();
+                    preview.new_secret = preview.has_secret;
+                    continue;
+                }
+            };
+            preview.in_keyring = true;
+            let existing_uids: Vec<&str> = existing.user_ids().filter_map(|uid| uid.id().ok()).collect();
+            let existing_subkeys: Vec<&str> = existing.subkeys().filter_map(|sk| sk.fingerprint().ok()).collect();
+            preview.new_user_ids = preview
+                .user_ids
+                .iter()
+                .filter(|uid| !existing_uids.contains(&uid.as_str()))
+                .cloned()
+                .collect();
+            preview.new_subkeys = preview
+                .subkeys
+                .iter()
+                .skip(1)
+                .filter(|sk| !existing_subkeys.contains(&sk.fingerprint.as_str()))
+                .map(|sk| sk.fingerprint.clone())
+                .collect();
+            preview.new_secret = preview.has_secret
+                && ctx.find_secret_keys([preview.fingerprint.as_str()])?.next().is_none();
+        }
+        Ok(parsed)
+    })
+}
+
+// Looks up a secret key by key ID or fingerprint, returning its primary user ID if we hold it
+pub fn find_secret_key_uid(key_id: &str) -> Result<Option<String>> {
+    with_gpg_ctx(|ctx| {
+        let mut iter = ctx.find_secret_keys([key_id])?;
+        match iter.next() {
+            Some(key_result) => {
+                let key = key_result?;
+                Ok(Some(
+                    key.user_ids()
+                        .next()
+                        .map(|uid| uid.id().unwrap_or("<no uid>").to_string())
+                        .unwrap_or_else(|| "<no user id>".to_string()),
+                ))
+            }
+            None => Ok(None),
+        }
+    })
+}
+
+// True if at least one secret key is available (needed for hidden-recipient messages)
+pub fn has_any_secret_key() -> Result<bool> {
+    with_gpg_ctx(|ctx| Ok(ctx.secret_keys()?.next().is_some()))
+}
+
+// Resolves a key ID / user ID to the primary key fingerprint
+pub fn key_fingerprint(key_id: &str) -> Result<String> {
+    with_gpg_ctx(|ctx| {
+        let key = find_single_key(ctx, key_id)?;
+        key.fingerprint()
+            .map(str::to_string)
+            .map_err(|_| AppError::Operation("Key has no valid fingerprint.".to_string()))
+    })
+}
+
+// Looks up a key by fingerprint. Only an exact match on the primary key
+// fingerprint counts, so a subkey fingerprint never "verifies" a key.
+pub fn find_key_by_fingerprint(fingerprint: &str) -> Result<Option<KeySummary>> {
+    with_gpg_ctx(|ctx| {
+        let key = match ctx.get_key(fingerprint) {
+            Ok(key) => key,
+            Err(e) if e.code() == gpgme::Error::EOF.code() => return Ok(None),
+            Err(e) => return Err(AppError::GpgME(e)),
+        };
+        let primary_fpr = key.fingerprint().unwrap_or("");
+        if !primary_fpr.eq_ignore_ascii_case(fingerprint) {
+            return Ok(None);
+        }
+        Ok(Some(KeySummary {
+            fingerprint: primary_fpr.to_string(),
+            user_ids: key
+                .user_ids()
+                .map(|uid| uid.id().unwrap_or("<invalid uid>").to_string())
+                .collect(),
+            has_secret: key.has_secret(),
+            in_keyring: true,
+        }))
+    })
+}
+
+// --- Key Certification ---
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrustLevel {
+    Marginal,
+    Full,
+}
+
+// Trust signature parameters (gpg's tsign)
+#[derive(Debug, Clone)]
+pub struct TrustSignature {
+    pub level: TrustLevel,
+    pub depth: u8,              // 1 = trusted introducer for keys it certifies directly
+    pub domain: Option<String>, // restrict to user IDs in this mail domain
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CertifyOptions {
+    pub signer: Option<String>,       // certifying key, default key if None
+    pub user_ids: Vec<String>,        // user IDs to certify, all if empty
+    pub local: bool,                  // non-exportable signature
+    pub expires: Option<SystemTime>,
+    pub trust: Option<TrustSignature>,
+}
+
+// Parses an expiry given as "0"/"never", days/weeks/months/years ("30d", "2w", "6m", "1y")
+// or plain seconds ("86400"). Returns None for no expiry.
+pub fn parse_expiry(expiry: &str) -> Result<Option<SystemTime>> {
+    let expiry = expiry.trim().to_ascii_lowercase();
+    if expiry.is_empty() || expiry == "0" || expiry == "never" {
+        return Ok(None);
+    }
+    let (number, unit_secs) = match expiry.char_indices().last() {
+        Some((i, 'd')) => (&expiry[..i], 86_400),
+        Some((i, 'w')) => (&expiry[..i], 7 * 86_400),
+        Some((i, 'm')) => (&expiry[..i], 30 * 86_400),
+        Some((i, 'y')) => (&expiry[..i], 365 * 86_400),
+        _ => (expiry.as_str(), 1),
+    };
+    let count: u64 = number
+        .parse()
+        .map_err(|_| AppError::InvalidInput(format!("Invalid expiry '{}'. Use e.g. 0, 30d, 2w, 6m or 1y.", expiry)))?;
+    count
+        .checked_mul(unit_secs)
+        .and_then(|secs| SystemTime::now().checked_add(Duration::from_secs(secs)))
+        .map(Some)
+        .ok_or_else(|| AppError::InvalidInput(format!("Expiry '{}' is too far in the future.", expiry)))
+}
+
+fn find_user_id_indices(key: &Key, user_ids: &[String]) -> Result<Vec<usize>> {
+    user_ids
+        .iter()
+        .map(|wanted| {
+            key.user_ids()
+                .position(|uid| uid.id().map(|id| id == wanted).unwrap_or(false))
+                .map(|i| i + 1) // edit-key numbers user IDs from 1
+                .ok_or_else(|| AppError::InvalidInput(format!("User ID '{}' not found on key.", wanted)))
+        })
+        .collect()
+}
+
+// Certifies (signs) another key. Plain and local signatures use gpgme's keysign,
+// trust signatures go through a scripted edit session since keysign cannot create them.
+pub fn certify_key(key_id: &str, options: &CertifyOptions) -> Result<String> {
+    policy::check_signer(Operation::Certify, options.signer.as_deref())?;
+    if let Some(trust) = &options.trust {
+        if trust.depth == 0 {
+            return Err(AppError::InvalidInput("Trust signature depth must be at least 1.".to_string()));
+        }
+        if options.expires.is_some() {
+            return Err(AppError::InvalidInput(
+                "Expiry is not supported for trust signatures.".to_string(),
+            ));
+        }
+    }
+    let result = with_gpg_ctx(|ctx| {
+        let key = find_single_key(ctx, key_id)?;
+        let fingerprint = key.fingerprint().unwrap_or("<no fpr>").to_string();
+
+        ctx.clear_signers();
+        if let Some(signer_id) = &options.signer {
+            let signer = ctx
+                .find_secret_keys([signer_id.as_str()])?
+                .next()
+                .ok_or_else(|| AppError::InvalidInput(format!("Signer secret key '{}' not found.", signer_id)))??;
+            ctx.add_signer(&signer)?;
+        }
+
+        let result = match &options.trust {
+            None => {
+                let mut flags = gpgme::KeySigningFlags::empty();
+                if options.local {
+                    flags |= gpgme::KeySigningFlags::LOCAL;
+                }
+                if options.expires.is_none() {
+                    flags |= gpgme::KeySigningFlags::NOEXPIRE;
+                }
+                if options.user_ids.len() > 1 {
+                    flags |= gpgme::KeySigningFlags::LFSEP;
+                }
+                ctx.sign_key_with_flags(&key, &options.user_ids, options.expires, flags)
+                    .map_err(AppError::from)
+            }
+            Some(trust) => {
+                let mut script = gpg_edit::EditScript::new();
+                for index in find_user_id_indices(&key, &options.user_ids)? {
+                    script = script.command(format!("uid {}", index));
+                }
+                script = script
+                    .command(if options.local { "ltsign" } else { "tsign" })
+                    .step("trustsig_prompt.trust_value", match trust.level {
+                        TrustLevel::Marginal => "1",
+                        TrustLevel::Full => "2",
+                    })
+                    .step("trustsig_prompt.trust_depth", trust.depth.to_string())
+                    .step("trustsig_prompt.trust_regexp", trust.domain.clone().unwrap_or_default())
+                    .optional("keyedit.sign_all.okay", "y")
+                    .optional("sign_uid.class", "0")
+                    .optional("sign_uid.expire", "n")
+                    .step("sign_uid.okay", "y");
+                gpg_edit::run(ctx, &key, script)
+            }
+        };
+        ctx.clear_signers();
+        result?;
+
+        Ok(format!(
+            "Key {} certified ({}{}{}).",
+            fingerprint,
+            if options.local { "local" } else { "exportable" },
+            if options.trust.is_some() { ", trust signature" } else { "" },
+            if options.user_ids.is_empty() {
+                String::new()
+            } else {
+                format!(", user IDs: {}", options.user_ids.join(", "))
+            }
+        ))
+    });
+    let mut keys = vec![key_id];
+    keys.extend(options.signer.as_deref());
+    audited_action("certify_key", &keys, None, result)
+}
+
+// Resolves an identifier to exactly one public key
+fn find_single_key(ctx: &mut Context, key_id: &str) -> Result<Key> {
+    let mut iter = ctx.find_keys([key_id])?;
+    let key = match iter.next() {
+        Some(key_result) => key_result?,
+        None => return Err(AppError::InvalidInput(format!("Key '{}' not found.", key_id))),
+    };
+    if iter.next().is_some() {
+        return Err(AppError::InvalidInput(format!(
+            "'{}' matches more than one key, use the fingerprint instead.",
+            key_id
+        )));
+    }
+    Ok(key)
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Certification {
+    pub user_id: String,
+    pub signer_key_id: String,
+    pub signer_user_id: Option<String>,
+    pub exportable: bool,
+    pub revoked: bool,
+    pub expired: bool,
+}
+
+// Lists the certifications on each user ID of a key
+pub fn list_certifications(key_id: &str) -> Result<Vec<Certification>> {
+    with_gpg_ctx(|ctx| {
+        let key = with_key_list_mode(ctx, gpgme::KeyListMode::SIGS, |ctx| find_single_key(ctx, key_id))?;
+
+        let mut certifications = Vec::new();
+        for uid in key.user_ids() {
+            for signature in uid.signatures() {
+                certifications.push(Certification {
+                    user_id: uid.id().unwrap_or("<invalid uid>").to_string(),
+                    signer_key_id: signature.signer_key_id().unwrap_or("<unknown>").to_string(),
+                    signer_user_id: signature
+                        .signer_user_id()
+                        .ok()
+                        .filter(|id| !id.is_empty())
+                        .map(str::to_string),
+                    exportable: signature.is_exportable(),
+                    revoked: signature.is_revoked(),
+                    expired: signature.is_expired(),
+                });
+            }
+        }
+        Ok(certifications)
+    })
+}
+
+// --- Trust Management ---
+
+pub fn validity_name(validity: Validity) -> &'static str {
+    match validity {
+        Validity::Unknown => "unknown",
+        Validity::Undefined => "undefined",
+        Validity::Never => "never",
+        Validity::Marginal => "marginal",
+        Validity::Full => "full",
+        Validity::Ultimate => "ultimate",
+    }
+}
+
+// Ownertrust values as offered by `gpg --edit-key` / `trust`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OwnerTrust {
+    Undefined,
+    Never,
+    Marginal,
+    Full,
+    Ultimate,
+}
+
+impl OwnerTrust {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "undefined" | "unknown" | "1" => Ok(OwnerTrust::Undefined),
+            "never" | "none" | "2" => Ok(OwnerTrust::Never),
+            "marginal" | "3" => Ok(OwnerTrust::Marginal),
+            "full" | "4" => Ok(OwnerTrust::Full),
+            "ultimate" | "5" => Ok(OwnerTrust::Ultimate),
+            other => Err(AppError::InvalidInput(format!(
+                "Invalid ownertrust '{}'. Use undefined, never, marginal, full or ultimate.",
+                other
+            ))),
+        }
+    }
+
+    fn edit_value(self) -> &'static str {
+        match self {
+            OwnerTrust::Undefined => "1",
+            OwnerTrust::Never => "2",
+            OwnerTrust::Marginal => "3",
+            OwnerTrust::Full => "4",
+            OwnerTrust::Ultimate => "5",
+        }
+    }
+}
+
+// Trust models selectable per profile (GnuPG home directory)
+pub const TRUST_MODELS: &[&str] = &["pgp", "tofu", "tofu+pgp", "always", "direct", "classic", "auto"];
+
+#[derive(Serialize, Debug, Clone)]
+pub struct UserIdValidity {
+    pub user_id: String,
+    pub validity: &'static str,
+    pub revoked: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct KeyTrust {
+    pub fingerprint: String,
+    pub owner_trust: &'static str,
+    pub user_ids: Vec<UserIdValidity>,
+}
+
+pub fn list_key_trust() -> Result<Vec<KeyTrust>> {
+    with_gpg_ctx(|ctx| {
+        let mut keys = Vec::new();
+        for key_result in ctx.keys()? {
+            let key = key_result?;
+            keys.push(KeyTrust {
+                fingerprint: key.fingerprint().unwrap_or("<no fpr>").to_string(),
+                owner_trust: validity_name(key.owner_trust()),
+                user_ids: key
+                    .user_ids()
+                    .map(|uid| UserIdValidity {
+                        user_id: uid.id().unwrap_or("<invalid uid>").to_string(),
+                        validity: validity_name(uid.validity()),
+                        revoked: uid.is_revoked(),
+                    })
+                    .collect(),
+            });
+        }
+        Ok(keys)
+    })
+}
+
+pub fn set_owner_trust(key_id: &str, trust: OwnerTrust) -> Result<String> {
+    policy::check(Operation::Trust)?;
+    let result = with_gpg_ctx(|ctx| {
+        let key = find_single_key(ctx, key_id)?;
+        let mut script = gpg_edit::EditScript::new()
+            .command("trust")
+            .step("edit_ownertrust.value", trust.edit_value());
+        if trust == OwnerTrust::Ultimate {
+            script = script.step("edit_ownertrust.set_ultimate.okay", "y");
+        }
+        gpg_edit::run(ctx, &key, script)?;
+        Ok(format!(
+            "Ownertrust of {} set to {:?}.",
+            key.fingerprint().unwrap_or("<no fpr>"),
+            trust
+        ))
+    });
+    audited_action("set_owner_trust", &[key_id], None, result)
+}
+
+// Ownertrust database in `gpg --export-ownertrust` format
+pub fn export_ownertrust() -> Result<String> {
+    let output = gpg_cli::run_gpg(&["--export-ownertrust"], None)?;
+    String::from_utf8(output.stdout).map_err(|e| AppError::Operation(format!("UTF8 Error: {}", e)))
+}
+
+// Number of entries in ownertrust data, ignoring comments
+pub fn ownertrust_entry_count(ownertrust: &str) -> usize {
+    ownertrust
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .count()
+}
+
+pub fn import_ownertrust(ownertrust: &str) -> Result<String> {
+    policy::check(Operation::Trust)?;
+    let entries = ownertrust_entry_count(ownertrust);
+    if entries == 0 {
+        return Err(AppError::InvalidInput("No ownertrust entries found.".to_string()));
+    }
+    let result = gpg_cli::run_gpg(&["--import-ownertrust"], Some(ownertrust.as_bytes()))
+        .map(|_| format!("Imported {} ownertrust entries.", entries));
+    audited_action("import_ownertrust", &[], Some(ownertrust.as_bytes()), result)
+}
+
+fn gpg_conf_path() -> Result<PathBuf> {
+    Ok(effective_home_dir()?.join("gpg.conf"))
+}
+
+// Trust model configured in the profile's gpg.conf ("pgp" is GnuPG's effective default)
+pub fn get_trust_model() -> Result<String> {
+    let conf = match std::fs::read_to_string(gpg_conf_path()?) {
+        Ok(conf) => conf,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok("pgp".to_string()),
+        Err(e) => return Err(AppError::Io(e)),
+    };
+    Ok(conf
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("trust-model"))
+        .map(|value| value.trim().to_string())
+        .last()
+        .unwrap_or_else(|| "pgp".to_string()))
+}
+
+// Stores the trust model in the profile's gpg.conf, so gpgme and gpg both use it
+pub fn set_trust_model(model: &str) -> Result<String> {
+    policy::check(Operation::Trust)?;
+    if !TRUST_MODELS.contains(&model) {
+        return Err(AppError::InvalidInput(format!(
+            "Invalid trust model '{}'. Use one of: {}.",
+            model,
+            TRUST_MODELS.join(", ")
+        )));
+    }
+    let path = gpg_conf_path()?;
+    let conf = match std::fs::read_to_string(&path) {
+        Ok(conf) => conf,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(AppError::Io(e)),
+    };
+    let mut lines: Vec<String> = conf
+        .lines()
+        .filter(|line| !line.trim().starts_with("trust-model"))
+        .map(str::to_string)
+        .collect();
+    lines.push(format!("trust-model {}", model));
+    let result = std::fs::write(&path, lines.join("\n") + "\n")
+        .map(|_| format!("Trust model set to '{}' in {}.", model, path.display()))
+        .map_err(AppError::from);
+    audited_action("set_trust_model", &[], Some(model.as_bytes()), result)
+}
+
+// Explains why GnuPG considers the user IDs of `key` valid (or not):
+// own key, trust model, TOFU history or certifications by trusted introducers.
+fn explain_key_validity(ctx: &mut Context, key: &Key, trust_model: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    if trust_model == "always" {
+        lines.push("Trust model 'always': GnuPG skips all validity checks.".to_string());
+        return lines;
+    }
+    if key.owner_trust() == Validity::Ultimate {
+        lines.push("Key has ultimate ownertrust (one of your own keys).".to_string());
+    }
+
+    for uid in key.user_ids().filter(|uid| !uid.is_revoked()) {
+        let uid_name = uid.id().unwrap_or("<invalid uid>");
+        let mut reasons = Vec::new();
+
+        if trust_model.contains("tofu") {
+            if let Some(tofu) = uid.tofu_info() {
+                reasons.push(format!(
+                    "TOFU: policy {:?}, {} verified signature(s), {} encryption(s) seen",
+                    tofu.policy(),
+                    tofu.signature_count(),
+                    tofu.encrypted_count()
+                ));
+            }
+        }
+        if trust_model.contains("pgp") || trust_model == "classic" || trust_model == "auto" {
+            for sig in uid.signatures().filter(|sig| !sig.is_revoked() && !sig.is_expired() && !sig.is_invalid()) {
+                let Ok(signer_id) = sig.signer_key_id() else { continue };
+                let Ok(signer) = ctx.get_key(signer_id) else { continue };
+                if signer.fingerprint().ok() == key.fingerprint().ok() {
+                    continue; // self-signature
+                }
+                let signer_trust = signer.owner_trust();
+                if matches!(signer_trust, Validity::Marginal | Validity::Full | Validity::Ultimate) {
+                    reasons.push(format!(
+                        "certified by {} ({}, ownertrust {})",
+                        signer.user_ids().next().and_then(|u| u.id().ok()).unwrap_or("<no user id>"),
+                        signer_id,
+                        validity_name(signer_trust)
+                    ));
+                }
+            }
+        }
+        if trust_model == "direct" {
+            reasons.push(format!("direct trust: ownertrust {}", validity_name(key.owner_trust())));
+        }
+        if reasons.is_empty() && key.owner_trust() != Validity::Ultimate {
+            reasons.push("no trust path found".to_string());
+        }
+        lines.push(format!(
+            "UID '{}': validity {}{}{}",
+            uid_name,
+            validity_name(uid.validity()),
+            if reasons.is_empty() { "" } else { " - " },
+            reasons.join("; ")
+        ));
+    }
+    lines
+}
+
+fn explain_fingerprint(ctx: &mut Context, fingerprint: &str, trust_model: &str) -> Vec<String> {
+    let extra = gpgme::KeyListMode::SIGS | gpgme::KeyListMode::WITH_TOFU;
+    match with_key_list_mode(ctx, extra, |ctx| Ok(ctx.get_key(fingerprint)?)) {
+        Ok(key) => explain_key_validity(ctx, &key, trust_model),
+        Err(e) => vec![format!("Key {} not available for trust explanation: {}", fingerprint, e)],
+    }
+}
+
+// Trust explanation for each encryption recipient
+pub fn explain_recipients(recipients: &[&str]) -> Result<Vec<String>> {
+    let trust_model = get_trust_model()?;
+    with_gpg_ctx(|ctx| {
+        let mut lines = vec![format!("Trust model: {}", trust_model)];
+        for recipient in recipients {
+            let fingerprints: Vec<String> = ctx
+                .find_keys([*recipient])?
+                .filter_map(|key| key.ok())
+                .filter_map(|key| key.fingerprint().ok().map(str::to_string))
+                .collect();
+            for fingerprint in fingerprints {
+                lines.push(format!("Recipient {} ({}):", recipient, fingerprint));
+                lines.extend(explain_fingerprint(ctx, &fingerprint, &trust_model).into_iter().map(|l| format!("  {}", l)));
+            }
+        }
+        Ok(lines)
+    })
+}
+
+// Human readable meaning of gpgme's signature summary flags
+fn describe_signature_summary(summary: gpgme::SignatureSummary) -> Vec<&'static str> {
+    use gpgme::SignatureSummary as S;
+    let descriptions = [
+        (S::VALID, "signature is fully valid"),
+        (S::GREEN, "signature is good"),
+        (S::RED, "signature is bad"),
+        (S::KEY_REVOKED, "signing key was revoked"),
+        (S::KEY_EXPIRED, "signing key has expired"),
+        (S::SIG_EXPIRED, "signature has expired"),
+        (S::KEY_MISSING, "signing key is not in the keyring"),
+        (S::BAD_POLICY, "a policy requirement was not met"),
+        (S::SYS_ERROR, "a system error occurred"),
+        (S::TOFU_CONFLICT, "TOFU conflict: another key was seen for this user ID"),
+    ];
+    let mut lines: Vec<&'static str> = descriptions
+        .iter()
+        .filter(|(flag, _)| summary.contains(*flag))
+        .map(|(_, text)| *text)
+        .collect();
+    if !summary.contains(S::VALID) && summary.contains(S::GREEN) {
+        lines.push("good signature, but the key is not fully valid under the current trust model");
+    }
+    lines
+}
+
+// --- Revocation ---
+
+// Revocation reason codes (RFC 4880, section 5.2.3.23) as numbered by gpg's prompts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevocationReason {
+    NoReason,
+    Compromised,
+    Superseded,
+    NoLongerUsed,
+    UserIdInvalid, // user IDs only
+}
+
+impl RevocationReason {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "0" | "none" | "no_reason" => Ok(RevocationReason::NoReason),
+            "1" | "compromised" => Ok(RevocationReason::Compromised),
+            "2" | "superseded" => Ok(RevocationReason::Superseded),
+            "3" | "no_longer_used" | "retired" => Ok(RevocationReason::NoLongerUsed),
+            "4" | "uid_invalid" => Ok(RevocationReason::UserIdInvalid),
+            other => Err(AppError::InvalidInput(format!(
+                "Invalid revocation reason '{}'. Use none, compromised, superseded, no_longer_used or uid_invalid.",
+                other
+            ))),
+        }
+    }
+
+    // Answer for gpg's ask_revocation_reason.code prompt
+    fn code(self, for_user_id: bool) -> Result<&'static str> {
+        match (self, for_user_id) {
+            (RevocationReason::NoReason, _) => Ok("0"),
+            (RevocationReason::Compromised, false) => Ok("1"),
+            (RevocationReason::Superseded, false) => Ok("2"),
+            (RevocationReason::NoLongerUsed, false) => Ok("3"),
+            (RevocationReason::UserIdInvalid, true) => Ok("4"),
+            (reason, true) => Err(AppError::InvalidInput(format!(
+                "Reason {:?} does not apply to user IDs (use none or uid_invalid).",
+                reason
+            ))),
+            (reason, false) => Err(AppError::InvalidInput(format!(
+                "Reason {:?} only applies to user IDs.",
+                reason
+            ))),
+        }
+    }
+}
+
+// Description lines for the revocation reason; an empty line ends gpg's input
+fn revocation_description_lines(description: &str) -> Vec<String> {
+    let mut lines: Vec<String> = description
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect();
+    lines.push(String::new());
+    lines
+}
+
+fn add_revocation_reason(
+    mut script: gpg_edit::EditScript,
+    code: &'static str,
+    description: &str,
+) -> gpg_edit::EditScript {
+    script = script.step("ask_revocation_reason.code", code);
+    for line in revocation_description_lines(description) {
+        script = script.step("ask_revocation_reason.text", line);
+    }
+    script.step("ask_revocation_reason.okay", "y")
+}
+
+// Generates an armored revocation certificate for one of our own keys without applying it
+pub fn generate_revocation_cert(key_id: &str, reason: RevocationReason, description: &str) -> Result<String> {
+    policy::check(Operation::Revoke)?;
+    let fingerprint = key_fingerprint(key_id)?;
+    let result = revocation_cert(&fingerprint, reason, description);
+    audited("generate_revocation_cert", &[&fingerprint], None, result)
+}
+
+fn revocation_cert(fingerprint: &str, reason: RevocationReason, description: &str) -> Result<String> {
+    let mut answers = vec!["y".to_string(), reason.code(false)?.to_string()];
+    answers.extend(revocation_description_lines(description));
+    answers.push("y".to_string());
+    let output = gpg_cli::run_gpg_with_answers(&["--armor", "--gen-revoke", fingerprint], &answers)?;
+    let cert = String::from_utf8(output.stdout).map_err(|e| AppError::Operation(format!("UTF8 Error: {}", e)))?;
+    if !cert.contains("-----BEGIN PGP PUBLIC KEY BLOCK-----") {
+        return Err(AppError::Operation("gpg did not produce a revocation certificate.".to_string()));
+    }
+    Ok(cert)
+}
+
+// The revocation certificate GnuPG stored in openpgp-revocs.d when the key was created
+pub fn stored_revocation_cert(key_id: &str) -> Result<String> {
+    policy::check(Operation::Revoke)?;
+    let fingerprint = key_fingerprint(key_id)?;
+    let path = effective_home_dir()?
+        .join("openpgp-revocs.d")
+        .join(format!("{}.rev", fingerprint));
+    let content = std::fs::read_to_string(&path).map_err(|e| {
+        AppError::InvalidInput(format!("No stored revocation certificate at {}: {}", path.display(), e))
+    })?;
+    // GnuPG prefixes the armor lines with ':' so the file cannot be imported by accident
+    let start = content
+        .find(":-----BEGIN PGP PUBLIC KEY BLOCK-----")
+        .ok_or_else(|| AppError::Operation(format!("Unexpected format in {}", path.display())))?;
+    Ok(content[start..]
+        .lines()
+        .map(|line| line.strip_prefix(':').unwrap_or(line))
+        .collect::<Vec<_>>()
+        .join("\n")
+        + "\n")
+}
+
+// Puts a revocation certificate into openpgp-revocs.d without applying it (e.g. when
+// restoring a backup). The BEGIN line is escaped with ':' the way GnuPG stores them.
+pub fn store_revocation_cert(fingerprint: &str, cert: &str) -> Result<bool> {
+    // The fingerprint becomes the file name, so nothing but a v4 or v6 fingerprint
+    if !matches!(fingerprint.len(), 40 | 64) || !fingerprint.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(AppError::InvalidInput(format!("Invalid fingerprint for a revocation certificate: {}", fingerprint)));
+    }
+    let dir = effective_home_dir()?.join("openpgp-revocs.d");
+    let path = dir.join(format!("{}.rev", fingerprint.to_ascii_uppercase()));
+    if path.exists() {
+        return Ok(false); // keep the certificate GnuPG created with the key
+    }
+    std::fs::create_dir_all(&dir)?;
+    let escaped = cert.replacen("-----BEGIN PGP PUBLIC KEY BLOCK-----", ":-----BEGIN PGP PUBLIC KEY BLOCK-----", 1);
+    std::fs::write(
+        &path,
+        format!(
+            "This is a revocation certificate for the OpenPGP key {}.\n\
+             Remove the ':' at the start of the BEGIN line to use it.\n\n{}",
+            fingerprint, escaped
+        ),
+    )?;
+    Ok(true)
+}
+
+// Revokes a whole key by generating a revocation certificate and importing it
+pub fn revoke_key(key_id: &str, reason: RevocationReason, description: &str) -> Result<String> {
+    policy::check(Operation::Revoke)?;
+    let cert = generate_revocation_cert(key_id, reason, description)?;
+    let result = import_key(&cert).map(|_| format!("Key {} revoked ({:?}).", key_id, reason));
+    audited_action("revoke_key", &[key_id], None, result)
+}
+
+pub fn revoke_subkey(key_id: &str, subkey_fpr: &str, reason: RevocationReason, description: &str) -> Result<String> {
+    policy::check(Operation::Revoke)?;
+    let code = reason.code(false)?;
+    let result = with_gpg_ctx(|ctx| {
+        let key = find_single_key(ctx, key_id)?;
+        // Position in subkeys() matches edit-key's "key N" numbering (the primary key is 0)
+        let index = key
+            .subkeys()
+            .position(|sk| sk.fingerprint().map(|f| f.eq_ignore_ascii_case(subkey_fpr)).unwrap_or(false))
+            .filter(|&i| i > 0)
+            .ok_or_else(|| AppError::InvalidInput(format!("Subkey {} not found on key {}.", subkey_fpr, key_id)))?;
+        let script = gpg_edit::EditScript::new()
+            .command(format!("key {}", index))
+            .command("revkey")
+            .step("keyedit.revoke.subkey.okay", "y");
+        gpg_edit::run(ctx, &key, add_revocation_reason(script, code, description))?;
+        Ok(format!("Subkey {} revoked ({:?}).", subkey_fpr, reason))
+    });
+    audited_action("revoke_subkey", &[key_id, subkey_fpr], None, result)
+}
+
+pub fn revoke_user_id(key_id: &str, user_id: &str, reason: RevocationReason, description: &str) -> Result<String> {
+    policy::check(Operation::Revoke)?;
+    let code = reason.code(true)?;
+    let result = with_gpg_ctx(|ctx| {
+        let key = find_single_key(ctx, key_id)?;
+        let index = find_user_id_indices(&key, &[user_id.to_string()])?[0];
+        let script = gpg_edit::EditScript::new()
+            .command(format!("uid {}", index))
+            .command("revuid")
+            .step("keyedit.revoke.uid.okay", "y");
+        gpg_edit::run(ctx, &key, add_revocation_reason(script, code, description))?;
+        Ok(format!("User ID '{}' revoked ({:?}).", user_id, reason))
+    });
+    audited_action("revoke_user_id", &[key_id], None, result)
+}
+
+// --- Subkey Management ---
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubkeyUsage {
+    Sign,
+    Encrypt,
+    Authenticate,
+}
 
-        if secret {
-             // Need passphrase handling here if key is protected
-             println!("Warning: Exporting secret keys requires careful handling.");
-            ctx.export_secret_keys_writer(&[key_id], &mut armored_output)?;
+impl SubkeyUsage {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "sign" | "s" => Ok(SubkeyUsage::Sign),
+            "encrypt" | "encr" | "e" => Ok(SubkeyUsage::Encrypt),
+            "auth" | "authenticate" | "a" => Ok(SubkeyUsage::Authenticate),
+            other => Err(AppError::InvalidInput(format!(
+                "Invalid subkey usage '{}'. Use sign, encrypt or auth.",
+                other
+            ))),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SubkeyInfo {
+    pub fingerprint: String,
+    pub key_id: String,
+    pub algorithm: String,       // e.g. "ed25519", "rsa4096"
+    pub usage: String,           // capability letters as in gpg listings: S, E, A, C
+    pub created: Option<u64>,    // seconds since the epoch
+    pub expires: Option<u64>,    // None = never expires
+    pub is_primary: bool,
+    pub has_secret: bool,        // secret part available in this keyring (not offline or on a card)
+    pub revoked: bool,
+    pub expired: bool,
+    pub live: bool,              // neither revoked, expired, disabled nor invalid
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+// Formats seconds since the epoch as a UTC date (YYYY-MM-DD)
+pub fn format_date(secs: u64) -> String {
+    // Civil-from-days conversion (proleptic Gregorian calendar)
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn subkey_info(subkey: &gpgme::Subkey<'_>, is_primary: bool) -> SubkeyInfo {
+    let mut usage = String::new();
+    for (capable, letter) in [
+        (subkey.can_sign(), 'S'),
+        (subkey.can_encrypt(), 'E'),
+        (subkey.can_authenticate(), 'A'),
+        (subkey.can_certify(), 'C'),
+    ] {
+        if capable {
+            usage.push(letter);
+        }
+    }
+    let revoked = subkey.is_revoked();
+    let expired = subkey.is_expired();
+    SubkeyInfo {
+        fingerprint: subkey.fingerprint().unwrap_or("<no fpr>").to_string(),
+        key_id: subkey.id().unwrap_or("<no id>").to_string(),
+        algorithm: subkey
+            .algorithm_name()
+            .unwrap_or_else(|_| format!("{:?}", subkey.algorithm())),
+        usage,
+        created: subkey.creation_time().map(unix_secs),
+        expires: subkey.expiration_time().map(unix_secs),
+        is_primary,
+        has_secret: subkey.is_secret(),
+        revoked,
+        expired,
+        live: !(revoked || expired || subkey.is_disabled() || subkey.is_invalid()),
+    }
+}
+
+// Short status for key listings, e.g. "live until 2026-05-01", "revoked"
+pub fn subkey_status(info: &SubkeyInfo) -> String {
+    if info.revoked {
+        "revoked".to_string()
+    } else if info.expired {
+        format!("expired {}", info.expires.map(format_date).unwrap_or_default())
+    } else if !info.live {
+        "unusable".to_string()
+    } else {
+        match info.expires {
+            Some(expires) => format!("live until {}", format_date(expires)),
+            None => "live, no expiry".to_string(),
+        }
+    }
+}
+
+// Primary key first, followed by its subkeys in keyring order
+pub fn list_subkeys(key_id: &str) -> Result<Vec<SubkeyInfo>> {
+    with_gpg_ctx(|ctx| {
+        let key = find_single_key(ctx, key_id)?;
+        // Secret key listing tells us which secret parts are present
+        let secret = ctx.find_secret_keys([key.fingerprint().unwrap_or(key_id)])?.next().transpose()?;
+        let key = secret.as_ref().unwrap_or(&key);
+        Ok(key.subkeys().enumerate().map(|(i, sk)| subkey_info(&sk, i == 0)).collect())
+    })
+}
+
+// Seconds from now until `expires`, as gpgme expects; zero means "never expires"
+fn expiry_from_now(expires: Option<SystemTime>) -> Result<Duration> {
+    match expires {
+        None => Ok(Duration::ZERO),
+        Some(time) => time
+            .duration_since(SystemTime::now())
+            .map_err(|_| AppError::InvalidInput("Expiry must be in the future.".to_string())),
+    }
+}
+
+// Adds a subkey. Needs the primary secret key, so run it on the machine holding the offline primary.
+pub fn add_subkey(key_id: &str, algorithm: &str, usage: SubkeyUsage, expires: Option<SystemTime>) -> Result<String> {
+    policy::check(Operation::EditKey)?;
+    let algorithm = match algorithm.trim() {
+        "" => "default",
+        algorithm => algorithm,
+    };
+    let duration = expiry_from_now(expires)?;
+    let result = with_gpg_ctx(|ctx| {
+        let key = find_single_key(ctx, key_id)?;
+        let mut flags = match usage {
+            SubkeyUsage::Sign => gpgme::CreateKeyFlags::SIGN,
+            SubkeyUsage::Encrypt => gpgme::CreateKeyFlags::ENCR,
+            SubkeyUsage::Authenticate => gpgme::CreateKeyFlags::AUTH,
+        };
+        if expires.is_none() {
+            flags |= gpgme::CreateKeyFlags::NOEXPIRE;
+        }
+        let result = ctx.create_subkey_with_flags(&key, algorithm, duration, flags)?;
+        Ok(format!(
+            "Added {:?} subkey ({}) {} to key {}.",
+            usage,
+            algorithm,
+            result.fingerprint().unwrap_or("<unknown fpr>"),
+            key.fingerprint().unwrap_or(key_id)
+        ))
+    });
+    audited_action("add_subkey", &[key_id], None, result)
+}
+
+// Sets the expiry of the primary key (no subkeys given) or of the listed subkeys.
+// Extending an expired key makes it usable again.
+pub fn set_expiry(key_id: &str, subkey_fprs: &[String], expires: Option<SystemTime>) -> Result<String> {
+    policy::check(Operation::EditKey)?;
+    let duration = expiry_from_now(expires)?;
+    let result = with_gpg_ctx(|ctx| {
+        let key = find_single_key(ctx, key_id)?;
+        let primary_fpr = key.fingerprint().unwrap_or(key_id).to_string();
+        let mut targets = Vec::new();
+        for wanted in subkey_fprs {
+            let subkey = key
+                .subkeys()
+                .skip(1)
+                .find(|sk| sk.fingerprint().map(|f| f.eq_ignore_ascii_case(wanted)).unwrap_or(false))
+                .ok_or_else(|| AppError::InvalidInput(format!("Subkey {} not found on key {}.", wanted, primary_fpr)))?;
+            targets.push(subkey.fingerprint().unwrap_or(wanted).to_string());
+        }
+        ctx.set_expire(&key, duration, targets.iter().map(String::as_str))?;
+        let when = match expires {
+            Some(time) => format!("expires {}", format_date(unix_secs(time))),
+            None => "never expires".to_string(),
+        };
+        Ok(if targets.is_empty() {
+            format!("Primary key {} now {}.", primary_fpr, when)
         } else {
-            ctx.export_keys_writer(&[key_id], &mut armored_output)?;
+            format!("Subkeys {} now {}.", targets.join(", "), when)
+        })
+    });
+    audited_action("set_expiry", &[key_id], None, result)
+}
+
+// --- User ID Management ---
+
+pub fn add_user_id(key_id: &str, user_id: &str) -> Result<String> {
+    policy::check(Operation::EditKey)?;
+    let user_id = user_id.trim();
+    if user_id.is_empty() {
+        return Err(AppError::InvalidInput("User ID must not be empty.".to_string()));
+    }
+    let result = with_gpg_ctx(|ctx| {
+        let key = find_single_key(ctx, key_id)?;
+        if key.user_ids().any(|uid| uid.id().map(|id| id == user_id).unwrap_or(false)) {
+            return Err(AppError::InvalidInput(format!("Key already has user ID '{}'.", user_id)));
+        }
+        ctx.add_uid(&key, user_id)?;
+        Ok(format!("User ID '{}' added to key {}.", user_id, key.fingerprint().unwrap_or(key_id)))
+    });
+    audited_action("add_user_id", &[key_id], None, result)
+}
+
+// Marks a user ID as primary (newest self-signature wins, so this simply re-signs it)
+pub fn set_primary_user_id(key_id: &str, user_id: &str) -> Result<String> {
+    policy::check(Operation::EditKey)?;
+    let result = with_gpg_ctx(|ctx| {
+        let key = find_single_key(ctx, key_id)?;
+        let uid = key
+            .user_ids()
+            .find(|uid| uid.id().map(|id| id == user_id).unwrap_or(false))
+            .ok_or_else(|| AppError::InvalidInput(format!("User ID '{}' not found on key.", user_id)))?;
+        if uid.is_revoked() {
+            return Err(AppError::InvalidInput(format!("User ID '{}' is revoked.", user_id)));
         }
+        ctx.set_uid_flag(&key, user_id, "primary", None::<&str>)?;
+        Ok(format!("'{}' is now the primary user ID of {}.", user_id, key.fingerprint().unwrap_or(key_id)))
+    });
+    audited_action("set_primary_user_id", &[key_id], None, result)
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct KeyDetails {
+    pub fingerprint: String,
+    pub owner_trust: &'static str,
+    pub has_secret: bool,
+    pub revoked: bool,
+    pub expired: bool,
+    pub user_ids: Vec<UserIdValidity>, // primary user ID first
+    pub subkeys: Vec<SubkeyInfo>,      // primary key first
+    pub validity_notes: Vec<String>,   // why the key is (not) valid under the current trust model
+}
+
+pub fn key_details(key_id: &str) -> Result<KeyDetails> {
+    let trust_model = get_trust_model()?;
+    let subkeys = list_subkeys(key_id)?;
+    with_gpg_ctx(|ctx| {
+        let extra = gpgme::KeyListMode::SIGS | gpgme::KeyListMode::WITH_TOFU;
+        let key = with_key_list_mode(ctx, extra, |ctx| find_single_key(ctx, key_id))?;
+        Ok(KeyDetails {
+            fingerprint: key.fingerprint().unwrap_or("<no fpr>").to_string(),
+            owner_trust: validity_name(key.owner_trust()),
+            has_secret: subkeys.iter().any(|sk| sk.has_secret),
+            revoked: key.is_revoked(),
+            expired: key.is_expired(),
+            user_ids: key
+                .user_ids()
+                .map(|uid| UserIdValidity {
+                    user_id: uid.id().unwrap_or("<invalid uid>").to_string(),
+                    validity: validity_name(uid.validity()),
+                    revoked: uid.is_revoked(),
+                })
+                .collect(),
+            subkeys,
+            validity_notes: explain_key_validity(ctx, &key, &trust_model),
+        })
+    })
+}
+
+pub fn export_key(key_id: &str) -> Result<String> {
+    policy::check(Operation::Export)?;
+    let result = with_gpg_ctx(|ctx| {
+        let mut output = Vec::new();
+        let mut armored_output = Data::from_armor_writer(&mut output)?;
+        ctx.export_keys_writer(&[key_id], &mut armored_output)?;
         drop(armored_output); // Ensure data is flushed
 
         String::from_utf8(output).map_err(|e| AppError::Operation(format!("UTF8 Error: {}", e)))
-    })
+    });
+    audited("export_key", &[key_id], None, result)
+}
+
+// Plain armored secret key; only export_secret_protected hands it out (encrypted)
+fn export_secret_armored(fingerprint: &str, passphrase: &str) -> Result<Zeroizing<String>> {
+    policy::check(Operation::ExportSecret)?;
+    let result = export_secret_proven(fingerprint, &["--armor", "--export-secret-keys", fingerprint], passphrase)
+        .and_then(|output| {
+            String::from_utf8(output.to_vec())
+                .map(Zeroizing::new)
+                .map_err(|e| AppError::Operation(format!("UTF8 Error: {}", e)))
+        });
+    audited("export_secret_key", &[fingerprint], None, result)
+}
+
+// How a secret key export is protected before it is shown or leaves the machine
+pub enum ExportProtection {
+    Recipient(String),             // encrypted to this public key
+    Passphrase(Zeroizing<String>), // symmetric, at least 12 characters
+}
+
+impl ExportProtection {
+    // From the usual form/CLI fields: a recipient, or else a passphrase
+    pub fn choose(recipient: Option<&str>, passphrase: Option<Zeroizing<String>>) -> Result<Self> {
+        match (recipient.map(str::trim).filter(|r| !r.is_empty()), passphrase.filter(|p| !p.is_empty())) {
+            (Some(recipient), _) => Ok(ExportProtection::Recipient(recipient.to_string())),
+            (None, Some(passphrase)) => Ok(ExportProtection::Passphrase(passphrase)),
+            (None, None) => Err(AppError::InvalidInput(
+                "Secret keys are only exported encrypted: give a recipient key or an export passphrase.".to_string(),
+            )),
+        }
+    }
+}
+
+// Runs a gpg secret key export (`args`) that only succeeds for the holder of the key's passphrase.
+// Over loopback gpg-agent asks for the passphrase on every export, even when it has it cached,
+// and does not cache the answer. Keys without a passphrase are refused: there is nothing to prove.
+fn export_secret_proven(fingerprint: &str, args: &[&str], passphrase: &str) -> Result<Zeroizing<Vec<u8>>> {
+    let export = |passphrase: &str| -> Result<Option<Zeroizing<Vec<u8>>>> {
+        let mut full_args = vec!["--pinentry-mode", "loopback", "--passphrase-fd", "0"];
+        full_args.extend_from_slice(args);
+        let input = Zeroizing::new(format!("{}\n", passphrase));
+        let output = gpg_cli::run_gpg_unchecked(&full_args, Some(input.as_bytes()))?;
+        let stdout = Zeroizing::new(output.stdout);
+        // A bad passphrase skips the key ("ERROR export_keys.secret") and may still exit 0
+        let refused = output.status.iter().any(|line| line.starts_with("ERROR export_keys"));
+        Ok((output.success && !refused && !stdout.is_empty()).then_some(stdout))
+    };
+    let decoy = format!("{:032x}", rand::random::<u128>());
+    if export(&decoy)?.is_some() {
+        return Err(AppError::InvalidInput(format!(
+            "Secret key {} has no passphrase; protect it (gpg --passwd) before exporting it.",
+            fingerprint
+        )));
+    }
+    export(passphrase)?
+        .ok_or_else(|| AppError::InvalidInput(format!("Wrong passphrase for the secret key {}.", fingerprint)))
+}
+
+// The only way secret keys leave the keyring as text: after the key's passphrase was proven,
+// encrypted to a recipient or a passphrase. `subkeys` selects a secret-subkeys-only export
+// (empty = all subkeys); None exports the whole secret key.
+pub fn export_secret_protected(
+    key_id: &str,
+    subkeys: Option<&[String]>,
+    key_passphrase: &str,
+    protection: &ExportProtection,
+) -> Result<String> {
+    policy::check(Operation::ExportSecret)?;
+    if let ExportProtection::Passphrase(passphrase) = protection {
+        if passphrase.chars().count() < 12 {
+            return Err(AppError::InvalidInput("Export passphrase must have at least 12 characters.".to_string()));
+        }
+    }
+    let fingerprint = key_fingerprint(key_id)?;
+    let exported = match subkeys {
+        Some(subkeys) => Zeroizing::new(export_secret_subkeys(&fingerprint, subkeys, key_passphrase)?),
+        None => export_secret_armored(&fingerprint, key_passphrase)?,
+    };
+    match protection {
+        ExportProtection::Recipient(recipient) => encrypt(&exported, &[recipient]),
+        ExportProtection::Passphrase(passphrase) => encrypt_symmetric(exported.as_bytes(), passphrase),
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExportFilter {
+    pub minimal: bool,              // export-minimal: only the newest self-signatures, no third-party certifications
+    pub clean: bool,                // export-clean: drop unusable signatures and user IDs
+    pub drop_user_ids: Vec<String>, // user IDs to leave out
+    pub subkeys: Vec<String>,       // subkey fingerprints to keep, empty = all
+}
+
+// Exports a public key reduced to what the receiver needs, typically to fit one QR code
+pub fn export_key_filtered(key_id: &str, filter: &ExportFilter) -> Result<String> {
+    policy::check(Operation::Export)?;
+    let (primary_fpr, user_ids, subkey_fprs) = with_gpg_ctx(|ctx| {
+        let key = find_single_key(ctx, key_id)?;
+        Ok((
+            key.fingerprint().unwrap_or(key_id).to_string(),
+            key.user_ids().filter_map(|uid| uid.id().ok().map(str::to_string)).collect::<Vec<_>>(),
+            key.subkeys().skip(1).filter_map(|sk| sk.fingerprint().ok().map(str::to_string)).collect::<Vec<_>>(),
+        ))
+    })?;
+
+    let mut args = vec!["--armor".to_string(), "--export".to_string()];
+    let export_options: Vec<&str> = [(filter.minimal, "export-minimal"), (filter.clean, "export-clean")]
+        .iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, option)| *option)
+        .collect();
+    if !export_options.is_empty() {
+        args.push("--export-options".to_string());
+        args.push(export_options.join(","));
+    }
+
+    if !filter.drop_user_ids.is_empty() {
+        for dropped in &filter.drop_user_ids {
+            if !user_ids.contains(dropped) {
+                return Err(AppError::InvalidInput(format!("User ID '{}' not found on key.", dropped)));
+            }
+        }
+        // gpg can only keep user IDs, so keep everything that is not dropped
+        let kept: Vec<&String> = user_ids.iter().filter(|uid| !filter.drop_user_ids.contains(uid)).collect();
+        if kept.is_empty() {
+            return Err(AppError::InvalidInput("At least one user ID must be kept.".to_string()));
+        }
+        if kept.iter().any(|uid| uid.contains("||")) {
+            return Err(AppError::InvalidInput("User IDs containing '||' cannot be filtered.".to_string()));
+        }
+        let expression: Vec<String> = kept.iter().map(|uid| format!("uid = {}", uid)).collect();
+        args.push("--export-filter".to_string());
+        args.push(format!("keep-uid={}", expression.join(" || ")));
+    }
+
+    if filter.subkeys.is_empty() {
+        args.push(primary_fpr);
+    } else {
+        for wanted in &filter.subkeys {
+            let fpr = subkey_fprs
+                .iter()
+                .find(|fpr| fpr.eq_ignore_ascii_case(wanted.trim()))
+                .ok_or_else(|| AppError::InvalidInput(format!("Subkey {} not found on key {}.", wanted, primary_fpr)))?;
+            args.push(format!("{}!", fpr)); // "!" exports the primary key with just this subkey
+        }
+    }
+
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let result = gpg_cli::run_gpg(&args, None)
+        .and_then(|output| String::from_utf8(output.stdout).map_err(|e| AppError::Operation(format!("UTF8 Error: {}", e))));
+    audited("export_key", &[key_id], None, result)
+}
+
+// Binary secret key export for paper keys and share cards, which encode it themselves.
+// Like export_secret_protected, only for the holder of the key's passphrase.
+pub fn export_secret_key_binary(fingerprint: &str, key_passphrase: &str) -> Result<Zeroizing<Vec<u8>>> {
+    policy::check(Operation::ExportSecret)?;
+    let result = export_secret_proven(fingerprint, &["--export-secret-keys", fingerprint], key_passphrase);
+    audited("export_secret_key", &[fingerprint], None, result)
+}
+
+// Exports only the secret subkeys, with the primary secret key replaced by a stub
+// (gpg --export-secret-subkeys), for daily-use machines in an offline-primary setup.
+// Selected subkeys are exported with gpg's "!" suffix; none selected = all subkeys.
+fn export_secret_subkeys(key_id: &str, subkey_fprs: &[String], passphrase: &str) -> Result<String> {
+    policy::check(Operation::ExportSecret)?;
+    let subkeys = list_subkeys(key_id)?;
+    let primary = subkeys
+        .first()
+        .ok_or_else(|| AppError::Operation(format!("Key '{}' has no primary key.", key_id)))?;
+    if !subkeys.iter().skip(1).any(|sk| sk.has_secret) {
+        return Err(AppError::InvalidInput(format!(
+            "No secret subkeys of {} are available in this keyring.",
+            primary.fingerprint
+        )));
+    }
+
+    let mut targets = Vec::new();
+    for wanted in subkey_fprs {
+        let subkey = subkeys
+            .iter()
+            .skip(1)
+            .find(|sk| sk.fingerprint.eq_ignore_ascii_case(wanted.trim()))
+            .ok_or_else(|| AppError::InvalidInput(format!("Subkey {} not found on key {}.", wanted, primary.fingerprint)))?;
+        if !subkey.has_secret {
+            return Err(AppError::InvalidInput(format!(
+                "Secret part of subkey {} is not available.",
+                subkey.fingerprint
+            )));
+        }
+        targets.push(format!("{}!", subkey.fingerprint));
+    }
+    if targets.is_empty() {
+        targets.push(primary.fingerprint.clone());
+    }
+
+    let mut args = vec!["--armor", "--export-secret-subkeys"];
+    args.extend(targets.iter().map(String::as_str));
+    let result = export_secret_proven(&primary.fingerprint, &args, passphrase)
+        .and_then(|output| String::from_utf8(output.to_vec()).map_err(|e| AppError::Operation(format!("UTF8 Error: {}", e))));
+    audited("export_secret_subkeys", &[key_id], None, result)
 }
 
 pub fn import_key(key_data: &str) -> Result<String> {
-     with_gpg_ctx(|ctx| {
+    policy::check(Operation::Import)?;
+    let result = with_gpg_ctx(|ctx| {
          let mut input_data = Data::from_bytes(key_data.as_bytes())?;
          let import_result = ctx.import(&mut input_data)?;
          // Provide more detailed result information
@@ -104,7 +1476,221 @@ pub fn import_key(key_data: &str) -> Result<String> {
              import_result.new_user_ids(),
              import_result.new_sub_keys()
          ))
-     })
+    });
+    audited_action("import_key", &[], Some(key_data.as_bytes()), result)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ImportOptions {
+    pub keep_ownertrust: bool,      // keep-ownertrust: don't touch ownertrust values
+    pub clean: bool,                // import-clean: drop unusable signatures and user IDs
+    pub restore: bool,              // import-restore: restore a backup, including ownertrust
+    pub keep_user_ids: Vec<String>, // import only these user IDs, empty = all
+    pub fingerprints: Vec<String>,  // import only these keys, empty = all
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ImportStatus {
+    pub fingerprint: String,
+    pub user_id: Option<String>,
+    pub changes: Vec<&'static str>, // new_key, new_user_ids, new_signatures, new_subkeys, secret_imported
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ImportReport {
+    pub keys: Vec<ImportStatus>,
+    pub skipped: Vec<String>, // fingerprints left out by the fingerprint filter
+}
+
+impl ImportReport {
+    pub fn describe(&self) -> String {
+        let mut lines: Vec<String> = self
+            .keys
+            .iter()
+            .map(|key| {
+                let outcome = match &key.error {
+                    Some(error) => format!("ERROR: {}", error),
+                    None if key.changes.is_empty() => "unchanged".to_string(),
+                    None => key.changes.join(", "),
+                };
+                format!("{} {}: {}", key.fingerprint, key.user_id.as_deref().unwrap_or(""), outcome)
+            })
+            .collect();
+        lines.extend(self.skipped.iter().map(|fpr| format!("{}: skipped (not selected)", fpr)));
+        if lines.is_empty() {
+            lines.push("No keys imported.".to_string());
+        }
+        lines.join("\n")
+    }
+}
+
+// IMPORT_OK flag bits (doc/DETAILS in GnuPG)
+const IMPORT_FLAGS: &[(u32, &str)] = &[
+    (1, "new_key"),
+    (2, "new_user_ids"),
+    (4, "new_signatures"),
+    (8, "new_subkeys"),
+    (16, "secret_imported"),
+];
+
+fn import_problem(reason: &str) -> &'static str {
+    match reason {
+        "1" => "invalid certificate",
+        "2" => "issuer certificate missing",
+        "3" => "certificate chain too long",
+        "4" => "error storing certificate",
+        _ => "not imported",
+    }
+}
+
+// Splits dearmored key data into one packet sequence per key (each starts at a primary key packet)
+fn split_keys(key_data: &str) -> Result<Vec<Vec<u8>>> {
+    let blocks = armor::find_armor_blocks(key_data);
+    if blocks.is_empty() {
+        return Err(AppError::InvalidInput("Key selection needs ASCII-armored key data.".to_string()));
+    }
+    let mut keys: Vec<Vec<u8>> = Vec::new();
+    for block in blocks {
+        let data = armor::parse_armor(block)?.data;
+        let mut offset = 0;
+        for packet in classify::parse_packets(&data) {
+            let len = packet.header_len + packet.length.unwrap_or(0);
+            if matches!(packet.packet_type, classify::PacketType::PublicKey | classify::PacketType::SecretKey) {
+                keys.push(Vec::new());
+            }
+            match keys.last_mut() {
+                Some(key) => key.extend_from_slice(&data[offset..offset + len]),
+                None => return Err(AppError::InvalidInput("Key data does not start with a key packet.".to_string())),
+            }
+            offset += len;
+        }
+        if offset != data.len() {
+            return Err(AppError::InvalidInput("Key data contains malformed packets.".to_string()));
+        }
+    }
+    Ok(keys)
+}
+
+// Imports keys with options and reports per key what changed
+pub fn import_keys(key_data: &str, options: &ImportOptions) -> Result<ImportReport> {
+    policy::check(Operation::Import)?;
+    let previews = preview_keys(key_data.as_bytes())?;
+    if previews.is_empty() {
+        return Err(AppError::InvalidInput("No keys found in the data.".to_string()));
+    }
+
+    let mut skipped = Vec::new();
+    let input: Zeroizing<Vec<u8>> = if options.fingerprints.is_empty() {
+        Zeroizing::new(key_data.as_bytes().to_vec())
+    } else {
+        // gpg has no key-level import filter: cut the selected keys out of the data
+        let segments = split_keys(key_data)?;
+        if segments.len() != previews.len() {
+            return Err(AppError::InvalidInput(
+                "Could not match the keys in the data to their fingerprints.".to_string(),
+            ));
+        }
+        let mut selected = Zeroizing::new(Vec::new());
+        for (preview, segment) in previews.iter().zip(segments) {
+            if options.fingerprints.iter().any(|fpr| fpr.trim().eq_ignore_ascii_case(&preview.fingerprint)) {
+                selected.extend_from_slice(&segment);
+            } else {
+                skipped.push(preview.fingerprint.clone());
+            }
+        }
+        if selected.is_empty() {
+            return Err(AppError::InvalidInput("None of the selected fingerprints are in the data.".to_string()));
+        }
+        selected
+    };
+
+    let mut import_options = Vec::new();
+    for (enabled, option) in [
+        (options.keep_ownertrust, "keep-ownertrust"),
+        (options.clean, "import-clean"),
+        (options.restore, "import-restore"),
+    ] {
+        if enabled {
+            import_options.push(option);
+        }
+    }
+    let mut args = vec!["--import".to_string()];
+    if !import_options.is_empty() {
+        args.push("--import-options".to_string());
+        args.push(import_options.join(","));
+    }
+    if !options.keep_user_ids.is_empty() {
+        if options.keep_user_ids.iter().any(|uid| uid.contains("||")) {
+            return Err(AppError::InvalidInput("User IDs containing '||' cannot be filtered.".to_string()));
+        }
+        let expression: Vec<String> = options.keep_user_ids.iter().map(|uid| format!("uid = {}", uid.trim())).collect();
+        args.push("--import-filter".to_string());
+        args.push(format!("keep-uid={}", expression.join(" || ")));
+    }
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let output = gpg_cli::run_gpg_unchecked(&args, Some(input.as_slice()))?;
+
+    let user_id_of = |fpr: &str| {
+        previews
+            .iter()
+            .find(|p| p.fingerprint.eq_ignore_ascii_case(fpr))
+            .and_then(|p| p.user_ids.first().cloned())
+    };
+    let mut keys: Vec<ImportStatus> = Vec::new();
+    for line in &output.status {
+        let mut fields = line.split_whitespace();
+        match (fields.next(), fields.next(), fields.next()) {
+            (Some("IMPORT_OK"), Some(flags), Some(fpr)) => {
+                let flags: u32 = flags.parse().unwrap_or(0);
+                // gpg reports a key once for the public and once for the secret part
+                let changes: Vec<&'static str> =
+                    IMPORT_FLAGS.iter().filter(|(bit, _)| flags & bit != 0).map(|(_, name)| *name).collect();
+                match keys.iter_mut().find(|k| k.fingerprint == fpr) {
+                    Some(existing) => {
+                        for change in changes {
+                            if !existing.changes.contains(&change) {
+                                existing.changes.push(change);
+                            }
+                        }
+                    }
+                    None => keys.push(ImportStatus {
+                        fingerprint: fpr.to_string(),
+                        user_id: user_id_of(fpr),
+                        changes,
+                        error: None,
+                    }),
+                }
+            }
+            (Some("IMPORT_PROBLEM"), Some(reason), fpr) => keys.push(ImportStatus {
+                fingerprint: fpr.unwrap_or("<unknown>").to_string(),
+                user_id: fpr.and_then(user_id_of),
+                changes: Vec::new(),
+                error: Some(import_problem(reason).to_string()),
+            }),
+            _ => {}
+        }
+    }
+    if keys.is_empty() && !output.success {
+        let error = AppError::Operation("gpg --import failed without importing any key.".to_string());
+        audit::record("import_keys", &[], Some(input.as_slice()), Err(&error));
+        return Err(error);
+    }
+    // Keys gpg dropped silently, e.g. because the user ID filter left them without user IDs
+    for preview in &previews {
+        let reported = keys.iter().any(|k| k.fingerprint.eq_ignore_ascii_case(&preview.fingerprint));
+        if !reported && !skipped.contains(&preview.fingerprint) {
+            keys.push(ImportStatus {
+                fingerprint: preview.fingerprint.clone(),
+                user_id: preview.user_ids.first().cloned(),
+                changes: Vec::new(),
+                error: Some("not imported (no user ID left after filtering, or rejected by gpg)".to_string()),
+            });
+        }
+    }
+    let imported: Vec<&str> = keys.iter().filter(|k| k.error.is_none()).map(|k| k.fingerprint.as_str()).collect();
+    audit::record("import_keys", &imported, Some(input.as_slice()), Ok(None));
+    Ok(ImportReport { keys, skipped })
 }
 
 // --- Crypto Operations ---
@@ -114,10 +1700,11 @@ pub fn encrypt(
     recipients: &[&str],
     // Add signer options if needed
 ) -> Result<String> {
+    policy::check(Operation::Encrypt)?;
     if recipients.is_empty() {
         return Err(AppError::InvalidInput("No recipients specified for encryption.".to_string()));
     }
-    with_gpg_ctx(|ctx| {
+    let result = with_gpg_ctx(|ctx| {
         // Find recipient keys
         let keys: Vec<Key> = recipients
             .iter()
@@ -146,11 +1733,13 @@ pub fn encrypt(
 
 
         String::from_utf8(output).map_err(|e| AppError::Operation(format!("UTF8 Error: {}", e)))
-    })
+    });
+    audited("encrypt", recipients, Some(plaintext.as_bytes()), result)
 }
 
 pub fn decrypt(ciphertext: &str) -> Result<String> {
-     with_gpg_ctx(|ctx| {
+    policy::check_decrypt(ciphertext.as_bytes())?;
+    let result = with_gpg_ctx(|ctx| {
          // Passphrase handling needed if private key is protected
          // ctx.set_passphrase_cb(...); // More complex setup needed
 
@@ -162,7 +1751,91 @@ pub fn decrypt(ciphertext: &str) -> Result<String> {
          ctx.decrypt(&mut input_data, Data::from_writer(&mut output)?)?;
 
          String::from_utf8(output).map_err(|e| AppError::Operation(format!("UTF8 Error: {}", e)))
-     })
+    });
+    audited("decrypt", &[], Some(ciphertext.as_bytes()), result)
+}
+
+// Like decrypt, but the plaintext stays in a buffer that is wiped when dropped
+pub fn decrypt_to_bytes(ciphertext: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    policy::check_decrypt(ciphertext)?;
+    let result = with_gpg_ctx(|ctx| {
+        let mut output = Zeroizing::new(Vec::new());
+        ctx.decrypt(ciphertext, Data::from_writer(&mut *output)?)?;
+        Ok(output)
+    });
+    audited("decrypt", &[], Some(ciphertext), result)
+}
+
+// Encrypts to the key(s) in an armored public key file without importing them into the keyring
+pub fn encrypt_to_key_file(plaintext: &[u8], key_file: &Path) -> Result<String> {
+    policy::check(Operation::Encrypt)?;
+    let key_file = key_file
+        .to_str()
+        .ok_or_else(|| AppError::InvalidInput("Key file path is not valid UTF-8.".to_string()))?;
+    let result = gpg_cli::run_gpg(&["--armor", "--recipient-file", key_file, "--encrypt"], Some(plaintext))
+        .and_then(|output| String::from_utf8(output.stdout).map_err(|e| AppError::Operation(format!("UTF8 Error: {}", e))));
+    audited("encrypt", &[], Some(plaintext), result)
+}
+
+// Runs `f` with gpg asking us (instead of pinentry) for passphrases, answering with `passphrase`
+fn with_passphrase<F, R>(ctx: &mut Context, passphrase: &str, f: F) -> Result<R>
+where
+    F: FnOnce(&mut Context) -> Result<R>,
+{
+    let previous_mode = ctx.pinentry_mode();
+    ctx.set_pinentry_mode(gpgme::PinentryMode::Loopback)?;
+    let result = ctx.with_passphrase_provider(
+        |_: gpgme::PassphraseRequest<'_>, out: &mut dyn Write| {
+            out.write_all(passphrase.as_bytes())?;
+            Ok(())
+        },
+        f,
+    );
+    ctx.set_pinentry_mode(previous_mode)?;
+    result
+}
+
+// with_passphrase for symmetric encryption and decryption: without no-symkey-cache gpg-agent
+// keeps the passphrase, and the next decrypt of the same message succeeds without it
+fn with_symmetric_passphrase<F, R>(ctx: &mut Context, passphrase: &str, f: F) -> Result<R>
+where
+    F: FnOnce(&mut Context) -> Result<R>,
+{
+    ctx.set_flag("no-symkey-cache", "1")?;
+    let result = with_passphrase(ctx, passphrase, f);
+    ctx.set_flag("no-symkey-cache", "0")?;
+    result
+}
+
+// Passphrase-only encryption (no recipient keys), ASCII armored
+pub fn encrypt_symmetric(plaintext: &[u8], passphrase: &str) -> Result<String> {
+    policy::check(Operation::Encrypt)?;
+    if passphrase.is_empty() {
+        return Err(AppError::InvalidInput("A passphrase is required.".to_string()));
+    }
+    let result = with_gpg_ctx(|ctx| {
+        let mut output = Vec::new();
+        ctx.set_armor(true);
+        with_symmetric_passphrase(ctx, passphrase, |ctx| {
+            ctx.encrypt_symmetric(plaintext, Data::from_armor_writer(&mut output)?)?;
+            Ok(())
+        })?;
+        String::from_utf8(output).map_err(|e| AppError::Operation(format!("UTF8 Error: {}", e)))
+    });
+    audited("encrypt_symmetric", &[], Some(plaintext), result)
+}
+
+pub fn decrypt_symmetric(ciphertext: &[u8], passphrase: &str) -> Result<Zeroizing<Vec<u8>>> {
+    policy::check(Operation::Decrypt)?;
+    let result = with_gpg_ctx(|ctx| {
+        let mut output = Zeroizing::new(Vec::new());
+        with_symmetric_passphrase(ctx, passphrase, |ctx| {
+            ctx.decrypt(ciphertext, Data::from_writer(&mut *output)?)?;
+            Ok(())
+        })?;
+        Ok(output)
+    });
+    audited("decrypt_symmetric", &[], Some(ciphertext), result)
 }
 
 pub fn sign(
@@ -171,7 +1844,8 @@ pub fn sign(
     mode: gpgme::SignMode,
     passphrase: Option<Zeroizing<String>>,
 ) -> Result<String> {
-     with_gpg_ctx(|ctx| {
+    policy::check_signer(Operation::Sign, Some(signer_key_id))?;
+    let result = with_gpg_ctx(|ctx| {
          // --- Passphrase Handling ---
          // This is tricky with gpgme. It often relies on pinentry or an agent.
          // For programmatic passphrase:
@@ -206,11 +1880,14 @@ pub fn sign(
 
 
          String::from_utf8(output).map_err(|e| AppError::Operation(format!("UTF8 Error: {}", e)))
-     })
+    });
+    audited("sign", &[signer_key_id], Some(plaintext.as_bytes()), result)
 }
 
 pub fn verify(signed_data: &str) -> Result<String> {
-    with_gpg_ctx(|ctx| {
+    policy::check(Operation::Verify)?;
+    let trust_model = get_trust_model()?;
+    let result = with_gpg_ctx(|ctx| {
         let mut input_data = Data::from_bytes(signed_data.as_bytes())?;
         let mut plaintext_output: Option<Data> = None; // We don't capture plaintext here, just verify
 
@@ -222,13 +1899,23 @@ pub fn verify(signed_data: &str) -> Result<String> {
         if let Some(signature) = verification_result.signatures().next() {
              // Check signature status
             if signature.status().is_ok() {
-                 let fpr = signature.fingerprint().unwrap_or("<no fpr>");
+                 let fpr = signature.fingerprint().unwrap_or("<no fpr>").to_string();
                  summary.push_str(&format!("Signature valid. Key Fingerprint: {}\n", fpr));
                  // You can add more details like signer user ID if needed
                  summary.push_str(&format!("Summary: {:?}\n", signature.summary()));
+                 for line in describe_signature_summary(signature.summary()) {
+                     summary.push_str(&format!("  - {}\n", line));
+                 }
+                 summary.push_str(&format!("Signer validity: {} (trust model: {})\n", validity_name(signature.validity()), trust_model));
+                 for line in explain_fingerprint(ctx, &fpr, &trust_model) {
+                     summary.push_str(&format!("  {}\n", line));
+                 }
             } else {
                  summary.push_str(&format!("Signature invalid or untrusted. Status: {:?}\n", signature.status()));
                  summary.push_str(&format!("Summary: {:?}\n", signature.summary()));
+                 for line in describe_signature_summary(signature.summary()) {
+                     summary.push_str(&format!("  - {}\n", line));
+                 }
                  return Err(AppError::Operation(summary)); // Return error on bad signature
             }
         } else {
@@ -237,8 +1924,105 @@ pub fn verify(signed_data: &str) -> Result<String> {
         }
 
         Ok(summary) // Return summary string on success
+    });
+    audited_action("verify", &[], Some(signed_data.as_bytes()), result)
+}
+
+// Signs with signature notations (name@domain = value) in the hashed area, so the signature
+// itself carries e.g. the nonce of an air-gapped signing request
+pub fn sign_with_notations(
+    data: &[u8],
+    signer_key_id: &str,
+    mode: gpgme::SignMode,
+    notations: &[(&str, &str)],
+) -> Result<String> {
+    policy::check_signer(Operation::Sign, Some(signer_key_id))?;
+    let result = notation_signature(data, signer_key_id, mode, notations);
+    audited("sign", &[signer_key_id], Some(data), result)
+}
+
+// Signs an audit log checkpoint. Not subject to the operation policy, which governs what users
+// may do; a kiosk that cannot sign still has to keep its audit log signed.
+pub fn sign_audit_checkpoint(statement: &[u8], key_id: &str) -> Result<String> {
+    let result = notation_signature(statement, key_id, gpgme::SignMode::Detach, &[]);
+    audited("sign_audit_checkpoint", &[key_id], Some(statement), result)
+}
+
+fn notation_signature(
+    data: &[u8],
+    signer_key_id: &str,
+    mode: gpgme::SignMode,
+    notations: &[(&str, &str)],
+) -> Result<String> {
+    with_gpg_ctx(|ctx| {
+        let key = ctx
+            .get_secret_key(signer_key_id)
+            .map_err(|_| AppError::InvalidInput(format!("Signer secret key '{}' not found.", signer_key_id)))?;
+        let mut input_data = Data::from_bytes(data)?;
+        let mut output = Vec::new();
+        ctx.set_armor(true);
+        ctx.clear_signers();
+        ctx.add_signer(&key)?;
+        for (name, value) in notations {
+            ctx.add_signature_notation(*name, *value, gpgme::SignatureNotationFlags::HUMAN_READABLE)?;
+        }
+        let result = ctx.sign(mode, &mut input_data, Data::from_armor_writer(&mut output)?);
+        ctx.clear_signature_notations();
+        ctx.clear_signers();
+        result?;
+        String::from_utf8(output).map_err(|e| AppError::Operation(format!("UTF8 Error: {}", e)))
+    })
+}
+
+#[derive(Debug)]
+pub struct SignatureCheck {
+    pub valid: bool,
+    pub status: String,                      // gpg's status for the first signature
+    pub primary_fingerprint: Option<String>, // of the signing key (the signature names the subkey)
+    pub notations: Vec<(String, String)>,
+    pub plaintext: Option<Zeroizing<Vec<u8>>>, // signed content of inline/cleartext signatures
+}
+
+// Checks the first signature in `signature`, either detached over `signed_data` or inline
+pub fn check_signature(signature: &[u8], signed_data: Option<&[u8]>) -> Result<SignatureCheck> {
+    with_gpg_ctx(|ctx| {
+        let mut signature_data = Data::from_bytes(signature)?;
+        let mut plaintext = Zeroizing::new(Vec::new());
+        let result = match signed_data {
+            Some(signed) => ctx.verify_detached(&mut signature_data, &mut Data::from_bytes(signed)?)?,
+            None => ctx.verify_opaque(&mut signature_data, Data::from_writer(&mut *plaintext)?)?,
+        };
+        let signature = result
+            .signatures()
+            .next()
+            .ok_or_else(|| AppError::InvalidInput("No signature found in the provided data.".to_string()))?;
+        let primary_fingerprint = signature
+            .fingerprint()
+            .ok()
+            .and_then(|fpr| ctx.get_key(fpr).ok())
+            .and_then(|key| key.fingerprint().ok().map(str::to_string));
+        let notations = signature
+            .notations()
+            .filter_map(|n| Some((n.name().ok()?.to_string(), n.value().ok()?.to_string())))
+            .collect();
+        Ok(SignatureCheck {
+            valid: signature.status().is_ok(),
+            status: match signature.status() {
+                Ok(()) => "Good signature".to_string(),
+                Err(e) => e.to_string(),
+            },
+            primary_fingerprint,
+            notations,
+            plaintext: if signed_data.is_none() { Some(plaintext) } else { None },
+        })
     })
 }
 
+// SHA-256 of data as uppercase hex (the form gpg prints)
+pub fn sha256_hex(data: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    Sha256::digest(data).iter().map(|byte| format!("{:02X}", byte)).collect()
+}
+
 // Add functions for generate_key, delete_key etc. following similar patterns
 // Remember to handle passphrases securely for key generation/deletion.
\ No newline at end of file
@@ -1,30 +1,628 @@
+mod airgap;
+mod armor;
+mod audit;
+mod backup;
+mod classify;
 mod cli;
+mod config;
 mod error;
+mod gpg_cli;
+mod gpg_edit;
 mod gpg_ops;
+mod paperkey;
+mod policy;
+mod qr_decode;
 mod qr_utils;
+mod scan;
+mod shamir;
+mod sign_queue;
 mod web_handlers;
 mod web_server;
 
-use cli::{CliArgs, Commands};
+use cli::{CliArgs, Commands, QrCompactArg, QrEccArg, TrustLevelArg};
 use clap::Parser;
 use error::Result; // Use custom result type
+use zeroize::Zeroizing;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = CliArgs::parse();
+    let mut config = config::Config::load(args.config.as_deref())?;
+    config.select_profile(args.profile.as_deref())?;
+    // A --policy file replaces the config file's [policy] table
+    if let Some(path) = &args.policy {
+        config.policy = policy::Policy::load(path)?;
+    }
+    config.policy.read_only |= args.read_only;
+    policy::set_policy(config.policy.clone());
+    config.qr = qr_overrides(&args.qr).apply(&config.qr)?;
+    config::set_config(config);
+    // Also for the command line: audit and queue warnings go through tracing
+    web_server::init_logging()?;
 
     match args.command {
-        Commands::Web { port, bind, gpg_dir } => {
+        Commands::Web { port, bind, gpg_dir, audit_key, tls_cert, tls_key } => {
+             // Command line flags win over the config file
+             let mut server = config::current().server;
+             let gpg = config::current().gpg;
+             let bind = bind.or(server.bind).unwrap_or_else(|| "127.0.0.1".to_string());
              // Validate bind address format early
              if let Err(_) = bind.parse::<std::net::IpAddr>() {
                   eprintln!("Error: Invalid IP address format for --bind: {}", bind);
                   std::process::exit(1);
              }
+             server.bind = Some(bind);
+             server.port = port.or(server.port);
+             if let (Some(cert), Some(key)) = (tls_cert, tls_key) {
+                 let tls = config::TlsConfig { cert, key };
+                 config::validate_tls(&tls).map_err(error::AppError::Config)?;
+                 server.tls = Some(tls);
+             }
              println!("Starting web server mode...");
-             web_server::run_web_server(bind, port, gpg_dir).await?;
+             web_server::run_web_server(server, gpg_dir.or(gpg.home), audit_key.or(gpg.audit_key)).await?;
+        }
+        Commands::ScanImage { paths, raw, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            let raw_payloads = qr_decode::decode_paths(&paths)?;
+            let scanned = scan::process_payloads(&raw_payloads)?;
+            for (i, payload) in scanned.iter().enumerate() {
+                if raw {
+                    println!("{}", payload.data);
+                } else {
+                    let analysis = &payload.analysis;
+                    println!("--- Payload {} of {}: {} ---", i + 1, scanned.len(), analysis.data_type);
+                    for key in &analysis.keys {
+                        println!("Key: {}", key.describe());
+                    }
+                    for recipient in &analysis.recipients {
+                        println!("Encrypted to: {} {}", recipient.key_id,
+                            recipient.secret_key_uid.as_deref().unwrap_or("(no secret key)"));
+                    }
+                    for note in &analysis.notes {
+                        println!("Note: {}", note);
+                    }
+                    println!("Possible actions: {:?}", analysis.actions);
+                    println!("{}", payload.data);
+                }
+            }
+        }
+        Commands::Certify { key_id, signer, user_ids, local, expires, trust, trust_depth, trust_domain, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            let options = gpg_ops::CertifyOptions {
+                signer: config::signer_or_default(signer.as_deref()),
+                user_ids,
+                local,
+                expires: gpg_ops::parse_expiry(&expires)?,
+                trust: trust.map(|level| gpg_ops::TrustSignature {
+                    level: match level {
+                        TrustLevelArg::Marginal => gpg_ops::TrustLevel::Marginal,
+                        TrustLevelArg::Full => gpg_ops::TrustLevel::Full,
+                    },
+                    depth: trust_depth,
+                    domain: trust_domain,
+                }),
+            };
+            println!("{}", gpg_ops::certify_key(&key_id, &options)?);
+        }
+        Commands::Certifications { key_id, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            for cert in gpg_ops::list_certifications(&key_id)? {
+                println!(
+                    "{} <- {} {}{}{}{}",
+                    cert.user_id,
+                    cert.signer_key_id,
+                    cert.signer_user_id.as_deref().unwrap_or(""),
+                    if cert.exportable { "" } else { " [local]" },
+                    if cert.revoked { " [revoked]" } else { "" },
+                    if cert.expired { " [expired]" } else { "" },
+                );
+            }
+        }
+        Commands::ListTrust { gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            println!("Trust model: {}", gpg_ops::get_trust_model()?);
+            for key in gpg_ops::list_key_trust()? {
+                println!("{} ownertrust: {}", key.fingerprint, key.owner_trust);
+                for uid in key.user_ids {
+                    println!("    [{}]{} {}", uid.validity, if uid.revoked { " [revoked]" } else { "" }, uid.user_id);
+                }
+            }
+        }
+        Commands::SetOwnertrust { key_id, trust, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            let trust = gpg_ops::OwnerTrust::parse(&trust)?;
+            println!("{}", gpg_ops::set_owner_trust(&key_id, trust)?);
+        }
+        Commands::ExportOwnertrust { gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            print!("{}", gpg_ops::export_ownertrust()?);
+        }
+        Commands::ImportOwnertrust { file, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            let ownertrust = std::fs::read_to_string(file)?;
+            println!("{}", gpg_ops::import_ownertrust(&ownertrust)?);
+        }
+        Commands::TrustModel { model, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            match model {
+                Some(model) => println!("{}", gpg_ops::set_trust_model(&model)?),
+                None => println!("{}", gpg_ops::get_trust_model()?),
+            }
+        }
+        Commands::Import { file, dry_run, yes, keep_ownertrust, clean, restore, keep_user_ids, fingerprints, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            let key_data = Zeroizing::new(std::fs::read_to_string(&file)?); // armored key data
+            let previews = gpg_ops::preview_keys(key_data.as_bytes())?;
+            if previews.is_empty() {
+                return Err(error::AppError::InvalidInput(format!("No keys found in {}", file.display())));
+            }
+            for preview in &previews {
+                println!("{}", preview.describe());
+            }
+            if dry_run {
+                return Ok(());
+            }
+            if !yes {
+                eprintln!("Import these changes? [y/N]");
+                let mut answer = String::new();
+                std::io::stdin().read_line(&mut answer)?;
+                if !answer.trim().eq_ignore_ascii_case("y") {
+                    println!("Nothing imported.");
+                    return Ok(());
+                }
+            }
+            let options = gpg_ops::ImportOptions { keep_ownertrust, clean, restore, keep_user_ids, fingerprints };
+            println!("{}", gpg_ops::import_keys(&key_data, &options)?.describe());
+        }
+        Commands::ExportKey { key_id, minimal, clean, drop_user_ids, subkeys, estimate, output, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            let filter = gpg_ops::ExportFilter { minimal, clean, drop_user_ids, subkeys };
+            let key_data = gpg_ops::export_key_filtered(&key_id, &filter)?;
+            let size = qr_utils::estimate_qr(&key_data);
+            if estimate {
+                println!("{}", size.describe());
+            } else if let Some(path) = output {
+                std::fs::write(&path, &key_data)?;
+                println!("Key written to {} ({})", path.display(), size.describe());
+            } else {
+                print!("{}", key_data);
+                eprintln!("{}", size.describe());
+            }
+        }
+        Commands::ExportSecretSubkeys {
+            key_id,
+            subkeys,
+            recipient,
+            export_passphrase_file,
+            passphrase_file,
+            output,
+            qr,
+            gpg_dir,
+        } => {
+            init_gpg_dir(gpg_dir)?;
+            let key_passphrase = read_passphrase(passphrase_file, "Passphrase of the secret key:")?;
+            let protection = match recipient {
+                Some(recipient) => gpg_ops::ExportProtection::Recipient(recipient),
+                None => gpg_ops::ExportProtection::Passphrase(read_passphrase(
+                    export_passphrase_file,
+                    "Passphrase to encrypt the export with:",
+                )?),
+            };
+            let exported = gpg_ops::export_secret_protected(&key_id, Some(&subkeys), &key_passphrase, &protection)?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, &exported)?;
+                    println!("Encrypted secret subkeys written to {}", path.display());
+                    if qr {
+                        write_qr_frames(&path, &exported)?;
+                    }
+                }
+                None => print!("{}", exported),
+            }
+        }
+        Commands::KeyDetails { key_id, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            let details = gpg_ops::key_details(&key_id)?;
+            println!(
+                "{} ownertrust: {}{}{}{}",
+                details.fingerprint,
+                details.owner_trust,
+                if details.has_secret { " [secret key available]" } else { "" },
+                if details.revoked { " [revoked]" } else { "" },
+                if details.expired { " [expired]" } else { "" },
+            );
+            for (i, uid) in details.user_ids.iter().enumerate() {
+                println!(
+                    "uid [{}]{}{} {}",
+                    uid.validity,
+                    if i == 0 { " [primary]" } else { "" },
+                    if uid.revoked { " [revoked]" } else { "" },
+                    uid.user_id
+                );
+            }
+            for subkey in &details.subkeys {
+                println!(
+                    "{} {} {} [{}] {}",
+                    if subkey.is_primary { "pub" } else { "sub" },
+                    subkey.fingerprint,
+                    subkey.algorithm,
+                    subkey.usage,
+                    gpg_ops::subkey_status(subkey)
+                );
+            }
+            for note in details.validity_notes {
+                println!("  {}", note);
+            }
+        }
+        Commands::AddUid { key_id, user_id, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            println!("{}", gpg_ops::add_user_id(&key_id, &user_id)?);
+        }
+        Commands::SetPrimaryUid { key_id, user_id, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            println!("{}", gpg_ops::set_primary_user_id(&key_id, &user_id)?);
+        }
+        Commands::Subkeys { key_id, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            for subkey in gpg_ops::list_subkeys(&key_id)? {
+                println!(
+                    "{} {} {} [{}] {}{}",
+                    if subkey.is_primary { "pub" } else { "sub" },
+                    subkey.fingerprint,
+                    subkey.algorithm,
+                    subkey.usage,
+                    gpg_ops::subkey_status(&subkey),
+                    if subkey.has_secret { "" } else { " (no secret key)" },
+                );
+            }
+        }
+        Commands::AddSubkey { key_id, usage, algorithm, expires, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            let usage = gpg_ops::SubkeyUsage::parse(&usage)?;
+            println!("{}", gpg_ops::add_subkey(&key_id, &algorithm, usage, gpg_ops::parse_expiry(&expires)?)?);
+        }
+        Commands::SetExpiry { key_id, expires, subkeys, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            println!("{}", gpg_ops::set_expiry(&key_id, &subkeys, gpg_ops::parse_expiry(&expires)?)?);
+        }
+        Commands::GenRevoke { key_id, output, reason, description, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            let reason = gpg_ops::RevocationReason::parse(&reason)?;
+            let cert = gpg_ops::generate_revocation_cert(&key_id, reason, &description)?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, &cert)?;
+                    println!("Revocation certificate written to {}", path.display());
+                }
+                None => print!("{}", cert),
+            }
+        }
+        Commands::RevokeKey { key_id, reason, description, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            let reason = gpg_ops::RevocationReason::parse(&reason)?;
+            println!("{}", gpg_ops::revoke_key(&key_id, reason, &description)?);
+        }
+        Commands::RevokeSubkey { key_id, subkey, reason, description, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            let reason = gpg_ops::RevocationReason::parse(&reason)?;
+            println!("{}", gpg_ops::revoke_subkey(&key_id, &subkey, reason, &description)?);
+        }
+        Commands::RevokeUid { key_id, user_id, reason, description, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            let reason = gpg_ops::RevocationReason::parse(&reason)?;
+            println!("{}", gpg_ops::revoke_user_id(&key_id, &user_id, reason, &description)?);
+        }
+        Commands::Backup { output, qr, passphrase_file, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            let passphrase = read_passphrase(passphrase_file, "Passphrase:")?;
+            let (archive, manifest) = backup::create_backup(&passphrase)?;
+            std::fs::write(&output, &archive)?;
+            println!("{}", manifest.describe());
+            println!("Backup written to {}", output.display());
+            if qr {
+                write_qr_frames(&output, &archive)?;
+            }
+        }
+        Commands::RestoreBackup { file, validate_only, passphrase_file, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            let archive = std::fs::read_to_string(&file)?;
+            let passphrase = read_passphrase(passphrase_file, "Passphrase:")?;
+            let report = backup::restore_backup(&archive, &passphrase, validate_only)?;
+            println!("{}", report.manifest.describe());
+            if !report.problems.is_empty() {
+                for problem in &report.problems {
+                    eprintln!("Problem: {}", problem);
+                }
+                return Err(error::AppError::InvalidInput("Backup is incomplete, nothing was restored.".to_string()));
+            }
+            if let Some(import) = &report.import {
+                println!("{}", import.describe());
+            }
+            if let Some(ownertrust) = &report.ownertrust {
+                println!("{}", ownertrust);
+            }
+            if report.restored {
+                println!("{} revocation certificate(s) stored.", report.revocation_certs_stored);
+            } else {
+                println!("Backup is complete. Nothing was restored (validation only).");
+            }
+        }
+        Commands::PaperBackup { key_id, format, output, passphrase_file, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            let encoding = paperkey::PaperEncoding::parse(&format)?;
+            let key_passphrase = read_passphrase(passphrase_file, "Passphrase of the secret key:")?;
+            let backup = paperkey::create_paper_backup(&key_id, encoding, &key_passphrase)?;
+            match output {
+                Some(path) => {
+                    write_private(&path, backup.html()?.as_bytes())?;
+                    println!("Printable paper backup written to {}", path.display());
+                }
+                None => print!("{}", backup.text()),
+            }
+        }
+        Commands::PaperRestore { file, public_key, output, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            let text = std::fs::read_to_string(&file)?;
+            let public_key = public_key.map(std::fs::read_to_string).transpose()?;
+            let restored = paperkey::restore_paper_backup(&text, public_key.as_deref(), output.is_none())?;
+            match (output, restored.import) {
+                (Some(path), _) => {
+                    write_private(&path, restored.secret_key.as_bytes())?;
+                    println!("Secret key {} written to {}", restored.fingerprint, path.display());
+                }
+                (None, Some(import)) => println!("{}", import.describe()),
+                (None, None) => {}
+            }
+        }
+        Commands::SplitShares { key_id, archive, passphrase_file, threshold, shares, output, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            let archive = archive.map(std::fs::read_to_string).transpose()?;
+            let key_passphrase = match &archive {
+                Some(_) => Zeroizing::new(String::new()),
+                None => read_passphrase(passphrase_file, "Passphrase of the secret key:")?,
+            };
+            let source = match (&archive, &key_id) {
+                (Some(archive), _) => shamir::SplitSource::BackupArchive(archive),
+                (None, Some(key_id)) => shamir::SplitSource::SecretKey(key_id, &key_passphrase),
+                (None, None) => unreachable!("clap requires a key ID or --archive"),
+            };
+            let cards = shamir::create_share_cards(source, threshold, shares)?;
+            write_private(&output, cards.html()?.as_bytes())?;
+            println!(
+                "{} share cards of set {} written to {} (any {} restore the {}).",
+                cards.total, cards.set_id, output.display(), cards.threshold, cards.kind
+            );
+        }
+        Commands::CombineShares { files, output, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            let mut lines = Vec::new();
+            for file in &files {
+                lines.extend(std::fs::read_to_string(file)?.lines().map(str::to_string));
+            }
+            let shares = shamir::parse_share_lines(&lines)?;
+            let restored = shamir::restore_from_shares(&shares, output.is_none())?;
+            match output {
+                Some(path) => {
+                    write_private(&path, restored.data.as_bytes())?;
+                    println!("Restored {} written to {}", restored.kind.description(), path.display());
+                }
+                None if restored.kind == shamir::SecretKind::BackupArchive => {
+                    println!("{}", restored.data.as_str()); // still encrypted, for restore-backup
+                }
+                None => println!("{}", restored.describe()),
+            }
+        }
+        Commands::SignRequest { signer, mode, file, hash_only, document, requester, description, output, qr, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            let signer = config::signer_or_default(signer.as_deref()).ok_or_else(|| {
+                error::AppError::InvalidInput("No --signer given and no default signer configured.".to_string())
+            })?;
+            let mode = airgap::SignRequestMode::parse(&mode)?;
+            let input_bytes = std::fs::read(&file)?;
+            let document = document
+                .or_else(|| file.file_name().map(|n| n.to_string_lossy().to_string()))
+                .unwrap_or_default();
+            let requester = requester.or_else(|| std::env::var("USER").ok()).unwrap_or_default();
+            let content;
+            let input = if hash_only {
+                airgap::SignInput::Digest(&input_bytes)
+            } else {
+                content = String::from_utf8(input_bytes)
+                    .map_err(|_| error::AppError::InvalidInput("Only text documents can be sent as content; use --hash-only.".to_string()))?;
+                airgap::SignInput::Content(&content)
+            };
+            let info = airgap::RequestInfo { document: &document, requester: &requester, description: &description };
+            let request = airgap::create_sign_request(input, &signer, mode, info)?;
+            let encoded = request.encode()?;
+            std::fs::write(&output, &encoded)?;
+            println!("{}", request.describe().join("\n"));
+            println!("Signing request written to {}", output.display());
+            if qr {
+                write_qr_frames(&output, &encoded)?;
+            }
+        }
+        Commands::AnswerSignRequest { file, yes, output, qr, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            let request = airgap::parse_sign_request(&std::fs::read_to_string(&file)?)?;
+            if sign_queue::already_decided(&request.nonce)? {
+                return Err(error::AppError::InvalidInput(format!(
+                    "Signing request {} was already decided; the requester has to create a new one.",
+                    request.nonce
+                )));
+            }
+            println!("{}", sign_queue::review(&request)?.join("\n"));
+            if !yes {
+                eprintln!("Sign exactly this? [y/N]");
+                let mut answer = String::new();
+                std::io::stdin().read_line(&mut answer)?;
+                if !answer.trim().eq_ignore_ascii_case("y") {
+                    sign_queue::record_decision(&request, sign_queue::Decision::Rejected, "declined at the prompt")?;
+                    println!("Not signed.");
+                    return Ok(());
+                }
+            }
+            let response = airgap::answer_sign_request(&request)?;
+            if let Err(e) = sign_queue::record_decision(&request, sign_queue::Decision::Approved, "") {
+                tracing::warn!("Could not log approval of signing request {}: {}", request.nonce, e);
+            }
+            std::fs::write(&output, &response)?;
+            println!("Signing response written to {}", output.display());
+            if qr {
+                write_qr_frames(&output, &response)?;
+            }
+        }
+        Commands::CheckSignResponse { file, request, output, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            let request = airgap::parse_sign_request(&std::fs::read_to_string(&request)?)?;
+            let accepted = airgap::check_sign_response(&std::fs::read_to_string(&file)?, Some(request))?;
+            println!("{}", accepted.notes.join("\n"));
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, &accepted.signature)?;
+                    println!("Signature written to {}", path.display());
+                }
+                None => println!("{}", accepted.signature),
+            }
+        }
+        Commands::DecryptRequest { file, use_passphrase, description, output, session, qr, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            let ciphertext = std::fs::read_to_string(&file)?;
+            let (request, decrypt_session) = airgap::create_decrypt_request(&ciphertext, use_passphrase, &description)?;
+            let encoded = request.encode()?;
+            write_private(&session, decrypt_session.encode()?.as_bytes())?;
+            std::fs::write(&output, &encoded)?;
+            println!("{}", request.describe().join("\n"));
+            println!("Decryption request written to {}", output.display());
+            println!("Session written to {} (keep it private, delete it once the response is opened)", session.display());
+            if qr {
+                write_qr_frames(&output, &encoded)?;
+            }
+        }
+        Commands::AnswerDecryptRequest { file, output, qr, show_plaintext, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            let payload = std::fs::read_to_string(&file)?;
+            println!("{}", airgap::parse_decrypt_request(&payload)?.describe().join("\n"));
+            if show_plaintext {
+                println!("{}", airgap::reveal_decrypt_request(&payload)?.as_str());
+                return Ok(());
+            }
+            let response = airgap::answer_decrypt_request(&payload)?;
+            std::fs::write(&output, &response)?;
+            println!("Decryption response written to {}", output.display());
+            if qr {
+                write_qr_frames(&output, &response)?;
+            }
+        }
+        Commands::OpenDecryptResponse { file, session, output, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            let session = airgap::DecryptSession::parse(&Zeroizing::new(std::fs::read_to_string(&session)?))?;
+            let plaintext = airgap::open_decrypt_response(&std::fs::read_to_string(&file)?, Some(&session))?;
+            match output {
+                Some(path) => {
+                    write_private(&path, plaintext.as_bytes())?;
+                    println!("Plaintext written to {}", path.display());
+                }
+                None => println!("{}", plaintext.as_str()),
+            }
+        }
+        Commands::AuditVerify { file, key, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            let report = match file {
+                Some(path) => audit::verify_text(&std::fs::read_to_string(path)?, key.as_deref())?,
+                None => audit::verify_log(key.as_deref())?,
+            };
+            println!("{}", report.describe());
+            if !report.is_intact() {
+                return Err(error::AppError::Operation("The audit log failed verification.".to_string()));
+            }
+        }
+        Commands::AuditCheckpoint { key, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            audit::set_signing_key(Some(key));
+            let entry = audit::checkpoint()?;
+            if let Some(checkpoint) = entry.body.checkpoint {
+                println!("Signed audit log up to entry {} ({}).", checkpoint.seq, checkpoint.hash);
+            }
+        }
+        Commands::AuditExport { since, output, qr, gpg_dir } => {
+            init_gpg_dir(gpg_dir)?;
+            let exported = audit::export(since)?;
+            std::fs::write(&output, &exported)?;
+            println!("Audit log entries written to {}", output.display());
+            if qr {
+                write_qr_frames(&output, &exported)?;
+            }
         }
         // Add handlers for other CLI commands if implemented
     }
 
     Ok(())
 }
+
+// Writes data as a multi-frame QR set: <path>.qr1.svg, <path>.qr2.svg, ...
+fn write_qr_frames(path: &std::path::Path, data: &str) -> Result<()> {
+    for (i, svg) in qr_utils::generate_qr_frames_svg(data)?.iter().enumerate() {
+        let frame_path = path.with_extension(format!("qr{}.svg", i + 1));
+        std::fs::write(&frame_path, svg)?;
+        println!("QR frame {} written to {}", i + 1, frame_path.display());
+    }
+    Ok(())
+}
+
+// Writes secret material readable by the owner only
+fn write_private(path: &std::path::Path, data: &[u8]) -> Result<()> {
+    use std::io::Write;
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(data)?;
+    Ok(())
+}
+
+// Reads a passphrase from the first line of a file, or of stdin
+fn read_passphrase(file: Option<std::path::PathBuf>, prompt: &str) -> Result<Zeroizing<String>> {
+    let content = Zeroizing::new(match file {
+        Some(path) => std::fs::read_to_string(path)?,
+        None => {
+            eprintln!("{}", prompt);
+            let mut line = String::new();
+            std::io::stdin().read_line(&mut line)?;
+            line
+        }
+    });
+    Ok(Zeroizing::new(content.lines().next().unwrap_or("").to_string()))
+}
+
+// The --qr-* flags, applied over the [qr] table of the config file
+fn qr_overrides(args: &cli::QrArgs) -> qr_utils::QrOverrides {
+    qr_utils::QrOverrides {
+        ecc: args.qr_ecc.map(|ecc| match ecc {
+            QrEccArg::Low => qr_utils::QrEcc::Low,
+            QrEccArg::Medium => qr_utils::QrEcc::Medium,
+            QrEccArg::Quartile => qr_utils::QrEcc::Quartile,
+            QrEccArg::High => qr_utils::QrEcc::High,
+        }),
+        min_version: args.qr_min_version,
+        max_version: args.qr_max_version,
+        mask: args.qr_mask,
+        border: args.qr_border,
+        module_size: args.qr_module_size,
+        foreground: args.qr_fg.clone(),
+        background: args.qr_bg.clone(),
+        compact: args.qr_compact.map(|compact| match compact {
+            QrCompactArg::None => qr_utils::CompactEncoding::None,
+            QrCompactArg::Base45 => qr_utils::CompactEncoding::Base45,
+            QrCompactArg::Binary => qr_utils::CompactEncoding::Binary,
+        }),
+    }
+}
+
+// --gpg-dir, else the configured home directory, else GnuPG's default
+fn init_gpg_dir(gpg_dir: Option<String>) -> Result<()> {
+    let gpg_dir = gpg_dir.or(config::current().gpg.home);
+    if gpg_dir.is_some() {
+        gpg_ops::set_gpg_homedir(gpg_dir)?;
+    }
+    Ok(())
+}
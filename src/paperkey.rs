@@ -0,0 +1,569 @@
+use crate::armor;
+use crate::classify::{self, PacketType};
+use crate::error::{AppError, Result};
+use crate::gpg_ops::{self, ImportOptions, ImportReport};
+use crate::qr_utils;
+use askama::Template;
+use std::time::SystemTime;
+use zeroize::{Zeroize, Zeroizing};
+
+// Paper backup of secret key material, in the spirit of `paperkey`: only the secret part of
+// each key packet is printed (still protected by the key's passphrase). The public key supplies
+// everything else on restore.
+//
+// Byte stream: version, fingerprint length, fingerprint, then per key packet its tag (5 or 7),
+// the CRC24 of its public part (to find the matching public packet), a 2-byte length and the
+// secret bytes. Printed as numbered lines, each with a CRC24 over line number and data.
+
+pub const PAPER_QR_PREFIX: &str = "SGPK1:";
+const STREAM_VERSION: u8 = 1;
+const LINE_BYTES: usize = 20;
+// Generous for the secret parts of a key with several RSA-4096 subkeys
+const MAX_STREAM_BYTES: usize = 64 * 1024;
+const MAX_LINES: usize = MAX_STREAM_BYTES.div_ceil(LINE_BYTES);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaperEncoding {
+    Base16,
+    Base32,
+}
+
+impl PaperEncoding {
+    pub fn parse(encoding: &str) -> Result<Self> {
+        match encoding.trim().to_ascii_lowercase().as_str() {
+            "base16" | "hex" => Ok(PaperEncoding::Base16),
+            "base32" => Ok(PaperEncoding::Base32),
+            other => Err(AppError::InvalidInput(format!(
+                "Unknown paper encoding '{}'. Use base16 or base32.",
+                other
+            ))),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PaperEncoding::Base16 => "base16",
+            PaperEncoding::Base32 => "base32",
+        }
+    }
+
+    // Groups of four characters, easier to read aloud and re-type
+    fn encode_line(self, data: &[u8]) -> String {
+        let encoded = match self {
+            PaperEncoding::Base16 => encode_hex(data),
+            PaperEncoding::Base32 => qr_utils::encode_base32(data),
+        };
+        encoded
+            .as_bytes()
+            .chunks(4)
+            .map(|group| String::from_utf8_lossy(group).into_owned())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn decode(self, text: &str) -> Option<Vec<u8>> {
+        match self {
+            PaperEncoding::Base16 => decode_hex(text),
+            PaperEncoding::Base32 => qr_utils::decode_base32(text),
+        }
+    }
+}
+
+fn encode_hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    let text = text.as_bytes();
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    text.chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
+fn crc_hex(crc: u32) -> String {
+    format!("{:06X}", crc)
+}
+
+fn line_checksum(number: usize, data: &[u8]) -> u32 {
+    let mut input = (number as u16).to_be_bytes().to_vec();
+    input.extend_from_slice(data);
+    armor::crc24(&input)
+}
+
+// Length of the public part of a v4 key packet body (version, creation time, algorithm, key fields)
+fn public_part_len(body: &[u8]) -> Result<usize> {
+    enum Field {
+        Mpi,
+        Sized, // one length octet, then the data (curve OID, ECDH KDF parameters)
+    }
+    use Field::*;
+    if body.first() != Some(&4) {
+        return Err(AppError::InvalidInput("Paper backups support version 4 keys only.".to_string()));
+    }
+    let fields: &[Field] = match body.get(5) {
+        Some(1..=3) => &[Mpi, Mpi],               // RSA
+        Some(16 | 20) => &[Mpi, Mpi, Mpi],        // ElGamal
+        Some(17) => &[Mpi, Mpi, Mpi, Mpi],        // DSA
+        Some(18) => &[Sized, Mpi, Sized],         // ECDH
+        Some(19 | 22) => &[Sized, Mpi],           // ECDSA, EdDSA
+        Some(algo) => {
+            return Err(AppError::InvalidInput(format!("Unsupported public key algorithm {}.", algo)));
+        }
+        None => return Err(AppError::InvalidInput("Truncated key packet.".to_string())),
+    };
+    let mut pos = 6;
+    for field in fields {
+        let len = match field {
+            Mpi => match body.get(pos..pos + 2) {
+                Some([hi, lo]) => 2 + (u16::from_be_bytes([*hi, *lo]) as usize).div_ceil(8),
+                _ => return Err(AppError::InvalidInput("Truncated key packet.".to_string())),
+            },
+            Sized => 1 + *body
+                .get(pos)
+                .ok_or_else(|| AppError::InvalidInput("Truncated key packet.".to_string()))? as usize,
+        };
+        pos += len;
+    }
+    if pos > body.len() {
+        return Err(AppError::InvalidInput("Truncated key packet.".to_string()));
+    }
+    Ok(pos)
+}
+
+// Builds the byte stream from a binary secret key export
+fn secret_stream(fingerprint: &str, exported: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    let fpr = decode_hex(fingerprint)
+        .ok_or_else(|| AppError::Operation(format!("Invalid fingerprint '{}'.", fingerprint)))?;
+    let mut stream = Zeroizing::new(vec![STREAM_VERSION, fpr.len() as u8]);
+    stream.extend_from_slice(&fpr);
+
+    let mut packets = classify::parse_packets(exported);
+    let mut parts = 0;
+    let mut result = Ok(());
+    for packet in &packets {
+        if !matches!(packet.packet_type, PacketType::SecretKey | PacketType::SecretSubkey) {
+            continue;
+        }
+        let public_len = match public_part_len(&packet.body) {
+            Ok(len) => len,
+            Err(e) => {
+                result = Err(e);
+                break;
+            }
+        };
+        let secret = &packet.body[public_len..];
+        stream.push(packet.tag);
+        stream.extend_from_slice(&armor::crc24(&packet.body[..public_len]).to_be_bytes()[1..]);
+        stream.extend_from_slice(&(secret.len() as u16).to_be_bytes());
+        stream.extend_from_slice(secret);
+        parts += 1;
+    }
+    for packet in packets.iter_mut() {
+        packet.body.zeroize();
+    }
+    result?;
+    if parts == 0 {
+        return Err(AppError::Operation("The export contained no secret key packets.".to_string()));
+    }
+    Ok(stream)
+}
+
+#[derive(Template)]
+#[template(path = "paperkey.html")]
+pub struct PaperBackup {
+    pub fingerprint: String,
+    pub user_id: String,
+    pub created: String,
+    pub encoding: &'static str,
+    pub lines: Vec<String>,
+    pub checksum: String,      // CRC24 of the whole stream
+    pub qr_codes: Vec<String>, // SVG; one code, or a multi-frame set for large keys
+}
+
+impl PaperBackup {
+    // The printable page (print to paper or PDF from the browser)
+    pub fn html(&self) -> Result<String> {
+        Ok(self.render()?)
+    }
+
+    // Plain-text version of the printed page; also what the restore flow parses
+    pub fn text(&self) -> String {
+        let mut text = format!(
+            "Secure-GPG-QR paper key backup\nFingerprint: {}\nUser ID: {}\nCreated: {}\nFormat: {}\nLines: {}\n\n",
+            self.fingerprint,
+            self.user_id,
+            self.created,
+            self.encoding,
+            self.lines.len()
+        );
+        for line in &self.lines {
+            text.push_str(line);
+            text.push('\n');
+        }
+        text.push_str(&format!("\nChecksum: {}\n", self.checksum));
+        text
+    }
+}
+
+pub fn create_paper_backup(key_id: &str, encoding: PaperEncoding, key_passphrase: &str) -> Result<PaperBackup> {
+    let fingerprint = gpg_ops::key_fingerprint(key_id)?;
+    let user_id = gpg_ops::find_secret_key_uid(&fingerprint)?
+        .ok_or_else(|| AppError::InvalidInput(format!("No secret key for {} in this keyring.", fingerprint)))?;
+    let exported = gpg_ops::export_secret_key_binary(&fingerprint, key_passphrase)?;
+    let stream = secret_stream(&fingerprint, &exported)?;
+
+    let lines = stream
+        .chunks(LINE_BYTES)
+        .enumerate()
+        .map(|(i, chunk)| {
+            format!(
+                "{:>3}: {} {}",
+                i + 1,
+                encoding.encode_line(chunk),
+                crc_hex(line_checksum(i + 1, chunk))
+            )
+        })
+        .collect();
+    let checksum = armor::crc24(&stream);
+
+    let mut qr_data = stream.clone();
+    qr_data.extend_from_slice(&checksum.to_be_bytes()[1..]);
+    let qr_payload = Zeroizing::new(format!("{}{}", PAPER_QR_PREFIX, qr_utils::encode_base32(&qr_data)));
+    let qr_codes = qr_utils::generate_printable_qr_svg(&qr_payload)?;
+
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    Ok(PaperBackup {
+        fingerprint,
+        user_id,
+        created: gpg_ops::format_date(now),
+        encoding: encoding.name(),
+        lines,
+        checksum: crc_hex(checksum),
+        qr_codes,
+    })
+}
+
+fn header_value<'a>(text: &'a str, name: &str) -> Option<&'a str> {
+    text.lines()
+        .find_map(|line| line.trim().strip_prefix(name)?.strip_prefix(':'))
+        .map(str::trim)
+}
+
+// Reads the stream back from the printed text (or a scanned QR payload). Every bad or missing
+// line is reported by number so it can be re-checked against the paper.
+pub fn parse_paper_text(text: &str) -> Result<Zeroizing<Vec<u8>>> {
+    if let Some(payload) = text.trim().strip_prefix(PAPER_QR_PREFIX) {
+        let mut data = Zeroizing::new(
+            qr_utils::decode_base32(payload.trim())
+                .ok_or_else(|| AppError::InvalidInput("Paper key QR payload is not valid base32.".to_string()))?,
+        );
+        if data.len() < 3 {
+            return Err(AppError::InvalidInput("Paper key QR payload is truncated.".to_string()));
+        }
+        let crc_start = data.len() - 3;
+        let crc = data.split_off(crc_start);
+        if crc != armor::crc24(&data).to_be_bytes()[1..] {
+            return Err(AppError::InvalidInput("Paper key QR payload checksum error.".to_string()));
+        }
+        return Ok(data);
+    }
+
+    let encoding = PaperEncoding::parse(
+        header_value(text, "Format")
+            .ok_or_else(|| AppError::InvalidInput("Missing 'Format:' line (base16 or base32).".to_string()))?,
+    )?;
+    let line_count: usize = header_value(text, "Lines")
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| AppError::InvalidInput("Missing or invalid 'Lines:' line.".to_string()))?;
+    if line_count > MAX_LINES {
+        return Err(AppError::InvalidInput(format!(
+            "'Lines: {}' is more than a paper key can have ({}).",
+            line_count, MAX_LINES
+        )));
+    }
+
+    let mut decoded: Vec<Option<Zeroizing<Vec<u8>>>> = vec![None; line_count];
+    let mut errors = Vec::new();
+    for line in text.lines() {
+        let Some((number, rest)) = line.split_once(':') else { continue };
+        let Ok(number) = number.trim().parse::<usize>() else { continue };
+        let mut tokens: Vec<&str> = rest.split_whitespace().collect();
+        let checksum = tokens.pop().and_then(|t| u32::from_str_radix(t, 16).ok());
+        let data = encoding.decode(&tokens.concat()).map(Zeroizing::new);
+        match (number.checked_sub(1).and_then(|i| decoded.get_mut(i)), checksum, data) {
+            (None, _, _) => errors.push(format!("Line {}: beyond the {} lines announced.", number, line_count)),
+            (Some(Some(_)), _, _) => errors.push(format!("Line {}: appears twice.", number)),
+            (Some(_), None, _) => errors.push(format!("Line {}: missing checksum.", number)),
+            (Some(_), _, None) => errors.push(format!("Line {}: invalid {} characters.", number, encoding.name())),
+            (Some(slot), Some(checksum), Some(data)) => {
+                if line_checksum(number, &data) == checksum {
+                    *slot = Some(data);
+                } else {
+                    errors.push(format!("Line {}: checksum error, re-check this line.", number));
+                }
+            }
+        }
+    }
+    for (i, slot) in decoded.iter().enumerate() {
+        if slot.is_none() && !errors.iter().any(|e| e.starts_with(&format!("Line {}:", i + 1))) {
+            errors.push(format!("Line {}: missing.", i + 1));
+        }
+    }
+    if !errors.is_empty() {
+        return Err(AppError::InvalidInput(errors.join("\n")));
+    }
+
+    let mut stream = Zeroizing::new(Vec::new());
+    for data in decoded.into_iter().flatten() {
+        stream.extend_from_slice(&data);
+    }
+    match header_value(text, "Checksum").and_then(|v| u32::from_str_radix(v, 16).ok()) {
+        Some(crc) if crc == armor::crc24(&stream) => Ok(stream),
+        Some(_) => Err(AppError::InvalidInput(
+            "All lines are valid but the overall checksum does not match: check the line order.".to_string(),
+        )),
+        None => Err(AppError::InvalidInput("Missing or invalid 'Checksum:' line.".to_string())),
+    }
+}
+
+struct SecretPart<'a> {
+    tag: u8,
+    public_crc: [u8; 3],
+    secret: &'a [u8],
+    used: bool,
+}
+
+fn parse_stream(stream: &[u8]) -> Result<(String, Vec<SecretPart<'_>>)> {
+    let truncated = || AppError::InvalidInput("Paper key data is truncated.".to_string());
+    match stream.first() {
+        Some(&STREAM_VERSION) => {}
+        Some(v) => return Err(AppError::InvalidInput(format!("Unsupported paper key version {}.", v))),
+        None => return Err(truncated()),
+    }
+    let fpr_len = *stream.get(1).ok_or_else(truncated)? as usize;
+    let fingerprint = encode_hex(stream.get(2..2 + fpr_len).ok_or_else(truncated)?);
+    let mut parts = Vec::new();
+    let mut pos = 2 + fpr_len;
+    while pos < stream.len() {
+        let header = stream.get(pos..pos + 6).ok_or_else(truncated)?;
+        let len = u16::from_be_bytes([header[4], header[5]]) as usize;
+        parts.push(SecretPart {
+            tag: header[0],
+            public_crc: [header[1], header[2], header[3]],
+            secret: stream.get(pos + 6..pos + 6 + len).ok_or_else(truncated)?,
+            used: false,
+        });
+        pos += 6 + len;
+    }
+    Ok((fingerprint, parts))
+}
+
+// New-format packet header followed by the body
+fn write_packet(out: &mut Vec<u8>, tag: u8, body: &[u8]) {
+    out.push(0xC0 | tag);
+    match body.len() {
+        len if len < 192 => out.push(len as u8),
+        len if len < 8384 => {
+            let len = len - 192;
+            out.push((len >> 8) as u8 + 192);
+            out.push(len as u8);
+        }
+        len => {
+            out.push(255);
+            out.extend_from_slice(&(len as u32).to_be_bytes());
+        }
+    }
+    out.extend_from_slice(body);
+}
+
+// Puts the secret parts back into the matching packets of the public key
+fn rebuild_secret_key(parts: &mut [SecretPart<'_>], public_key: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    let mut out = Zeroizing::new(Vec::new());
+    let mut in_key = false;
+    for packet in classify::parse_packets(public_key) {
+        let secret_tag = match packet.packet_type {
+            PacketType::PublicKey => Some(5),
+            PacketType::PublicSubkey => Some(7),
+            _ => None,
+        };
+        if let Some(secret_tag) = secret_tag {
+            let public_crc = public_part_len(&packet.body)
+                .map(|len| armor::crc24(&packet.body[..len]).to_be_bytes())
+                .ok();
+            let part = parts
+                .iter_mut()
+                .find(|p| p.tag == secret_tag && Some(p.public_crc) == public_crc.map(|c| [c[1], c[2], c[3]]));
+            if packet.packet_type == PacketType::PublicKey {
+                in_key = part.is_some(); // skip any other keys in the public key data
+            }
+            match (in_key, part) {
+                (true, Some(part)) => {
+                    let mut body = Zeroizing::new(packet.body.clone());
+                    body.extend_from_slice(part.secret);
+                    write_packet(&mut out, secret_tag, &body);
+                    part.used = true;
+                    continue;
+                }
+                (true, None) => {} // no secret on paper for this subkey: keep it public
+                (false, _) => continue,
+            }
+        }
+        if in_key {
+            write_packet(&mut out, packet.tag, &packet.body);
+        }
+    }
+    if parts.iter().any(|p| !p.used) {
+        return Err(AppError::InvalidInput(
+            "The public key does not match the paper backup (some secret parts have no public key packet).".to_string(),
+        ));
+    }
+    Ok(out)
+}
+
+pub struct PaperRestore {
+    pub fingerprint: String,
+    pub secret_key: Zeroizing<String>, // armored
+    pub import: Option<ImportReport>,
+}
+
+// Recombines a paper backup with the public key (from `public_key`, or the keyring if None)
+// and imports the result unless `import` is false.
+pub fn restore_paper_backup(text: &str, public_key: Option<&str>, import: bool) -> Result<PaperRestore> {
+    let stream = parse_paper_text(text)?;
+    let (fingerprint, mut parts) = parse_stream(&stream)?;
+    let public_armored = match public_key {
+        Some(key) => key.to_string(),
+        None => gpg_ops::export_key(&fingerprint).map_err(|_| {
+            AppError::InvalidInput(format!(
+                "Public key {} is not in the keyring; provide it with the paper backup.",
+                fingerprint
+            ))
+        })?,
+    };
+    let block = armor::find_armor_blocks(&public_armored)
+        .first()
+        .map(|block| armor::parse_armor(block))
+        .transpose()?
+        .ok_or_else(|| AppError::InvalidInput(format!("No public key found for {}.", fingerprint)))?;
+
+    let secret_packets = rebuild_secret_key(&mut parts, &block.data)?;
+    let secret_key = Zeroizing::new(armor::encode_armor("PRIVATE KEY BLOCK", &secret_packets));
+    let rebuilt = gpg_ops::preview_keys(secret_key.as_bytes())?;
+    if !rebuilt.iter().any(|key| key.fingerprint == fingerprint) {
+        return Err(AppError::InvalidInput(format!(
+            "The rebuilt key does not have the expected fingerprint {}.",
+            fingerprint
+        )));
+    }
+    let import = if import {
+        Some(gpg_ops::import_keys(&secret_key, &ImportOptions::default())?)
+    } else {
+        None
+    };
+    Ok(PaperRestore { fingerprint, secret_key, import })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(stream: &[u8], encoding: PaperEncoding) -> String {
+        let lines = stream
+            .chunks(LINE_BYTES)
+            .enumerate()
+            .map(|(i, chunk)| {
+                format!("{:>3}: {} {}", i + 1, encoding.encode_line(chunk), crc_hex(line_checksum(i + 1, chunk)))
+            })
+            .collect();
+        PaperBackup {
+            fingerprint: "0123456789ABCDEF0123456789ABCDEF01234567".to_string(),
+            user_id: "Test <test@example.org>".to_string(),
+            created: "2026-10-18".to_string(),
+            encoding: encoding.name(),
+            lines,
+            checksum: crc_hex(armor::crc24(stream)),
+            qr_codes: Vec::new(),
+        }
+        .text()
+    }
+
+    fn stream() -> Vec<u8> {
+        (0..45u8).map(|b| b.wrapping_mul(37)).collect() // three lines, the last one short
+    }
+
+    #[test]
+    fn paper_text_round_trip() {
+        for encoding in [PaperEncoding::Base16, PaperEncoding::Base32] {
+            let text = page(&stream(), encoding);
+            assert_eq!(parse_paper_text(&text).unwrap().as_slice(), stream().as_slice());
+            // Lines are found by number, not position
+            let mut lines: Vec<&str> = text.lines().collect();
+            let first = lines.iter().position(|line| line.trim_start().starts_with("1:")).unwrap();
+            lines.swap(first, first + 2);
+            assert_eq!(parse_paper_text(&lines.join("\n")).unwrap().as_slice(), stream().as_slice());
+        }
+    }
+
+    #[test]
+    fn line_errors_are_reported_by_number() {
+        let text = page(&stream(), PaperEncoding::Base16);
+        let line = |n: &str| text.lines().find(|line| line.trim_start().starts_with(n)).unwrap().to_string();
+        let (second, third) = (line("2:"), line("3:"));
+
+        // One mistyped character fails that line's checksum only
+        let mut typo = second.clone();
+        typo.replace_range(5..6, if &second[5..6] == "0" { "1" } else { "0" }); // first data character
+        let error = parse_paper_text(&text.replace(&second, &typo)).unwrap_err().to_string();
+        assert_eq!(error, "Line 2: checksum error, re-check this line.");
+
+        let error = parse_paper_text(&text.replace(&third, "")).unwrap_err().to_string();
+        assert_eq!(error, "Line 3: missing.");
+
+        let error = parse_paper_text(&text.replace(&third, &format!("{}\n{}", third, third))).unwrap_err().to_string();
+        assert_eq!(error, "Line 3: appears twice.");
+
+        let no_checksum = third.rsplit_once(' ').unwrap().0.to_string();
+        let error = parse_paper_text(&text.replace(&third, &no_checksum)).unwrap_err().to_string();
+        assert!(error.contains("Line 3:"), "{}", error);
+
+        let error = parse_paper_text(&text.replace("Lines: 3", "Lines: 2")).unwrap_err().to_string();
+        assert_eq!(error, "Line 3: beyond the 2 lines announced.");
+
+        let error = parse_paper_text(&text.replace("Lines: 3", &format!("Lines: {}", MAX_LINES + 1))).unwrap_err();
+        assert!(error.to_string().contains("more than a paper key can have"));
+    }
+
+    #[test]
+    fn qr_payload_round_trip() {
+        let mut data = stream();
+        data.extend_from_slice(&armor::crc24(&stream()).to_be_bytes()[1..]);
+        let payload = format!("{}{}", PAPER_QR_PREFIX, qr_utils::encode_base32(&data));
+        assert_eq!(parse_paper_text(&payload).unwrap().as_slice(), stream().as_slice());
+
+        data[0] ^= 0x80;
+        let tampered = format!("{}{}", PAPER_QR_PREFIX, qr_utils::encode_base32(&data));
+        assert!(parse_paper_text(&tampered).unwrap_err().to_string().contains("checksum error"));
+    }
+
+    #[test]
+    fn write_packet_length_encodings() {
+        let header = |len: usize| {
+            let mut out = Vec::new();
+            write_packet(&mut out, 5, &vec![0xAA; len]);
+            let body = out.split_off(out.len() - len);
+            assert!(body.iter().all(|&b| b == 0xAA));
+            out
+        };
+        assert_eq!(header(0), [0xC5, 0]);
+        assert_eq!(header(191), [0xC5, 191]);
+        assert_eq!(header(192), [0xC5, 192, 0]);
+        assert_eq!(header(8383), [0xC5, 223, 255]);
+        assert_eq!(header(8384), [0xC5, 255, 0, 0, 0x20, 0xC0]);
+    }
+}
@@ -0,0 +1,203 @@
+use crate::audit;
+use crate::classify::{self, PacketType};
+use crate::error::{AppError, Result};
+use crate::gpg_ops;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// Operation policy: which operations this installation performs at all, and with which keys.
+// It is checked twice, in web_server routing (whole endpoints) and in gpg_ops (every operation,
+// whichever endpoint or command reached it), so a kiosk that must never export secret keys stays
+// that way even through scan actions or the CLI.
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Operation {
+    Export,       // public keys and fingerprints
+    ExportSecret, // secret keys and subkeys, backups, paper keys, share cards
+    Import,       // keys, backups, paper keys, shares
+    Encrypt,
+    Decrypt,
+    Sign,
+    Verify,
+    Certify,
+    Trust, // ownertrust and trust model
+    Revoke,
+    EditKey, // user IDs, subkeys, expiry
+}
+
+impl Operation {
+    pub fn name(self) -> &'static str {
+        match self {
+            Operation::Export => "export",
+            Operation::ExportSecret => "export_secret",
+            Operation::Import => "import",
+            Operation::Encrypt => "encrypt",
+            Operation::Decrypt => "decrypt",
+            Operation::Sign => "sign",
+            Operation::Verify => "verify",
+            Operation::Certify => "certify",
+            Operation::Trust => "trust",
+            Operation::Revoke => "revoke",
+            Operation::EditKey => "edit_key",
+        }
+    }
+
+    // Operations that change the keyring or trust database, refused in read-only mode
+    pub fn modifies_keyring(self) -> bool {
+        matches!(
+            self,
+            Operation::Import | Operation::Certify | Operation::Trust | Operation::Revoke | Operation::EditKey
+        )
+    }
+}
+
+// Policy file (TOML), e.g.
+//   read_only = false
+//   disabled = ["export_secret", "import"]
+//   disabled_endpoints = ["/api/scan_action"]  # web server paths, refused with HTTP 403
+//   sign_keys = ["<fingerprint>"]     # sign and certify only with these keys
+//   decrypt_keys = ["<fingerprint>"]  # decrypt only messages to these keys
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Policy {
+    pub read_only: bool,
+    pub disabled: Vec<Operation>,
+    pub disabled_endpoints: Vec<String>,
+    pub sign_keys: Option<Vec<String>>, // None = any key
+    pub decrypt_keys: Option<Vec<String>>,
+}
+
+lazy_static::lazy_static! {
+    // Permissive until main installs the configured policy
+    static ref POLICY: RwLock<Policy> = RwLock::new(Policy::default());
+}
+
+impl Policy {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| AppError::Config(format!("Cannot read policy file {}: {}", path.display(), e)))?;
+        toml::from_str(&text).map_err(|e| AppError::Config(format!("Invalid policy file {}: {}", path.display(), e)))
+    }
+
+    pub fn allows(&self, operation: Operation) -> bool {
+        let read_only_refused = self.read_only && operation.modifies_keyring();
+        !self.disabled.contains(&operation) && !read_only_refused
+    }
+
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if self.read_only {
+            lines.push("Read-only: no imports, certifications, trust changes, revocations or key edits.".to_string());
+        }
+        if !self.disabled.is_empty() {
+            let names: Vec<&str> = self.disabled.iter().map(|op| op.name()).collect();
+            lines.push(format!("Disabled: {}", names.join(", ")));
+        }
+        if !self.disabled_endpoints.is_empty() {
+            lines.push(format!("Disabled endpoints: {}", self.disabled_endpoints.join(", ")));
+        }
+        if let Some(keys) = &self.sign_keys {
+            lines.push(format!("Signing only with: {}", keys.join(", ")));
+        }
+        if let Some(keys) = &self.decrypt_keys {
+            lines.push(format!("Decryption only for: {}", keys.join(", ")));
+        }
+        lines
+    }
+}
+
+pub fn set_policy(policy: Policy) {
+    *POLICY.write() = policy;
+}
+
+pub fn current() -> Policy {
+    POLICY.read().clone()
+}
+
+// Refusals end up in the audit log like any other failed operation
+fn deny(operation: Operation, keys: &[&str], reason: String) -> Result<()> {
+    let error = AppError::PolicyDenied(reason);
+    audit::record(operation.name(), keys, None, Err(&error));
+    Err(error)
+}
+
+pub fn check(operation: Operation) -> Result<()> {
+    let policy = POLICY.read().clone();
+    if policy.allows(operation) {
+        return Ok(());
+    }
+    let reason = if policy.read_only && operation.modifies_keyring() {
+        format!("{} is not available in read-only mode.", operation.name())
+    } else {
+        format!("{} is disabled on this machine.", operation.name())
+    };
+    deny(operation, &[], reason)
+}
+
+// Web server endpoints switched off as a whole, whatever operation they lead to
+pub fn check_endpoint(path: &str) -> Result<()> {
+    if !POLICY.read().disabled_endpoints.iter().any(|endpoint| endpoint == path) {
+        return Ok(());
+    }
+    let error = AppError::PolicyDenied(format!("{} is disabled on this machine.", path));
+    audit::record(path, &[], None, Err(&error));
+    Err(error)
+}
+
+// Whether `key` is one of `allowed` (fingerprints or anything gpg resolves to a key)
+fn key_allowed(allowed: &[String], key: &str) -> bool {
+    let Ok(fingerprint) = gpg_ops::key_fingerprint(key) else {
+        return false;
+    };
+    allowed.iter().any(|entry| {
+        gpg_ops::key_fingerprint(entry).map(|allowed| allowed.eq_ignore_ascii_case(&fingerprint)).unwrap_or(false)
+    })
+}
+
+// Signing and certifying: the operation must be enabled and the signer on the sign_keys list
+pub fn check_signer(operation: Operation, signer: Option<&str>) -> Result<()> {
+    check(operation)?;
+    let Some(allowed) = POLICY.read().sign_keys.clone() else {
+        return Ok(());
+    };
+    match signer {
+        Some(signer) if key_allowed(&allowed, signer) => Ok(()),
+        Some(signer) => deny(operation, &[signer], format!("Signing with {} is not allowed on this machine.", signer)),
+        None => deny(operation, &[], "A signing key from the policy's sign_keys has to be chosen.".to_string()),
+    }
+}
+
+// Key IDs of the public-key recipients of an armored or binary message (zeros for hidden ones)
+fn recipient_key_ids(ciphertext: &[u8]) -> Vec<String> {
+    let packets: Vec<classify::PacketInfo> = match std::str::from_utf8(ciphertext) {
+        Ok(text) if text.contains("-----BEGIN PGP") => {
+            classify::classify(text).into_iter().flat_map(|block| block.packets).collect()
+        }
+        _ => classify::parse_packets(ciphertext),
+    };
+    packets
+        .iter()
+        .filter(|packet| packet.packet_type == PacketType::PublicKeyEncryptedSessionKey)
+        .filter_map(classify::pkesk_key_id)
+        .collect()
+}
+
+// Decryption: enabled, and with decrypt_keys set the message has to be addressed to one of them
+pub fn check_decrypt(ciphertext: &[u8]) -> Result<()> {
+    check(Operation::Decrypt)?;
+    let Some(allowed) = POLICY.read().decrypt_keys.clone() else {
+        return Ok(());
+    };
+    let recipients = recipient_key_ids(ciphertext);
+    if recipients.iter().any(|key_id| key_allowed(&allowed, key_id)) {
+        return Ok(());
+    }
+    let keys: Vec<&str> = recipients.iter().map(String::as_str).collect();
+    deny(
+        Operation::Decrypt,
+        &keys,
+        "The message is not addressed to a key this machine may decrypt for.".to_string(),
+    )
+}
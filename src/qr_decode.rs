@@ -0,0 +1,84 @@
+use crate::error::{AppError, Result};
+use crate::qr_utils;
+use std::path::{Path, PathBuf};
+
+// Server-side QR decoding for uploaded photos, screenshots and document scans.
+// Lets machines without a webcam feed QR data into the same scan pipeline.
+
+const SUPPORTED_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg"];
+
+// Decodes every QR code found in a PNG/JPEG image.
+// Codes are returned top-to-bottom, left-to-right so multi-QR prints keep their order.
+pub fn decode_image(image_bytes: &[u8]) -> Result<Vec<String>> {
+    let img = image::load_from_memory(image_bytes)
+        .map_err(|e| AppError::QrDecode(format!("Could not read image: {}", e)))?
+        .to_luma8();
+    let mut prepared = rqrr::PreparedImage::prepare(img);
+    let mut grids = prepared.detect_grids();
+    grids.sort_by_key(|g| {
+        let top_left = g.bounds[0];
+        (top_left.y, top_left.x)
+    });
+
+    let mut payloads = Vec::new();
+    for grid in grids {
+        // Raw bytes: binary compact payloads are not UTF-8
+        let mut content = Vec::new();
+        match grid.decode_to(&mut content) {
+            Ok(_meta) => payloads.push(qr_utils::payload_text(content)),
+            // A damaged code next to good ones should not discard the whole image
+            Err(e) => tracing::warn!("Skipping undecodable QR code: {:?}", e),
+        }
+    }
+
+    if payloads.is_empty() {
+        return Err(AppError::QrDecode("No readable QR code found in image.".to_string()));
+    }
+    Ok(payloads)
+}
+
+pub fn decode_image_file(path: &Path) -> Result<Vec<String>> {
+    let bytes = std::fs::read(path)?;
+    decode_image(&bytes)
+        .map_err(|e| AppError::QrDecode(format!("{}: {}", path.display(), e)))
+}
+
+// Decodes all images in a folder (e.g. a sequence of photos of a multi-frame transfer).
+// Files are processed in name order; files that are not images are skipped.
+pub fn decode_image_dir(dir: &Path) -> Result<Vec<String>> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && is_supported_image(path))
+        .collect();
+    files.sort();
+
+    if files.is_empty() {
+        return Err(AppError::QrDecode(format!("No PNG/JPEG images found in {}", dir.display())));
+    }
+
+    let mut payloads = Vec::new();
+    for file in files {
+        payloads.extend(decode_image_file(&file)?);
+    }
+    Ok(payloads)
+}
+
+// Decodes a mix of image files and folders given on the command line
+pub fn decode_paths(paths: &[PathBuf]) -> Result<Vec<String>> {
+    let mut payloads = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            payloads.extend(decode_image_dir(path)?);
+        } else {
+            payloads.extend(decode_image_file(path)?);
+        }
+    }
+    Ok(payloads)
+}
+
+fn is_supported_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| SUPPORTED_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+        .unwrap_or(false)
+}
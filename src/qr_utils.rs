@@ -1,24 +1,193 @@
+use crate::armor;
+use crate::config;
 use crate::error::{AppError, Result};
 use base64::{engine::general_purpose::STANDARD as Base64Engine, Engine as _};
-use qrcodegen::{QrCode, QrCodeEcc};
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use qrcodegen::{Mask, QrCode, QrCodeEcc, QrSegment, Version};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::io::{Read, Write};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum QrEcc {
+    Low,      // ~7% recoverable, most data per code (screen-to-camera transfer)
+    Medium,   // ~15%
+    Quartile, // ~25%
+    High,     // ~30%, for printed codes that get creased or stained
+}
+
+impl QrEcc {
+    fn level(self) -> QrCodeEcc {
+        match self {
+            QrEcc::Low => QrCodeEcc::Low,
+            QrEcc::Medium => QrCodeEcc::Medium,
+            QrEcc::Quartile => QrCodeEcc::Quartile,
+            QrEcc::High => QrCodeEcc::High,
+        }
+    }
+}
+
+// How QR codes are rendered; the defaults come from the [qr] table of the config file
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct QrSettings {
+    pub ecc: QrEcc,
+    pub min_version: u8,    // 1-40, larger pads small payloads to a fixed code size
+    pub max_version: u8,    // 1-40, data needing more is refused (or split into more frames)
+    pub mask: Option<u8>,   // 0-7, None = the best-scoring mask
+    pub border: i32,        // quiet zone, in modules
+    pub module_size: i32,   // px per module
+    pub foreground: String, // #RGB or #RRGGBB
+    pub background: String,
+    pub compact: CompactEncoding, // how armored OpenPGP data is put into codes
+}
+
+impl Default for QrSettings {
+    fn default() -> Self {
+        QrSettings {
+            ecc: QrEcc::Medium,
+            min_version: 1,
+            max_version: 40,
+            mask: None,
+            border: 4,
+            module_size: 5,
+            foreground: "#000000".to_string(),
+            background: "#FFFFFF".to_string(),
+            compact: CompactEncoding::None,
+        }
+    }
+}
+
+// Colors end up in SVG attributes, so only plain hex colors are accepted
+fn is_hex_color(color: &str) -> bool {
+    color.strip_prefix('#').is_some_and(|hex| {
+        matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit())
+    })
+}
+
+impl QrSettings {
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        for (name, version) in [("min_version", self.min_version), ("max_version", self.max_version)] {
+            if !(Version::MIN.value()..=Version::MAX.value()).contains(&version) {
+                return Err(format!("QR {} must be 1 to 40, not {}.", name, version));
+            }
+        }
+        if self.min_version > self.max_version {
+            return Err(format!("QR min_version {} is above max_version {}.", self.min_version, self.max_version));
+        }
+        if let Some(mask) = self.mask.filter(|mask| *mask > 7) {
+            return Err(format!("QR mask must be 0 to 7, not {}.", mask));
+        }
+        if !(0..=40).contains(&self.border) {
+            return Err(format!("QR border must be 0 to 40 modules, not {}.", self.border));
+        }
+        if !(1..=100).contains(&self.module_size) {
+            return Err(format!("QR module size must be 1 to 100 px, not {}.", self.module_size));
+        }
+        for (name, color) in [("foreground", &self.foreground), ("background", &self.background)] {
+            if !is_hex_color(color) {
+                return Err(format!("QR {} must be a hex color like #000000, not {:?}.", name, color));
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self, data: &str) -> Result<QrCode> {
+        let segments = segments(data);
+        QrCode::encode_segments_advanced(
+            &segments,
+            self.ecc.level(),
+            Version::new(self.min_version),
+            Version::new(self.max_version),
+            self.mask.map(Mask::new),
+            true,
+        )
+        .map_err(|e| AppError::QrCodeGen(format!("QR encoding failed: {}", e)))
+    }
+}
+
+// Settings given with one request (query parameters or CLI flags), on top of the configured ones
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct QrOverrides {
+    pub ecc: Option<QrEcc>,
+    pub min_version: Option<u8>,
+    pub max_version: Option<u8>,
+    pub mask: Option<u8>,
+    pub border: Option<i32>,
+    pub module_size: Option<i32>,
+    pub foreground: Option<String>,
+    pub background: Option<String>,
+    pub compact: Option<CompactEncoding>,
+}
+
+impl QrOverrides {
+    pub fn is_empty(&self) -> bool {
+        self.ecc.is_none()
+            && self.min_version.is_none()
+            && self.max_version.is_none()
+            && self.mask.is_none()
+            && self.border.is_none()
+            && self.module_size.is_none()
+            && self.foreground.is_none()
+            && self.background.is_none()
+            && self.compact.is_none()
+    }
+
+    pub fn apply(&self, settings: &QrSettings) -> Result<QrSettings> {
+        let mut settings = settings.clone();
+        settings.ecc = self.ecc.unwrap_or(settings.ecc);
+        settings.min_version = self.min_version.unwrap_or(settings.min_version);
+        settings.max_version = self.max_version.unwrap_or(settings.max_version);
+        settings.mask = self.mask.or(settings.mask);
+        settings.border = self.border.unwrap_or(settings.border);
+        settings.module_size = self.module_size.unwrap_or(settings.module_size);
+        if let Some(color) = &self.foreground {
+            settings.foreground = color.clone();
+        }
+        if let Some(color) = &self.background {
+            settings.background = color.clone();
+        }
+        settings.compact = self.compact.unwrap_or(settings.compact);
+        settings.validate().map_err(AppError::InvalidInput)?;
+        Ok(settings)
+    }
+}
+
+tokio::task_local! {
+    // Per-request settings, set by the web server around the handler
+    pub static REQUEST_SETTINGS: QrSettings;
+}
+
+// The request's settings if it gave any, else the configured ones
+pub fn current_settings() -> QrSettings {
+    REQUEST_SETTINGS.try_with(QrSettings::clone).unwrap_or_else(|_| config::current().qr)
+}
 
 // Generates a QR code as an SVG string
 pub fn generate_qr_svg(data: &str) -> Result<String> {
-    let ecc = QrCodeEcc::Medium; // Error correction level
-    let qr = QrCode::encode_text(data, ecc)
-        .map_err(|e| AppError::QrCodeGen(format!("QR encoding failed: {}", e)))?;
+    let settings = current_settings();
+    render_svg(&compacted(data, &settings), &settings)
+}
+
+// The data as it goes into codes: compact if the settings ask for it and the data allows it
+fn compacted<'a>(data: &'a str, settings: &QrSettings) -> Cow<'a, str> {
+    compact_payload(data, settings.compact).map_or(Cow::Borrowed(data), Cow::Owned)
+}
 
-    // Simple SVG generation (adjust parameters as needed)
-    let border = 4;
-    let module_size = 5; // px per module
+fn render_svg(data: &str, settings: &QrSettings) -> Result<String> {
+    let qr = settings.encode(data)?;
+
+    let border = settings.border;
+    let module_size = settings.module_size;
     let size = qr.size();
     let dim = (size + border * 2) * module_size;
     let mut svg = format!(
-        r#"<svg xmlns="http://www.w3.org/2000/svg" version="1.1" viewBox="0 0 {0} {0}" stroke="none">"#,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" version="1.1" width="{0}" height="{0}" viewBox="0 0 {0} {0}" stroke="none">"#,
         dim
     );
-    svg.push_str(r#"<rect width="100%" height="100%" fill="#FFFFFF"/>"#); // White background
-    svg.push_str(r#"<path d=""#); // Start path for black modules
+    svg.push_str(&format!(r#"<rect width="100%" height="100%" fill="{}"/>"#, settings.background)); // Background
+    svg.push_str(r#"<path d=""#); // Start path for the dark modules
 
     for y in 0..size {
         for x in 0..size {
@@ -30,12 +199,439 @@ pub fn generate_qr_svg(data: &str) -> Result<String> {
             }
         }
     }
-    svg.push_str(r#"" fill="#000000"/>"#); // Fill path black
+    svg.push_str(&format!(r#"" fill="{}"/>"#, settings.foreground)); // Fill path with the foreground
     svg.push_str(r#"</svg>"#);
 
     Ok(svg)
 }
 
+// Size of a payload in QR terms, so callers can decide before rendering anything
+#[derive(Serialize, Debug, Clone)]
+pub struct QrEstimate {
+    pub bytes: usize,
+    pub qr_version: Option<u8>,  // smallest version holding the data in one code, None if too large
+    pub fits_single_qr: bool,
+    pub frames: usize,           // number of frames for a multi-frame transfer
+}
+
+impl QrEstimate {
+    pub fn describe(&self) -> String {
+        match self.qr_version {
+            Some(version) => format!("{} bytes, fits a single QR code (version {}).", self.bytes, version),
+            None => format!(
+                "{} bytes, too large for a single QR code: needs {} frames.",
+                self.bytes, self.frames
+            ),
+        }
+    }
+}
+
+pub fn estimate_qr(data: &str) -> QrEstimate {
+    let settings = current_settings();
+    let data = compacted(data, &settings);
+    let qr_version = settings.encode(&data).ok().map(|qr| qr.version().value());
+    QrEstimate {
+        bytes: data.len(),
+        qr_version,
+        fits_single_qr: qr_version.is_some(),
+        frames: data.chars().count().div_ceil(frame_chunk(&data)).max(1),
+    }
+}
+
+// --- Fingerprint exchange ---
+// `OPENPGP4FPR:<FINGERPRINT>` is the format OpenKeychain and similar apps use for
+// in-person key verification. Upper case keeps the code in QR alphanumeric mode,
+// so a v4 fingerprint fits into a version-3 code.
+
+pub fn fingerprint_uri(fingerprint: &str) -> String {
+    format!("OPENPGP4FPR:{}", fingerprint.replace(' ', "").to_ascii_uppercase())
+}
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+// RFC 4648 base32 without padding: only uppercase letters and digits, so binary data
+// encodes in the denser QR alphanumeric mode
+pub fn encode_base32(data: &[u8]) -> String {
+    let mut out = String::new();
+    let (mut buffer, mut bits) = (0u32, 0);
+    for &byte in data {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+    out
+}
+
+pub fn decode_base32(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in text.bytes() {
+        let value = BASE32_ALPHABET.iter().position(|&a| a == c.to_ascii_uppercase())? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+// --- Compact transfer encoding ---
+// Armor spends a third on base64, plus headers and CRC. Compact payloads carry the dearmored
+// packets instead, deflated when that is smaller: `SGQRZ:` + base45 (RFC 9285) keeps codes in
+// alphanumeric mode, `SGQRB:` + raw bytes uses byte mode. Bytes travel through the text scan
+// pipeline as a Latin-1 string (one char per byte); their codes carry an ISO-8859-1 ECI so
+// scanners return them unchanged. Scanning re-armors both, see expand_compact.
+// Binary container: version, flags, armor label code, Comment headers (only with
+// COMPACT_COMMENTS: lines joined by \n, NUL-terminated), packets.
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CompactEncoding {
+    None,   // armored text, readable by any QR scanner app
+    Base45, // alphanumeric mode, works with every scanner that returns text
+    Binary, // byte mode, densest; needs a scanner that honours the ECI
+}
+
+pub const COMPACT_BASE45_PREFIX: &str = "SGQRZ:";
+pub const COMPACT_BINARY_PREFIX: &str = "SGQRB:";
+const COMPACT_VERSION: u8 = 1;
+const COMPACT_DEFLATE: u8 = 0x01;
+const COMPACT_COMMENTS: u8 = 0x02;
+const MAX_INFLATED: u64 = 16 * 1024 * 1024;
+const ARMOR_LABEL_CODES: &[(u8, &str)] =
+    &[(1, "PUBLIC KEY BLOCK"), (2, "PRIVATE KEY BLOCK"), (3, "MESSAGE"), (4, "SIGNATURE")];
+const ECI_ISO_8859_1: u32 = 3;
+
+// Compact form of `data`, if it is exactly one armored block. Comment headers are carried along
+// (backups are recognised by theirs), Version headers dropped. Cleartext signed messages and
+// blocks with other headers stay as they are.
+pub fn compact_payload(data: &str, encoding: CompactEncoding) -> Option<String> {
+    if encoding == CompactEncoding::None {
+        return None;
+    }
+    let data = data.trim();
+    if armor::find_armor_blocks(data).first().map(|block| block.len()) != Some(data.len()) {
+        return None;
+    }
+    let block = armor::parse_armor(data).ok()?;
+    let plain_headers = block.headers.iter().all(|(key, _)| key == "Version" || key == "Comment");
+    if block.cleartext.is_some() || block.checksum_valid == Some(false) || !plain_headers {
+        return None;
+    }
+    let label_code = ARMOR_LABEL_CODES.iter().find(|(_, label)| *label == block.label)?.0;
+    let comments: Vec<&str> =
+        block.headers.iter().filter(|(key, _)| key == "Comment").map(|(_, value)| value.as_str()).collect();
+    if comments.iter().any(|comment| comment.contains('\0')) {
+        return None;
+    }
+    let (mut flags, packets) = match deflate(&block.data) {
+        Some(deflated) if deflated.len() < block.data.len() => (COMPACT_DEFLATE, deflated),
+        _ => (0, block.data),
+    };
+    if !comments.is_empty() {
+        flags |= COMPACT_COMMENTS;
+    }
+    let mut container = vec![COMPACT_VERSION, flags, label_code];
+    if !comments.is_empty() {
+        container.extend_from_slice(comments.join("\n").as_bytes());
+        container.push(0);
+    }
+    container.extend_from_slice(&packets);
+    match encoding {
+        CompactEncoding::Base45 => Some(format!("{}{}", COMPACT_BASE45_PREFIX, encode_base45(&container))),
+        CompactEncoding::Binary => {
+            Some(format!("{}{}", COMPACT_BINARY_PREFIX, container.iter().map(|&b| b as char).collect::<String>()))
+        }
+        CompactEncoding::None => None,
+    }
+}
+
+// Re-armors a compact payload; None if `text` is not one
+pub fn expand_compact(text: &str) -> Result<Option<String>> {
+    // Scanners may add a line break, but a trailing space is base45 data
+    let text = text.trim_start().trim_end_matches(['\r', '\n']);
+    let container = if let Some(encoded) = text.strip_prefix(COMPACT_BASE45_PREFIX) {
+        decode_base45(encoded)
+            .ok_or_else(|| AppError::InvalidInput("Compact QR payload is not valid base45.".to_string()))?
+    } else if let Some(encoded) = text.strip_prefix(COMPACT_BINARY_PREFIX) {
+        latin1_bytes(encoded).ok_or_else(|| {
+            AppError::InvalidInput("Binary QR payload was not read as bytes (scanner ignored the ECI).".to_string())
+        })?
+    } else {
+        return Ok(None);
+    };
+    let [version, flags, label_code, packets @ ..] = container.as_slice() else {
+        return Err(AppError::InvalidInput("Compact QR payload is truncated.".to_string()));
+    };
+    if *version != COMPACT_VERSION {
+        return Err(AppError::InvalidInput(format!("Unsupported compact QR payload version {}.", version)));
+    }
+    let label = ARMOR_LABEL_CODES
+        .iter()
+        .find(|(code, _)| code == label_code)
+        .map(|(_, label)| *label)
+        .ok_or_else(|| AppError::InvalidInput(format!("Unknown armor type {} in compact QR payload.", label_code)))?;
+    let (comments, packets) = if flags & COMPACT_COMMENTS != 0 {
+        let end = packets
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(|| AppError::InvalidInput("Compact QR payload is truncated.".to_string()))?;
+        let comments = std::str::from_utf8(&packets[..end])
+            .map_err(|_| AppError::InvalidInput("Compact QR payload has an invalid comment.".to_string()))?;
+        (comments.split('\n').collect(), &packets[end + 1..])
+    } else {
+        (Vec::new(), packets)
+    };
+    let packets = if flags & COMPACT_DEFLATE != 0 { inflate(packets)? } else { packets.to_vec() };
+    let armored = armor::encode_armor(label, &packets);
+    // Headers go right after the BEGIN line
+    Ok(Some(match armored.split_once('\n') {
+        Some((begin, rest)) if !comments.is_empty() => {
+            let headers: String = comments.iter().map(|comment| format!("Comment: {}\n", comment)).collect();
+            format!("{}\n{}{}", begin, headers, rest)
+        }
+        _ => armored,
+    }))
+}
+
+fn deflate(data: &[u8]) -> Option<Vec<u8>> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(data).ok()?;
+    encoder.finish().ok()
+}
+
+fn inflate(data: &[u8]) -> Result<Vec<u8>> {
+    let mut inflated = Vec::new();
+    DeflateDecoder::new(data)
+        .take(MAX_INFLATED + 1)
+        .read_to_end(&mut inflated)
+        .map_err(|e| AppError::InvalidInput(format!("Compact QR payload does not decompress: {}", e)))?;
+    if inflated.len() as u64 > MAX_INFLATED {
+        return Err(AppError::InvalidInput("Compact QR payload decompresses to more than 16 MiB.".to_string()));
+    }
+    Ok(inflated)
+}
+
+// Latin-1 strings (binary payloads and their frames) go into byte mode as they are, with an
+// ISO-8859-1 ECI; everything else gets qrcodegen's usual segments
+fn segments(text: &str) -> Vec<QrSegment> {
+    if text.starts_with(COMPACT_BINARY_PREFIX) || text.starts_with(BINARY_FRAME_PREFIX) {
+        if let Some(bytes) = latin1_bytes(text) {
+            return vec![QrSegment::make_eci(ECI_ISO_8859_1), QrSegment::make_bytes(&bytes)];
+        }
+    }
+    QrSegment::make_segments(text)
+}
+
+pub fn latin1_bytes(text: &str) -> Option<Vec<u8>> {
+    text.chars().map(|c| u8::try_from(c).ok()).collect()
+}
+
+// Text of a decoded code: binary payloads as Latin-1, everything else as UTF-8. Bytes that
+// are not UTF-8 (e.g. raw OpenPGP packets) are Latin-1 too, the QR default, which keeps
+// them intact for classify instead of replacing them.
+pub fn payload_text(bytes: Vec<u8>) -> String {
+    if bytes.starts_with(COMPACT_BINARY_PREFIX.as_bytes()) || bytes.starts_with(BINARY_FRAME_PREFIX.as_bytes()) {
+        return bytes.iter().map(|&b| b as char).collect();
+    }
+    String::from_utf8(bytes).unwrap_or_else(|e| e.as_bytes().iter().map(|&b| b as char).collect())
+}
+
+const BASE45_ALPHABET: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+// RFC 9285: two bytes become three chars of the QR alphanumeric set (a last odd byte two)
+fn encode_base45(data: &[u8]) -> String {
+    let mut out = String::new();
+    for pair in data.chunks(2) {
+        let (mut value, digits) = match pair {
+            [a, b] => ((*a as usize) << 8 | *b as usize, 3),
+            _ => (pair[0] as usize, 2),
+        };
+        for _ in 0..digits {
+            out.push(BASE45_ALPHABET[value % 45] as char);
+            value /= 45;
+        }
+    }
+    out
+}
+
+fn decode_base45(text: &str) -> Option<Vec<u8>> {
+    let digits: Vec<usize> = text
+        .bytes()
+        .map(|c| BASE45_ALPHABET.iter().position(|&a| a == c))
+        .collect::<Option<_>>()?;
+    let mut out = Vec::new();
+    for group in digits.chunks(3) {
+        let value = group.iter().rev().fold(0, |value, digit| value * 45 + digit);
+        match group.len() {
+            3 if value <= 0xFFFF => out.extend_from_slice(&[(value >> 8) as u8, value as u8]),
+            2 if value <= 0xFF => out.push(value as u8),
+            _ => return None,
+        }
+    }
+    Some(out)
+}
+
+// --- Multi-frame transfer ---
+// Payloads that do not fit into a single QR code are split into numbered frames.
+// Each frame is a plain text QR code of the form `SGQR:<set-id>:<index>/<total>:<chunk>`
+// (`SGQB:` for binary compact payloads), so frames can be scanned in any order (camera,
+// photos, document scanner) and frames of different transfers are never mixed up.
+
+pub const FRAME_PREFIX: &str = "SGQR:";
+const BINARY_FRAME_PREFIX: &str = "SGQB:"; // frames of a binary compact payload
+pub const DEFAULT_FRAME_CHUNK: usize = 1000; // chars per frame, keeps codes scannable at Medium ECC
+const ALPHANUMERIC_FRAME_CHUNK: usize = 1450; // 5.5 instead of 8 bits per char: the same code size
+const MIN_FRAME_CHUNK: usize = 16;
+const MAX_FRAMES: usize = 256; // bounds what a scanned `<total>` can make us allocate
+
+fn frame_chunk(data: &str) -> usize {
+    if QrSegment::is_alphanumeric(data) {
+        ALPHANUMERIC_FRAME_CHUNK
+    } else {
+        DEFAULT_FRAME_CHUNK
+    }
+}
+
+// Splits data into frame strings. Data that fits into one chunk is still framed,
+// which lets the scanner tell single and multi-frame transfers apart consistently.
+pub fn split_into_frames(data: &str, chunk_size: usize) -> Result<Vec<String>> {
+    if chunk_size == 0 {
+        return Err(AppError::InvalidInput("Frame chunk size must be greater than zero.".to_string()));
+    }
+    // Upper case keeps frames of base45 data in alphanumeric mode
+    let set_id = format!("{:08X}", rand::random::<u32>());
+    let prefix = if data.starts_with(COMPACT_BINARY_PREFIX) { BINARY_FRAME_PREFIX } else { FRAME_PREFIX };
+    let chars: Vec<char> = data.chars().collect();
+    let chunks: Vec<String> = if chars.is_empty() {
+        vec![String::new()]
+    } else {
+        chars.chunks(chunk_size).map(|c| c.iter().collect()).collect()
+    };
+    let total = chunks.len();
+    if total > MAX_FRAMES {
+        return Err(AppError::InvalidInput(format!(
+            "Data needs {} QR frames, more than the {} a frame set can have.",
+            total, MAX_FRAMES
+        )));
+    }
+    Ok(chunks
+        .into_iter()
+        .enumerate()
+        .map(|(i, chunk)| format!("{}{}:{}/{}:{}", prefix, set_id, i + 1, total, chunk))
+        .collect())
+}
+
+// Generates one SVG per frame for data too large for a single QR code. Frames get smaller
+// (and more) when the ECC level and max_version in use cannot hold DEFAULT_FRAME_CHUNK chars.
+pub fn generate_qr_frames_svg(data: &str) -> Result<Vec<String>> {
+    let settings = current_settings();
+    let data = compacted(data, &settings);
+    let mut chunk_size = frame_chunk(&data);
+    loop {
+        let frames: Result<Vec<String>> =
+            split_into_frames(&data, chunk_size)?.iter().map(|frame| render_svg(frame, &settings)).collect();
+        match frames {
+            Err(AppError::QrCodeGen(_)) if chunk_size > MIN_FRAME_CHUNK => {
+                chunk_size = (chunk_size * 3 / 4).max(MIN_FRAME_CHUNK);
+            }
+            frames => return frames,
+        }
+    }
+}
+
+// For printed pages: a single code if the data fits into one, otherwise a frame set
+pub fn generate_printable_qr_svg(data: &str) -> Result<Vec<String>> {
+    match generate_qr_svg(data) {
+        Ok(svg) => Ok(vec![svg]),
+        Err(_) => generate_qr_frames_svg(data),
+    }
+}
+
+struct Frame<'a> {
+    set_id: &'a str,
+    index: usize,
+    total: usize,
+    chunk: &'a str,
+}
+
+fn parse_frame(payload: &str) -> Option<Frame<'_>> {
+    let rest = payload.strip_prefix(FRAME_PREFIX).or_else(|| payload.strip_prefix(BINARY_FRAME_PREFIX))?;
+    let (set_id, rest) = rest.split_once(':')?;
+    let (position, chunk) = rest.split_once(':')?;
+    let (index, total) = position.split_once('/')?;
+    let index: usize = index.parse().ok()?;
+    let total: usize = total.parse().ok()?;
+    if index == 0 || index > total {
+        return None;
+    }
+    Some(Frame { set_id, index, total, chunk })
+}
+
+// Reassembles scanned payloads. Frames are grouped by set id and joined in order;
+// payloads that are not frames are passed through unchanged. Duplicate frames
+// (the same code photographed twice) are ignored, missing frames are an error.
+pub fn reassemble_frames(payloads: &[String]) -> Result<Vec<String>> {
+    let mut results = Vec::new();
+    let mut sets: Vec<(String, usize, Vec<Option<String>>)> = Vec::new();
+
+    for payload in payloads {
+        let Some(frame) = parse_frame(payload) else {
+            results.push(payload.clone());
+            continue;
+        };
+        if frame.total > MAX_FRAMES {
+            return Err(AppError::InvalidInput(format!(
+                "Frame set {} claims {} frames, more than the maximum of {}.",
+                frame.set_id, frame.total, MAX_FRAMES
+            )));
+        }
+        let pos = match sets.iter().position(|(id, _, _)| id == frame.set_id) {
+            Some(pos) => pos,
+            None => {
+                sets.push((frame.set_id.to_string(), frame.total, vec![None; frame.total]));
+                sets.len() - 1
+            }
+        };
+        let (_, total, parts) = &mut sets[pos];
+        if *total != frame.total {
+            return Err(AppError::InvalidInput(format!(
+                "Frame set {} has inconsistent frame counts ({} vs {}).",
+                frame.set_id, total, frame.total
+            )));
+        }
+        parts[frame.index - 1].get_or_insert_with(|| frame.chunk.to_string());
+    }
+
+    for (set_id, total, parts) in sets {
+        let missing: Vec<String> = parts
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.is_none())
+            .map(|(i, _)| (i + 1).to_string())
+            .collect();
+        if !missing.is_empty() {
+            return Err(AppError::InvalidInput(format!(
+                "Frame set {} is incomplete: missing frame(s) {} of {}.",
+                set_id,
+                missing.join(", "),
+                total
+            )));
+        }
+        results.push(parts.into_iter().flatten().collect());
+    }
+
+    Ok(results)
+}
+
 // Optional: Generate as Base64 PNG (requires an image library like image + png)
 /*
 pub fn generate_qr_base64_png(data: &str) -> Result<String> {
@@ -48,4 +644,106 @@ pub fn generate_qr_base64_png(data: &str) -> Result<String> {
     // Requires adding `image` and `png` crates
     unimplemented!("PNG generation requires image/png crates");
 }
-*/
\ No newline at end of file
+*/
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn armored_message(headers: &str) -> String {
+        // Repetitive enough for deflate to win
+        let packets: Vec<u8> = (0..600u32).map(|i| (i % 7) as u8).collect();
+        let armored = armor::encode_armor("MESSAGE", &packets);
+        let (begin, rest) = armored.split_once('\n').unwrap();
+        format!("{}\n{}{}", begin, headers, rest)
+    }
+
+    #[test]
+    fn base45_rfc9285_vectors() {
+        let vectors: [(&[u8], &str); 5] = [
+            (b"AB", "BB8"),
+            (b"Hello!!", "%69 VD92EX0"),
+            (b"base-45", "UJCLQE7W581"),
+            (b"ietf!", "QED8WEX0"),
+            (b"", ""),
+        ];
+        for (data, encoded) in vectors {
+            assert_eq!(encode_base45(data), encoded);
+            assert_eq!(decode_base45(encoded).as_deref(), Some(data));
+        }
+        assert_eq!(decode_base45("GGW"), None); // 65536 does not fit into two bytes
+        assert_eq!(decode_base45("B"), None);
+        assert_eq!(decode_base45("bb8"), None);
+    }
+
+    #[test]
+    fn base45_round_trip() {
+        let data: Vec<u8> = (0..=255u8).collect();
+        for len in 0..data.len() {
+            assert_eq!(decode_base45(&encode_base45(&data[..len])).as_deref(), Some(&data[..len]));
+        }
+    }
+
+    #[test]
+    fn compact_round_trip_keeps_comments() {
+        let original = armored_message("Version: GnuPG v2\nComment: Secure-GPG-QR keyring backup\nComment: second\n");
+        let block = armor::parse_armor(&original).unwrap();
+        for encoding in [CompactEncoding::Base45, CompactEncoding::Binary] {
+            let compact = compact_payload(&original, encoding).unwrap();
+            assert!(compact.len() < original.len());
+            // Binary payloads reach expand_compact the way a scanner returns their bytes
+            let scanned = match encoding {
+                CompactEncoding::Binary => payload_text(latin1_bytes(&compact).unwrap()),
+                _ => compact,
+            };
+            let expanded = armor::parse_armor(&expand_compact(&scanned).unwrap().unwrap()).unwrap();
+            assert_eq!(expanded.label, block.label);
+            assert_eq!(expanded.data, block.data);
+            assert_eq!(expanded.checksum_valid, Some(true));
+            let comments = [
+                ("Comment".to_string(), "Secure-GPG-QR keyring backup".to_string()),
+                ("Comment".to_string(), "second".to_string()),
+            ];
+            assert_eq!(expanded.headers, comments);
+        }
+    }
+
+    #[test]
+    fn compact_leaves_other_data_alone() {
+        let message = armored_message("");
+        assert_eq!(compact_payload(&message, CompactEncoding::None), None);
+        assert_eq!(compact_payload(&armored_message("Charset: UTF-8\n"), CompactEncoding::Base45), None);
+        assert_eq!(compact_payload(&format!("{}\n{}", message, message), CompactEncoding::Base45), None);
+        assert_eq!(compact_payload("openpgp4fpr:0123456789ABCDEF", CompactEncoding::Base45), None);
+        assert_eq!(expand_compact("SGQR:not compact").unwrap(), None);
+        assert!(expand_compact("SGQRZ:GGW").is_err());
+    }
+
+    #[test]
+    fn frames_reassemble_out_of_order_with_duplicates() {
+        let data: String = (0..100).map(|i| char::from(b'a' + (i % 26) as u8)).collect();
+        let other = "second transfer".to_string();
+        let mut frames = split_into_frames(&data, 16).unwrap();
+        assert_eq!(frames.len(), 7);
+        frames.reverse();
+        frames.push(frames[2].clone());
+        frames.insert(3, frames[0].clone());
+        frames.insert(1, "not a frame".to_string());
+        frames.extend(split_into_frames(&other, 1000).unwrap());
+
+        let reassembled = reassemble_frames(&frames).unwrap();
+        assert_eq!(reassembled, vec!["not a frame".to_string(), data, other]);
+    }
+
+    #[test]
+    fn incomplete_or_oversized_frame_sets_are_refused() {
+        let mut frames = split_into_frames("0123456789abcdefghij", 8).unwrap();
+        frames.remove(1);
+        let error = reassemble_frames(&frames).unwrap_err();
+        assert!(error.to_string().contains("missing frame(s) 2 of 3"), "{}", error);
+
+        let huge = format!("SGQR:ABCDEF01:1/{}:x", MAX_FRAMES + 1);
+        assert!(reassemble_frames(&[huge]).is_err());
+        assert!(split_into_frames(&"x".repeat(MAX_FRAMES + 1), 1).is_err());
+        assert!(split_into_frames("x", 0).is_err());
+    }
+}
@@ -0,0 +1,454 @@
+use crate::airgap;
+use crate::backup;
+use crate::classify::{self, ClassifiedBlock, PacketInfo, PacketType, PayloadKind};
+use crate::config;
+use crate::error::{AppError, Result};
+use crate::gpg_ops::{self, KeyPreview, KeySummary};
+use crate::paperkey;
+use crate::policy::{self, Operation};
+use crate::qr_utils;
+use crate::shamir;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use zeroize::Zeroizing;
+
+// Shared scan pipeline: camera scans (browser), uploaded images and the CLI
+// all end up here so the same classification is applied everywhere.
+
+// Actions the server can perform on a scanned payload
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanAction {
+    ImportKey,
+    Decrypt,
+    Verify,
+    CertifyKey, // local certification of a key verified via openpgp4fpr
+    ApplyRevocation,
+    CombineShares, // enough secret shares of one set have been scanned
+    CheckSignResponse, // online side: check a signing response against our pending request
+    AnswerDecryptRequest, // offline side: decrypt and re-encrypt the result as the request asks
+    RevealDecryptRequest, // offline side: show the plaintext of a decryption request (explicit opt-in)
+    OpenDecryptResponse, // online side: open a decryption response with our pending session
+}
+
+impl ScanAction {
+    // What the action does, for the policy check before it runs
+    pub fn operation(self) -> Operation {
+        match self {
+            ScanAction::ImportKey | ScanAction::CombineShares => Operation::Import,
+            ScanAction::Decrypt
+            | ScanAction::AnswerDecryptRequest
+            | ScanAction::RevealDecryptRequest
+            | ScanAction::OpenDecryptResponse => Operation::Decrypt,
+            ScanAction::Verify | ScanAction::CheckSignResponse => Operation::Verify,
+            ScanAction::CertifyKey => Operation::Certify,
+            ScanAction::ApplyRevocation => Operation::Revoke,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Recipient {
+    pub key_id: String,                 // 16 hex digits, all zeros for hidden recipients
+    pub secret_key_uid: Option<String>, // set if we hold the matching secret key
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ScanAnalysis {
+    pub kind: PayloadKind,
+    pub data_type: &'static str,     // human readable form of `kind`
+    pub armor_label: Option<String>,
+    pub armor_headers: Vec<(String, String)>,
+    pub checksum_valid: Option<bool>,
+    pub packets: Vec<PacketInfo>,
+    pub keys: Vec<KeyPreview>,       // keys contained in a key block and what importing them changes
+    pub recipients: Vec<Recipient>,  // public-key recipients of an encrypted message
+    pub symmetric: bool,             // message can be decrypted with a passphrase
+    pub fingerprint: Option<String>, // fingerprint from an openpgp4fpr URI
+    pub fingerprint_key: Option<KeySummary>, // keyring key matching an openpgp4fpr URI or revocation certificate
+    pub actions: Vec<ScanAction>,    // what can actually be done with this payload
+    pub notes: Vec<String>,          // problems found while analysing
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ScannedPayload {
+    pub data: String,
+    #[serde(flatten)]
+    pub analysis: ScanAnalysis,
+}
+
+// Reassembles multi-frame transfers and analyses every resulting payload
+pub fn process_payloads(raw_payloads: &[String]) -> Result<Vec<ScannedPayload>> {
+    Ok(qr_utils::reassemble_frames(raw_payloads)?
+        .iter()
+        .flat_map(|data| analyze_payload(data))
+        .collect())
+}
+
+// Classifies a payload and analyses each OpenPGP block in it separately,
+// so a scan containing e.g. two keys and a message yields three entries.
+pub fn analyze_payload(data: &str) -> Vec<ScannedPayload> {
+    // Compact QR payloads are re-armored first, so everything after sees the usual armor
+    let (data, problem) = match qr_utils::expand_compact(data) {
+        Ok(Some(armored)) => (armored, None),
+        Ok(None) => (data.to_string(), None),
+        Err(e) => (data.to_string(), Some(e.to_string())),
+    };
+    classify::classify(&data)
+        .into_iter()
+        .map(|block| {
+            let mut analysis = analyze(&block);
+            analysis.notes.extend(problem.clone());
+            ScannedPayload { data: block.text, analysis }
+        })
+        .collect()
+}
+
+// Looks inside a classified block and works out which actions are possible.
+// Never fails: problems are reported in `notes` and simply remove actions.
+pub fn analyze(block: &ClassifiedBlock) -> ScanAnalysis {
+    let mut analysis = ScanAnalysis {
+        kind: block.kind,
+        data_type: block.kind.description(),
+        armor_label: block.armor_label.clone(),
+        armor_headers: block.armor_headers.clone(),
+        checksum_valid: block.checksum_valid,
+        packets: block.packets.clone(),
+        keys: Vec::new(),
+        recipients: Vec::new(),
+        symmetric: false,
+        fingerprint: block.fingerprint.clone(),
+        fingerprint_key: None,
+        actions: Vec::new(),
+        notes: block.notes.clone(),
+    };
+
+    match block.kind {
+        PayloadKind::PublicKey | PayloadKind::SecretKey => match gpg_ops::preview_keys(block.text.as_bytes()) {
+            Ok(keys) if !keys.is_empty() => {
+                // Nothing is imported until the user confirms the previewed changes
+                analysis.notes.extend(keys.iter().map(|key| format!("Import preview: {}", key.describe())));
+                analysis.keys = keys;
+                analysis.actions.push(ScanAction::ImportKey);
+            }
+            Ok(_) => analysis.notes.push("Key block contains no usable keys.".to_string()),
+            Err(e) => analysis.notes.push(format!("Key block could not be read: {}", e)),
+        },
+        PayloadKind::EncryptedMessage => analyze_encrypted(block, &mut analysis),
+        PayloadKind::SignedMessage | PayloadKind::CleartextSigned => analysis.actions.push(ScanAction::Verify),
+        PayloadKind::DetachedSignature => analysis
+            .notes
+            .push("Detached signature: verification needs the signed data as well.".to_string()),
+        PayloadKind::RevocationCertificate => analyze_revocation(block, &mut analysis),
+        PayloadKind::FingerprintUri => analyze_fingerprint(&mut analysis),
+        PayloadKind::PlainText if shamir::is_share(&block.text) => match shamir::parse_share(&block.text) {
+            Ok(share) => analysis.notes.push(share.describe()),
+            Err(e) => analysis.notes.push(e.to_string()),
+        },
+        PayloadKind::PlainText if block.text.trim_start().starts_with(airgap::SIGN_REQUEST_PREFIX) => {
+            analyze_sign_request(block, &mut analysis)
+        }
+        PayloadKind::PlainText if block.text.trim_start().starts_with(airgap::SIGN_RESPONSE_PREFIX) => {
+            analyze_sign_response(block, &mut analysis)
+        }
+        PayloadKind::PlainText if block.text.trim_start().starts_with(airgap::DECRYPT_REQUEST_PREFIX) => {
+            analyze_decrypt_request(block, &mut analysis)
+        }
+        PayloadKind::PlainText if block.text.trim_start().starts_with(airgap::DECRYPT_RESPONSE_PREFIX) => {
+            analyze_decrypt_response(block, &mut analysis)
+        }
+        PayloadKind::PlainText if block.text.trim_start().starts_with(paperkey::PAPER_QR_PREFIX) => analysis
+            .notes
+            .push("Paper key backup: restore it with the Paper Backup Restore form.".to_string()),
+        PayloadKind::Invalid | PayloadKind::PlainText => {}
+    }
+
+    analysis
+}
+
+fn analyze_encrypted(block: &ClassifiedBlock, analysis: &mut ScanAnalysis) {
+    if backup::is_backup_archive(&block.armor_headers) {
+        analysis
+            .notes
+            .push("Keyring backup archive: restore it with the Restore Backup form.".to_string());
+        return;
+    }
+    for packet in &block.packets {
+        match packet.packet_type {
+            PacketType::PublicKeyEncryptedSessionKey => match classify::pkesk_key_id(packet) {
+                Some(key_id) => analysis.recipients.push(Recipient { key_id, secret_key_uid: None }),
+                None => analysis.notes.push("Unsupported public-key encrypted session key packet.".to_string()),
+            },
+            PacketType::SymmetricKeyEncryptedSessionKey => analysis.symmetric = true,
+            PacketType::Marker => {}
+            _ => break, // session key packets always come first
+        }
+    }
+
+    for recipient in analysis.recipients.iter_mut() {
+        match gpg_ops::find_secret_key_uid(&recipient.key_id) {
+            Ok(uid) => recipient.secret_key_uid = uid,
+            Err(e) => analysis.notes.push(format!("Key lookup for {} failed: {}", recipient.key_id, e)),
+        }
+    }
+    let hidden_recipient = analysis.recipients.iter().any(|r| r.key_id.chars().all(|c| c == '0'));
+    let can_decrypt = analysis.symmetric
+        || analysis.recipients.iter().any(|r| r.secret_key_uid.is_some())
+        || (hidden_recipient && gpg_ops::has_any_secret_key().unwrap_or(false));
+    if can_decrypt {
+        analysis.actions.push(ScanAction::Decrypt);
+    } else {
+        analysis.notes.push("No matching secret key for any recipient.".to_string());
+    }
+}
+
+fn analyze_revocation(block: &ClassifiedBlock, analysis: &mut ScanAnalysis) {
+    let Some(issuer) = block.packets.first().and_then(classify::signature_issuer) else {
+        analysis.notes.push("Revocation certificate does not name the key it revokes.".to_string());
+        return;
+    };
+    // Older certificates only carry the issuer key ID
+    let fingerprint = match issuer.len() {
+        40 => issuer.clone(),
+        _ => gpg_ops::key_fingerprint(&issuer).unwrap_or_else(|_| issuer.clone()),
+    };
+    match gpg_ops::find_key_by_fingerprint(&fingerprint) {
+        Ok(Some(key)) => {
+            analysis.notes.push(format!(
+                "Applying this certificate permanently revokes key {} ({}).",
+                key.fingerprint,
+                key.user_ids.first().map(String::as_str).unwrap_or("<no user id>")
+            ));
+            analysis.fingerprint_key = Some(key);
+            analysis.actions.push(ScanAction::ApplyRevocation);
+        }
+        Ok(None) => analysis.notes.push(format!(
+            "Revocation certificate for key {}, which is not in the keyring. Import the key first.",
+            issuer
+        )),
+        Err(e) => analysis.notes.push(format!("Key lookup failed: {}", e)),
+    }
+}
+
+// Shows what the request asks us to sign. Nothing is signed from here: the request is
+// queued for approval when the scan is stored (SignQueue::collect).
+fn analyze_sign_request(block: &ClassifiedBlock, analysis: &mut ScanAnalysis) {
+    match airgap::parse_sign_request(&block.text) {
+        Ok(request) => analysis.notes.extend(request.describe()),
+        Err(e) => analysis.notes.push(e.to_string()),
+    }
+}
+
+fn analyze_sign_response(block: &ClassifiedBlock, analysis: &mut ScanAnalysis) {
+    match airgap::parse_sign_response(&block.text) {
+        Ok(response) if airgap::has_pending_sign_request(&response.nonce) => {
+            analysis.notes.push(format!("Signing response for our request {}.", response.nonce));
+            analysis.actions.push(ScanAction::CheckSignResponse);
+        }
+        Ok(response) => analysis.notes.push(format!(
+            "Signing response for request {}, which is not pending here (answered, expired or foreign).",
+            response.nonce
+        )),
+        Err(e) => analysis.notes.push(e.to_string()),
+    }
+}
+
+// Same recipient check as for a scanned message, but the plaintext is only shown on request
+fn analyze_decrypt_request(block: &ClassifiedBlock, analysis: &mut ScanAnalysis) {
+    let request = match airgap::parse_decrypt_request(&block.text) {
+        Ok(request) => request,
+        Err(e) => {
+            analysis.notes.push(e.to_string());
+            return;
+        }
+    };
+    analysis.notes.extend(request.describe());
+    let blocks = classify::classify(&request.ciphertext);
+    let Some(message) = blocks.iter().find(|b| b.kind == PayloadKind::EncryptedMessage) else {
+        analysis.notes.push("The request contains no encrypted message.".to_string());
+        return;
+    };
+    analysis.packets = message.packets.clone();
+    analyze_encrypted(message, analysis);
+    if let Some(position) = analysis.actions.iter().position(|a| *a == ScanAction::Decrypt) {
+        analysis.actions.remove(position);
+        analysis.actions.push(ScanAction::AnswerDecryptRequest);
+        analysis.actions.push(ScanAction::RevealDecryptRequest);
+    }
+}
+
+fn analyze_decrypt_response(block: &ClassifiedBlock, analysis: &mut ScanAnalysis) {
+    match airgap::parse_decrypt_response(&block.text) {
+        Ok(response) if airgap::has_pending_decrypt_request(&response.nonce) => {
+            analysis.notes.push(format!("Decryption response for our request {}.", response.nonce));
+            analysis.actions.push(ScanAction::OpenDecryptResponse);
+        }
+        Ok(response) => analysis.notes.push(format!(
+            "Decryption response for request {}, which is not pending here (opened, expired or foreign).",
+            response.nonce
+        )),
+        Err(e) => analysis.notes.push(e.to_string()),
+    }
+}
+
+fn analyze_fingerprint(analysis: &mut ScanAnalysis) {
+    let Some(fingerprint) = analysis.fingerprint.clone() else {
+        return;
+    };
+    match gpg_ops::find_key_by_fingerprint(&fingerprint) {
+        Ok(Some(key)) => {
+            analysis.notes.push(format!(
+                "Fingerprint matches key in keyring: {}",
+                key.user_ids.first().map(String::as_str).unwrap_or("<no user id>")
+            ));
+            if key.has_secret {
+                analysis.notes.push("This is one of our own keys.".to_string());
+            } else {
+                analysis.actions.push(ScanAction::CertifyKey);
+            }
+            analysis.fingerprint_key = Some(key);
+        }
+        Ok(None) => analysis.notes.push(
+            "No key with this primary fingerprint in the keyring. Import the key first, then scan again.".to_string(),
+        ),
+        Err(e) => analysis.notes.push(format!("Key lookup failed: {}", e)),
+    }
+}
+
+// --- Server-held scans ---
+// Scanned payloads stay on the server under a random ID, so the client can trigger
+// "import / decrypt / verify this scan" without re-submitting the data.
+
+// [timeouts] scan_secs, 15 minutes by default
+fn scan_ttl() -> Duration {
+    config::timeouts().scan()
+}
+const MAX_STORED_SCANS: usize = 64;
+
+struct StoredScan {
+    data: Zeroizing<String>,
+    analysis: ScanAnalysis,
+    created: Instant,
+}
+
+// Secret shares scanned so far, per share set
+struct ShareSet {
+    shares: Vec<shamir::Share>,
+    created: Instant,
+}
+
+#[derive(Default)]
+pub struct ScanStore {
+    entries: Mutex<HashMap<String, StoredScan>>,
+    shares: Mutex<HashMap<String, ShareSet>>,
+}
+
+impl ScanStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Stores a scan and returns its ID. Expired and (if full) oldest entries are evicted.
+    pub fn insert(&self, data: String, analysis: ScanAnalysis) -> String {
+        let mut entries = self.entries.lock();
+        entries.retain(|_, scan| scan.created.elapsed() < scan_ttl());
+        while entries.len() >= MAX_STORED_SCANS {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, scan)| scan.created)
+                .map(|(id, _)| id.clone());
+            match oldest {
+                Some(id) => entries.remove(&id),
+                None => break,
+            };
+        }
+        let scan_id = format!("{:032x}", rand::random::<u128>());
+        entries.insert(
+            scan_id.clone(),
+            StoredScan { data: Zeroizing::new(data), analysis, created: Instant::now() },
+        );
+        scan_id
+    }
+
+    fn get(&self, scan_id: &str) -> Result<(Zeroizing<String>, ScanAnalysis)> {
+        let entries = self.entries.lock();
+        match entries.get(scan_id) {
+            Some(scan) if scan.created.elapsed() < scan_ttl() => Ok((scan.data.clone(), scan.analysis.clone())),
+            _ => Err(AppError::InvalidInput(format!("Unknown or expired scan ID: {}", scan_id))),
+        }
+    }
+
+    // Adds a scanned secret share to its set; once enough shares are in, the scan offers CombineShares
+    pub fn collect_share(&self, data: &str, analysis: &mut ScanAnalysis) {
+        let Ok(share) = shamir::parse_share(data) else { return };
+        let mut sets = self.shares.lock();
+        sets.retain(|_, set| set.created.elapsed() < scan_ttl());
+        let set = sets
+            .entry(share.set_id.clone())
+            .or_insert_with(|| ShareSet { shares: Vec::new(), created: Instant::now() });
+        if !set.shares.iter().any(|s| s.index == share.index) {
+            set.shares.push(share.clone());
+        }
+        let collected = set.shares.len().min(share.threshold as usize);
+        analysis.notes.push(format!(
+            "Collected {} of {} shares needed for set {}.",
+            collected, share.threshold, share.set_id
+        ));
+        if collected == share.threshold as usize {
+            analysis.actions.push(ScanAction::CombineShares);
+        }
+    }
+
+    pub fn discard(&self, scan_id: &str) -> bool {
+        self.entries.lock().remove(scan_id).is_some()
+    }
+
+    // Performs one of the actions offered for a stored scan
+    pub fn run_action(&self, scan_id: &str, action: ScanAction) -> Result<String> {
+        let (data, analysis) = self.get(scan_id)?;
+        if !analysis.actions.contains(&action) {
+            return Err(AppError::InvalidInput(format!(
+                "Action {:?} is not possible for this scan ({}).",
+                action, analysis.data_type
+            )));
+        }
+        policy::check(action.operation())?;
+        match action {
+            // Per-key report, so a scanned bundle shows exactly what changed
+            ScanAction::ImportKey => {
+                gpg_ops::import_keys(&data, &gpg_ops::ImportOptions::default()).map(|report| report.describe())
+            }
+            ScanAction::ApplyRevocation => gpg_ops::import_key(&data),
+            ScanAction::CombineShares => {
+                let set_id = shamir::parse_share(&data)?.set_id;
+                let shares = match self.shares.lock().get(&set_id) {
+                    Some(set) => set.shares.clone(),
+                    None => return Err(AppError::InvalidInput(format!("Shares of set {} have expired.", set_id))),
+                };
+                let restored = shamir::restore_from_shares(&shares, true)?;
+                self.shares.lock().remove(&set_id);
+                Ok(match restored.kind {
+                    shamir::SecretKind::SecretKey => restored.describe(),
+                    // Still encrypted: show it so it can go into the Restore Backup form
+                    shamir::SecretKind::BackupArchive => format!("{}\n\n{}", restored.describe(), restored.data.as_str()),
+                })
+            }
+            ScanAction::CheckSignResponse => airgap::check_sign_response(&data, None)
+                .map(|accepted| format!("{}\n\n{}", accepted.notes.join("\n"), accepted.signature)),
+            ScanAction::AnswerDecryptRequest => airgap::answer_decrypt_request(&data),
+            ScanAction::RevealDecryptRequest => airgap::reveal_decrypt_request(&data).map(|text| text.to_string()),
+            ScanAction::OpenDecryptResponse => airgap::open_decrypt_response(&data, None).map(|text| text.to_string()),
+            ScanAction::Decrypt => gpg_ops::decrypt(&data),
+            ScanAction::Verify => gpg_ops::verify(&data),
+            ScanAction::CertifyKey => match &analysis.fingerprint_key {
+                Some(key) => gpg_ops::certify_key(
+                    &key.fingerprint,
+                    &gpg_ops::CertifyOptions { local: true, ..Default::default() },
+                ),
+                None => Err(AppError::InvalidInput("Scan has no matching key to certify.".to_string())),
+            },
+        }
+    }
+}
@@ -0,0 +1,442 @@
+use crate::armor;
+use crate::backup;
+use crate::classify::{self, PayloadKind};
+use crate::error::{AppError, Result};
+use crate::gpg_ops::{self, ImportOptions, ImportReport};
+use crate::qr_utils;
+use askama::Template;
+use rand::RngCore;
+use std::time::SystemTime;
+use zeroize::Zeroizing;
+
+// Shamir secret sharing of a secret key export or keyring backup archive, byte-wise over
+// GF(256): any `threshold` of the `total` shares rebuild the secret, fewer reveal nothing.
+// A share is one QR-friendly line:
+//   SGSS2:<set-id>:<KEY|BACKUP>:<threshold>:<total>:<index>:<share CRC24>:<base32 share bytes>
+// The share CRC24 (over index and share bytes) catches a damaged share on its own. The CRC24
+// of the secret is split along with it, so only the combined shares can check the result.
+
+pub const SHARE_PREFIX: &str = "SGSS2:";
+pub const MAX_SHARES: u8 = 16; // more cards than this are unmanageable on paper anyway
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretKind {
+    SecretKey,     // binary `--export-secret-keys` output
+    BackupArchive, // armored keyring backup (see backup.rs)
+}
+
+impl SecretKind {
+    fn code(self) -> &'static str {
+        match self {
+            SecretKind::SecretKey => "KEY",
+            SecretKind::BackupArchive => "BACKUP",
+        }
+    }
+
+    fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "KEY" => Some(SecretKind::SecretKey),
+            "BACKUP" => Some(SecretKind::BackupArchive),
+            _ => None,
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            SecretKind::SecretKey => "secret key",
+            SecretKind::BackupArchive => "keyring backup archive",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Share {
+    pub set_id: String, // 8 hex digits, random per split
+    pub kind: SecretKind,
+    pub threshold: u8,
+    pub total: u8,
+    pub index: u8, // x coordinate, 1..=total
+    pub data: Zeroizing<Vec<u8>>, // share of the secret followed by its CRC24
+}
+
+impl Share {
+    pub fn encode(&self) -> Zeroizing<String> {
+        Zeroizing::new(format!(
+            "{}{}:{}:{}:{}:{}:{:06X}:{}",
+            SHARE_PREFIX,
+            self.set_id,
+            self.kind.code(),
+            self.threshold,
+            self.total,
+            self.index,
+            share_crc(self.index, &self.data),
+            qr_utils::encode_base32(&self.data)
+        ))
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "Share {} of {} of set {} ({}, any {} restore it)",
+            self.index,
+            self.total,
+            self.set_id,
+            self.kind.description(),
+            self.threshold
+        )
+    }
+}
+
+pub fn is_share(text: &str) -> bool {
+    text.trim_start().starts_with(SHARE_PREFIX)
+}
+
+pub fn parse_share(text: &str) -> Result<Share> {
+    let invalid = || AppError::InvalidInput("Not a valid secret share.".to_string());
+    let fields: Vec<&str> = text.trim().strip_prefix(SHARE_PREFIX).ok_or_else(invalid)?.split(':').collect();
+    let [set_id, kind, threshold, total, index, crc, data] = fields.as_slice() else {
+        return Err(invalid());
+    };
+    let share = Share {
+        set_id: set_id.to_ascii_uppercase(),
+        kind: SecretKind::from_code(kind).ok_or_else(invalid)?,
+        threshold: threshold.parse().map_err(|_| invalid())?,
+        total: total.parse().map_err(|_| invalid())?,
+        index: index.parse().map_err(|_| invalid())?,
+        data: Zeroizing::new(qr_utils::decode_base32(data).ok_or_else(invalid)?),
+    };
+    if share.threshold < 2 || share.threshold > share.total || share.index == 0 || share.index > share.total {
+        return Err(invalid());
+    }
+    if u32::from_str_radix(crc, 16).ok() != Some(share_crc(share.index, &share.data)) {
+        return Err(AppError::InvalidInput(format!(
+            "Share {} of set {} is damaged (checksum error).",
+            share.index, share.set_id
+        )));
+    }
+    Ok(share)
+}
+
+fn share_crc(index: u8, data: &[u8]) -> u32 {
+    let mut bytes = Zeroizing::new(Vec::with_capacity(data.len() + 1));
+    bytes.push(index);
+    bytes.extend_from_slice(data);
+    armor::crc24(&bytes)
+}
+
+// --- GF(256) arithmetic (AES polynomial x^8 + x^4 + x^3 + x + 1) ---
+
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        let carry = a & 0x80;
+        a <<= 1;
+        if carry != 0 {
+            a ^= 0x1B;
+        }
+        b >>= 1;
+    }
+    product
+}
+
+fn gf_inv(a: u8) -> u8 {
+    // a^254 = a^-1 for a != 0
+    let mut result = 1;
+    for _ in 0..254 {
+        result = gf_mul(result, a);
+    }
+    result
+}
+
+// Splits `secret` into `total` shares, any `threshold` of which rebuild it
+pub fn split(secret: &[u8], kind: SecretKind, threshold: u8, total: u8) -> Result<Vec<Share>> {
+    if threshold < 2 || threshold > total || total > MAX_SHARES {
+        return Err(AppError::InvalidInput(format!(
+            "Need 2 <= threshold <= shares <= {}, got threshold {} of {}.",
+            MAX_SHARES, threshold, total
+        )));
+    }
+    if secret.is_empty() {
+        return Err(AppError::InvalidInput("Nothing to split.".to_string()));
+    }
+    let set_id = format!("{:08X}", rand::random::<u32>());
+    // The checksum is shared like the secret: in the clear it would leak 24 bits of it
+    let mut secret_with_crc = Zeroizing::new(secret.to_vec());
+    secret_with_crc.extend_from_slice(&armor::crc24(secret).to_be_bytes()[1..]);
+    let secret = secret_with_crc.as_slice();
+
+    // Random polynomial coefficients 1..threshold for every secret byte
+    let degree = threshold as usize - 1;
+    let mut coefficients = Zeroizing::new(vec![0u8; secret.len() * degree]);
+    rand::rngs::OsRng.fill_bytes(&mut coefficients);
+
+    Ok((1..=total)
+        .map(|x| {
+            let data = secret
+                .iter()
+                .enumerate()
+                .map(|(i, &byte)| {
+                    // Horner's rule, highest coefficient first; the secret byte is the constant term
+                    let mut y = 0u8;
+                    for &c in coefficients[i * degree..(i + 1) * degree].iter().rev() {
+                        y = gf_mul(y, x) ^ c;
+                    }
+                    gf_mul(y, x) ^ byte
+                })
+                .collect();
+            Share { set_id: set_id.clone(), kind, threshold, total, index: x, data: Zeroizing::new(data) }
+        })
+        .collect())
+}
+
+// Rebuilds the secret from at least `threshold` shares of one set
+pub fn combine(shares: &[Share]) -> Result<Zeroizing<Vec<u8>>> {
+    let first = shares
+        .first()
+        .ok_or_else(|| AppError::InvalidInput("No shares given.".to_string()))?;
+    let mut unique: Vec<&Share> = Vec::new();
+    for share in shares {
+        if share.set_id != first.set_id
+            || share.kind != first.kind
+            || share.threshold != first.threshold
+            || share.total != first.total
+            || share.data.len() != first.data.len()
+        {
+            return Err(AppError::InvalidInput(format!(
+                "Share {} does not belong to set {}.",
+                share.index, first.set_id
+            )));
+        }
+        if !unique.iter().any(|s| s.index == share.index) {
+            unique.push(share);
+        }
+    }
+    if unique.len() < first.threshold as usize {
+        return Err(AppError::InvalidInput(format!(
+            "Set {} needs {} different shares, only {} given.",
+            first.set_id,
+            first.threshold,
+            unique.len()
+        )));
+    }
+    let used = &unique[..first.threshold as usize];
+
+    // Lagrange basis polynomials evaluated at x = 0 (subtraction is XOR in GF(256))
+    let weights: Vec<u8> = used
+        .iter()
+        .map(|share| {
+            used.iter().filter(|other| other.index != share.index).fold(1u8, |acc, other| {
+                gf_mul(acc, gf_mul(other.index, gf_inv(other.index ^ share.index)))
+            })
+        })
+        .collect();
+    let mut secret = Zeroizing::new(
+        (0..first.data.len())
+            .map(|i| {
+                used.iter()
+                    .zip(&weights)
+                    .fold(0u8, |acc, (share, &weight)| acc ^ gf_mul(share.data[i], weight))
+            })
+            .collect::<Vec<u8>>(),
+    );
+    let crc_start = secret.len().saturating_sub(3);
+    let crc = secret.split_off(crc_start);
+    if crc.len() != 3 || crc != armor::crc24(&secret).to_be_bytes()[1..] {
+        return Err(AppError::InvalidInput(format!(
+            "Shares of set {} do not combine to the original secret (damaged or altered share).",
+            first.set_id
+        )));
+    }
+    Ok(secret)
+}
+
+// --- Printable cards ---
+
+pub struct ShareCard {
+    pub index: u8,
+    pub qr_codes: Vec<String>, // SVG; a frame set if the share is too large for one code
+}
+
+#[derive(Template)]
+#[template(path = "shares.html")]
+pub struct ShareCards {
+    pub set_id: String,
+    pub label: String, // what was split, e.g. fingerprint and user ID
+    pub kind: &'static str,
+    pub threshold: u8,
+    pub total: u8,
+    pub created: String,
+    pub cards: Vec<ShareCard>,
+}
+
+impl ShareCards {
+    pub fn html(&self) -> Result<String> {
+        Ok(self.render()?)
+    }
+}
+
+pub enum SplitSource<'a> {
+    SecretKey(&'a str, &'a str), // key ID or fingerprint, the key's passphrase
+    BackupArchive(&'a str),      // armored archive from create_backup
+}
+
+// Splits a secret key or backup archive and renders one labelled QR card per share
+pub fn create_share_cards(source: SplitSource<'_>, threshold: u8, total: u8) -> Result<ShareCards> {
+    let (secret, kind, label) = match source {
+        SplitSource::SecretKey(key_id, key_passphrase) => {
+            let fingerprint = gpg_ops::key_fingerprint(key_id)?;
+            let user_id = gpg_ops::find_secret_key_uid(&fingerprint)?
+                .ok_or_else(|| AppError::InvalidInput(format!("No secret key for {} in this keyring.", fingerprint)))?;
+            let exported = gpg_ops::export_secret_key_binary(&fingerprint, key_passphrase)?;
+            (exported, SecretKind::SecretKey, format!("{} {}", fingerprint, user_id))
+        }
+        SplitSource::BackupArchive(archive) => {
+            let is_backup = classify::classify(archive)
+                .iter()
+                .any(|block| block.kind == PayloadKind::EncryptedMessage && backup::is_backup_archive(&block.armor_headers));
+            if !is_backup {
+                return Err(AppError::InvalidInput("The data is not a keyring backup archive.".to_string()));
+            }
+            let archive = archive.trim();
+            (
+                Zeroizing::new(archive.as_bytes().to_vec()),
+                SecretKind::BackupArchive,
+                format!("Keyring backup archive ({} bytes)", archive.len()),
+            )
+        }
+    };
+    let shares = split(&secret, kind, threshold, total)?;
+    let cards = shares
+        .iter()
+        .map(|share| {
+            Ok(ShareCard { index: share.index, qr_codes: qr_utils::generate_printable_qr_svg(&share.encode())? })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    Ok(ShareCards {
+        set_id: shares[0].set_id.clone(),
+        label,
+        kind: kind.description(),
+        threshold,
+        total,
+        created: gpg_ops::format_date(now),
+        cards,
+    })
+}
+
+pub struct SharesRestore {
+    pub kind: SecretKind,
+    pub data: Zeroizing<String>,      // armored secret key, or the backup archive for restore_backup
+    pub import: Option<ImportReport>, // set if a secret key was imported
+}
+
+// Parses every share line in `lines`, ignoring anything else (e.g. blank lines, notes)
+pub fn parse_share_lines(lines: &[String]) -> Result<Vec<Share>> {
+    lines.iter().filter(|line| is_share(line)).map(|line| parse_share(line)).collect()
+}
+
+// Combines shares (any order, duplicates ignored). A secret key is imported unless
+// `import` is false; a backup archive is only returned, it still needs its passphrase.
+pub fn restore_from_shares(shares: &[Share], import: bool) -> Result<SharesRestore> {
+    let secret = combine(shares)?;
+    let kind = shares[0].kind;
+    let data = match kind {
+        SecretKind::SecretKey => Zeroizing::new(armor::encode_armor("PRIVATE KEY BLOCK", &secret)),
+        SecretKind::BackupArchive => Zeroizing::new(String::from_utf8_lossy(&secret).into_owned()),
+    };
+    let import = if import && kind == SecretKind::SecretKey {
+        Some(gpg_ops::import_keys(&data, &ImportOptions::default())?)
+    } else {
+        None
+    };
+    Ok(SharesRestore { kind, data, import })
+}
+
+impl SharesRestore {
+    pub fn describe(&self) -> String {
+        match (&self.import, self.kind) {
+            (Some(import), _) => import.describe(),
+            (None, SecretKind::BackupArchive) => {
+                "Backup archive restored from shares. Restore it with the Restore Backup form and its passphrase."
+                    .to_string()
+            }
+            (None, SecretKind::SecretKey) => "Secret key restored from shares (not imported).".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gf_arithmetic() {
+        // FIPS-197 section 4.2
+        assert_eq!(gf_mul(0x57, 0x83), 0xC1);
+        assert_eq!(gf_mul(0x57, 0x13), 0xFE);
+        for a in 1..=255u8 {
+            assert_eq!(gf_mul(a, gf_inv(a)), 1, "inverse of {:#04x}", a);
+            assert_eq!(gf_mul(a, 0), 0);
+        }
+    }
+
+    #[test]
+    fn every_threshold_subset_recombines() {
+        let secret: Vec<u8> = (0..=255u8).chain([0, 0, 255]).collect();
+        let shares = split(&secret, SecretKind::SecretKey, 3, 5).unwrap();
+        for subset in 0u32..1 << shares.len() {
+            let chosen: Vec<Share> =
+                shares.iter().enumerate().filter(|(i, _)| subset & (1 << i) != 0).map(|(_, s)| s.clone()).collect();
+            match combine(&chosen) {
+                Ok(combined) => {
+                    assert!(chosen.len() >= 3);
+                    assert_eq!(combined.as_slice(), secret.as_slice(), "subset {:05b}", subset);
+                }
+                Err(_) => assert!(chosen.len() < 3, "subset {:05b} should combine", subset),
+            }
+        }
+    }
+
+    #[test]
+    fn duplicate_shares_do_not_count_twice() {
+        let shares = split(b"secret", SecretKind::BackupArchive, 2, 3).unwrap();
+        assert!(combine(&[shares[0].clone(), shares[0].clone()]).is_err());
+        assert_eq!(combine(&[shares[0].clone(), shares[0].clone(), shares[2].clone()]).unwrap().as_slice(), b"secret");
+    }
+
+    #[test]
+    fn tampered_share_is_detected() {
+        let secret = b"a secret key, more or less".to_vec();
+        let shares = split(&secret, SecretKind::SecretKey, 2, 3).unwrap();
+
+        // Altered data with a matching share checksum still fails the secret's CRC24
+        let mut altered = shares[1].clone();
+        altered.data[4] ^= 0x01;
+        let error = combine(&[shares[0].clone(), altered.clone()]).unwrap_err();
+        assert!(error.to_string().contains("do not combine"), "{}", error);
+
+        // A damaged card fails its own checksum when it is read
+        let encoded = altered.encode();
+        let reparsed = parse_share(&encoded).unwrap();
+        assert_eq!(reparsed.data.as_slice(), altered.data.as_slice());
+        let mut damaged = shares[1].encode().to_string();
+        let middle = damaged.len() - 10; // inside the base32 data, clear of its padding bits
+        let replacement = if damaged.as_bytes()[middle] == b'A' { "B" } else { "A" };
+        damaged.replace_range(middle..middle + 1, replacement);
+        let error = parse_share(&damaged).err().unwrap();
+        assert!(error.to_string().contains("checksum error"), "{}", error);
+    }
+
+    #[test]
+    fn shares_of_different_sets_are_refused() {
+        let first = split(b"one", SecretKind::SecretKey, 2, 2).unwrap();
+        let second = split(b"two", SecretKind::SecretKey, 2, 2).unwrap();
+        assert!(combine(&[first[0].clone(), second[1].clone()]).is_err());
+    }
+}
@@ -0,0 +1,315 @@
+use crate::airgap::{self, SignRequest};
+use crate::config;
+use crate::error::{AppError, Result};
+use crate::gpg_ops;
+use crate::scan::ScanAnalysis;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+// Approval queue of the offline signer: scanned or uploaded signing requests wait here until an
+// operator approves (signs) or rejects them. Every decision is appended to a log in the GnuPG
+// home directory, and approved content is kept so the next version of a document is reviewed
+// as a diff.
+
+// [timeouts] sign_queue_secs, a day by default
+fn queue_ttl() -> Duration {
+    config::timeouts().sign_queue()
+}
+const MAX_QUEUED: usize = 64;
+const DECISION_LOG: &str = "secure-gpg-qr-sign-decisions.log";
+const APPROVED_VERSIONS: &str = "secure-gpg-qr-approved-versions.json";
+// Larger documents are shown in full instead of diffed (the diff table is lines x lines)
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Decision {
+    Approved,
+    Rejected,
+    Expired,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct QueueEntry {
+    pub id: String, // request nonce
+    pub request: SignRequest,
+    pub signing_key: String, // user ID of the secret key that would sign
+    pub review: Vec<String>, // what the operator has to read before deciding
+    pub received: u64,
+    #[serde(skip)]
+    queued: Instant,
+}
+
+// One line of the decision log; holds the SHA-256 of what was (or would have been) signed, never the content
+#[derive(Serialize)]
+struct DecisionRecord<'a> {
+    time: u64,
+    decision: Decision,
+    nonce: &'a str,
+    document: &'a str,
+    requester: &'a str,
+    signer: &'a str,
+    mode: airgap::SignRequestMode,
+    sha256: String,
+    reason: &'a str,
+}
+
+// Last approved version of a document, per signer and document name
+#[derive(Serialize, Deserialize, Clone)]
+struct ApprovedVersion {
+    nonce: String,
+    approved: u64,
+    sha256: String,          // of the content, or the document digest of a hash-only request
+    content: Option<String>, // None for hash-only requests
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn home_file(name: &str) -> Result<PathBuf> {
+    Ok(gpg_ops::effective_home_dir()?.join(name))
+}
+
+fn version_key(request: &SignRequest) -> String {
+    format!("{} {}", request.signer, request.document)
+}
+
+fn load_approved_versions() -> Result<HashMap<String, ApprovedVersion>> {
+    let path = home_file(APPROVED_VERSIONS)?;
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    serde_json::from_str(&std::fs::read_to_string(&path)?)
+        .map_err(|e| AppError::Operation(format!("{} is damaged: {}", path.display(), e)))
+}
+
+fn last_approved(request: &SignRequest) -> Result<Option<ApprovedVersion>> {
+    Ok(load_approved_versions()?.remove(&version_key(request)))
+}
+
+fn remember_approved(request: &SignRequest) -> Result<()> {
+    let sha256 = match &request.digest {
+        Some(digest) => digest.sha256.clone(),
+        None => gpg_ops::sha256_hex(request.signed_text()?.as_bytes()),
+    };
+    let mut versions = load_approved_versions()?;
+    versions.insert(
+        version_key(request),
+        ApprovedVersion { nonce: request.nonce.clone(), approved: now_secs(), sha256, content: request.content.clone() },
+    );
+    std::fs::write(home_file(APPROVED_VERSIONS)?, serde_json::to_string_pretty(&versions)?)?;
+    Ok(())
+}
+
+// Whether a request was already approved, rejected or expired; its nonce must not come back
+pub(crate) fn already_decided(nonce: &str) -> Result<bool> {
+    let path = home_file(DECISION_LOG)?;
+    if !path.exists() {
+        return Ok(false);
+    }
+    Ok(std::fs::read_to_string(&path)?.lines().any(|line| {
+        serde_json::from_str::<serde_json::Value>(line)
+            .map(|record| record["nonce"] == nonce)
+            .unwrap_or(false)
+    }))
+}
+
+// Appends a decision to the log
+pub fn record_decision(request: &SignRequest, decision: Decision, reason: &str) -> Result<()> {
+    let record = DecisionRecord {
+        time: now_secs(),
+        decision,
+        nonce: &request.nonce,
+        document: &request.document,
+        requester: &request.requester,
+        signer: &request.signer,
+        mode: request.mode,
+        sha256: gpg_ops::sha256_hex(request.signed_text()?.as_bytes()),
+        reason,
+    };
+    let mut log = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(home_file(DECISION_LOG)?)?;
+    writeln!(log, "{}", serde_json::to_string(&record)?)?;
+    if decision == Decision::Approved {
+        remember_approved(request)?;
+    }
+    Ok(())
+}
+
+// Line diff based on the longest common subsequence: "+ " added, "- " removed, "  " unchanged
+fn line_diff(old: &str, new: &str) -> Vec<String> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    // lcs[i][j] = length of the common subsequence of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut diff = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            diff.push(format!("  {}", old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            diff.push(format!("- {}", old[i]));
+            i += 1;
+        } else {
+            diff.push(format!("+ {}", new[j]));
+            j += 1;
+        }
+    }
+    diff
+}
+
+// What the operator reviews: the request details, then the content or its changes since the
+// last approved version of the same document
+pub fn review(request: &SignRequest) -> Result<Vec<String>> {
+    let mut lines = request.describe();
+    // describe() already shows full content; replace it with a diff where one is available
+    let previous = last_approved(request)?;
+    match (&request.content, previous) {
+        (_, None) => lines.push(format!("No earlier version of {} was approved for this key.", request.document)),
+        (Some(content), Some(ApprovedVersion { content: Some(old), approved, .. })) => {
+            lines.retain(|line| !line.starts_with("Content to sign"));
+            if old == *content {
+                lines.push(format!("Content is identical to the version approved on {}:", gpg_ops::format_date(approved)));
+                lines.extend(content.lines().map(|line| format!("  {}", line)));
+            } else if old.lines().count() * content.lines().count() > MAX_DIFF_CELLS {
+                lines.push(format!("Content changed since {} (too large to diff):", gpg_ops::format_date(approved)));
+                lines.push(content.clone());
+            } else {
+                lines.push(format!("Changes since the version approved on {}:", gpg_ops::format_date(approved)));
+                lines.extend(line_diff(&old, content));
+            }
+        }
+        (_, Some(previous)) => {
+            let same = request.digest.as_ref().map(|digest| digest.sha256 == previous.sha256).unwrap_or(false);
+            lines.push(format!(
+                "Last approved version ({}) had SHA256 {}{}.",
+                gpg_ops::format_date(previous.approved),
+                previous.sha256,
+                if same { ", the same document" } else { "" }
+            ));
+        }
+    }
+    Ok(lines)
+}
+
+#[derive(Default)]
+pub struct SignQueue {
+    entries: Mutex<Vec<QueueEntry>>,
+}
+
+impl SignQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Drops entries older than the queue timeout, logging each as expired
+    fn expire(entries: &mut Vec<QueueEntry>) {
+        entries.retain(|entry| {
+            let alive = entry.queued.elapsed() < queue_ttl();
+            if !alive {
+                if let Err(e) = record_decision(&entry.request, Decision::Expired, "not decided in time") {
+                    tracing::warn!("Could not log expiry of signing request {}: {}", entry.id, e);
+                }
+            }
+            alive
+        });
+    }
+
+    // Queues a scanned or uploaded signing request for review
+    pub fn submit(&self, payload: &str) -> Result<QueueEntry> {
+        let request = airgap::parse_sign_request(payload)?;
+        if already_decided(&request.nonce)? {
+            return Err(AppError::InvalidInput(format!(
+                "Signing request {} was already decided; the requester has to create a new one.",
+                request.nonce
+            )));
+        }
+        let signing_key = gpg_ops::find_secret_key_uid(&request.signer)?.ok_or_else(|| {
+            AppError::InvalidInput(format!("No secret key for the requested signer {}.", request.signer))
+        })?;
+        let review = review(&request)?;
+        let mut entries = self.entries.lock();
+        Self::expire(&mut entries);
+        if let Some(queued) = entries.iter().find(|entry| entry.id == request.nonce) {
+            return Ok(queued.clone());
+        }
+        if entries.len() >= MAX_QUEUED {
+            return Err(AppError::InvalidInput("The signing queue is full; decide on pending requests first.".to_string()));
+        }
+        let entry = QueueEntry {
+            id: request.nonce.clone(),
+            request,
+            signing_key,
+            review,
+            received: now_secs(),
+            queued: Instant::now(),
+        };
+        entries.push(entry.clone());
+        Ok(entry)
+    }
+
+    // Queues a scanned signing request, noting the outcome in the scan analysis
+    pub fn collect(&self, data: &str, analysis: &mut ScanAnalysis) {
+        if !data.trim_start().starts_with(airgap::SIGN_REQUEST_PREFIX) {
+            return;
+        }
+        match self.submit(data) {
+            Ok(entry) => analysis.notes.push(format!(
+                "Queued for approval in the Signing Queue (signing key {}).",
+                entry.signing_key
+            )),
+            Err(e) => analysis.notes.push(format!("Not queued: {}", e)),
+        }
+    }
+
+    pub fn list(&self) -> Vec<QueueEntry> {
+        let mut entries = self.entries.lock();
+        Self::expire(&mut entries);
+        entries.clone()
+    }
+
+    // Signs a queued request and returns the response payload for the requester
+    pub fn approve(&self, id: &str) -> Result<String> {
+        let mut entries = self.entries.lock();
+        Self::expire(&mut entries);
+        let position = entries
+            .iter()
+            .position(|entry| entry.id == id)
+            .ok_or_else(|| AppError::InvalidInput(format!("No queued signing request {} (decided or expired).", id)))?;
+        let response = airgap::answer_sign_request(&entries[position].request)?;
+        let entry = entries.remove(position);
+        // The signature exists now; a failed log write must not lose it
+        if let Err(e) = record_decision(&entry.request, Decision::Approved, "") {
+            tracing::warn!("Could not log approval of signing request {}: {}", entry.id, e);
+        }
+        Ok(response)
+    }
+
+    pub fn reject(&self, id: &str, reason: &str) -> Result<()> {
+        let mut entries = self.entries.lock();
+        Self::expire(&mut entries);
+        let position = entries
+            .iter()
+            .position(|entry| entry.id == id)
+            .ok_or_else(|| AppError::InvalidInput(format!("No queued signing request {} (decided or expired).", id)))?;
+        let entry = entries.remove(position);
+        record_decision(&entry.request, Decision::Rejected, reason)
+    }
+}
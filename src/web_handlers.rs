@@ -1,11 +1,19 @@
+use crate::airgap;
+use crate::audit;
+use crate::backup;
+use crate::config;
 use crate::error::{AppError, Result};
 use crate::gpg_ops;
+use crate::paperkey;
+use crate::qr_decode;
 use crate::qr_utils;
+use crate::scan;
+use crate::shamir;
 use crate::web_server::AppState; // Import AppState
 use askama::Template;
 use axum::{
-    extract::{Form, State},
-    http::StatusCode,
+    extract::{Form, Multipart, State},
+    http::{header, HeaderValue, StatusCode},
     response::{Html, IntoResponse, Response},
     Json,
 };
@@ -32,11 +40,218 @@ struct IndexTemplate {
 pub struct ExportKeyRequest {
     key_id: String,
     secret: Option<bool>, // Checkbox might send "on" or nothing
+    key_passphrase: Option<String>,    // secret exports: proves the key's passphrase
+    recipient: Option<String>,         // secret exports: encrypt to this key...
+    export_passphrase: Option<String>, // ...or with this passphrase
+}
+
+#[derive(Deserialize)]
+pub struct ExportFilteredRequest {
+    key_id: String,
+    minimal: Option<bool>,
+    clean: Option<bool>,
+    drop_user_ids: Option<String>, // one per line
+    subkeys: Option<String>,       // subkey fingerprints to keep, one per line; empty = all
+    estimate_only: Option<bool>,   // return the size estimate without rendering a QR code
+}
+
+#[derive(Deserialize)]
+pub struct ExportSecretSubkeysRequest {
+    key_id: String,
+    subkeys: Option<String>, // subkey fingerprints, one per line; empty = all secret subkeys
+    key_passphrase: Option<String>,
+    recipient: Option<String>,
+    export_passphrase: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct FingerprintQrRequest {
+    key_id: String,
+}
+
+#[derive(Deserialize)]
+pub struct CertifyKeyRequest {
+    key_id: String,
+    signer_key_id: Option<String>,
+    user_ids: Option<String>, // one per line, empty = all user IDs
+    local: Option<bool>,
+    expires: Option<String>,  // e.g. "0", "1y"
+    trust_level: Option<String>, // "", "marginal" or "full"
+    trust_depth: Option<u8>,
+    trust_domain: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct KeyCertificationsRequest {
+    key_id: String,
+}
+
+#[derive(Deserialize)]
+pub struct SetOwnertrustRequest {
+    key_id: String,
+    trust: String, // undefined, never, marginal, full, ultimate
+}
+
+#[derive(Deserialize)]
+pub struct ImportOwnertrustRequest {
+    ownertrust: String,
+}
+
+#[derive(Deserialize)]
+pub struct TrustModelRequest {
+    trust_model: String,
+}
+
+#[derive(Deserialize)]
+pub struct RevocationCertRequest {
+    key_id: String,
+    reason: Option<String>,      // none, compromised, superseded, no_longer_used
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct StoredRevocationCertRequest {
+    key_id: String,
+}
+
+#[derive(Deserialize)]
+pub struct RevokeRequest {
+    key_id: String,
+    subkey: Option<String>,  // subkey fingerprint, for revoke_subkey
+    user_id: Option<String>, // exact user ID, for revoke_uid
+    reason: Option<String>,
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct SubkeysRequest {
+    key_id: String,
+}
+
+#[derive(Deserialize)]
+pub struct AddSubkeyRequest {
+    key_id: String,
+    algorithm: Option<String>, // e.g. "ed25519", "cv25519", "rsa4096"; empty = gpg default
+    usage: String,             // sign, encrypt or auth
+    expires: Option<String>,   // e.g. "1y", "0" = never
+}
+
+#[derive(Deserialize)]
+pub struct SetExpiryRequest {
+    key_id: String,
+    subkeys: Option<String>, // subkey fingerprints, one per line; empty = primary key
+    expires: String,
+}
+
+#[derive(Deserialize)]
+pub struct KeyDetailsRequest {
+    key_id: String,
+}
+
+#[derive(Deserialize)]
+pub struct UserIdRequest {
+    key_id: String,
+    user_id: String, // e.g. "Name <new@example.org>"
 }
 
 #[derive(Deserialize)]
 pub struct ImportKeyRequest {
     key_data: String,
+    keep_ownertrust: Option<bool>,
+    import_clean: Option<bool>,
+    restore: Option<bool>,
+    keep_user_ids: Option<String>, // one per line, empty = all
+    fingerprints: Option<String>,  // one per line, empty = all keys
+}
+
+#[derive(Deserialize)]
+pub struct BackupRequest {
+    passphrase: String,
+    passphrase_confirm: String,
+}
+
+#[derive(Deserialize)]
+pub struct RestoreBackupRequest {
+    archive: String,
+    passphrase: String,
+    validate_only: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct PaperBackupRequest {
+    key_id: String,
+    format: Option<String>, // base16 (default) or base32
+    key_passphrase: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct PaperRestoreRequest {
+    paper_text: String,
+    public_key: Option<String>, // empty = take the public key from the keyring
+}
+
+#[derive(Deserialize)]
+pub struct ShareCardsRequest {
+    key_id: Option<String>,
+    key_passphrase: Option<String>, // needed when splitting a key
+    archive: Option<String>,        // backup archive to split instead of a key
+    threshold: u8,
+    shares: u8,
+}
+
+#[derive(Deserialize)]
+pub struct CombineSharesRequest {
+    shares: String, // share payloads, one per line
+}
+
+#[derive(Deserialize)]
+pub struct AirgapSignRequest {
+    signer: String,
+    mode: String,                 // clearsign, detach or normal
+    content: String,
+    document: String,             // versions of one document share the name, for review diffs
+    requester: String,
+    hash_only: Option<bool>,      // send only the SHA-256 of the content to the offline signer
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct AirgapSignResponseRequest {
+    response: String,
+}
+
+#[derive(Deserialize)]
+pub struct SignQueueSubmitRequest {
+    request: String, // SGSIGREQ1:... payload
+}
+
+#[derive(Deserialize)]
+pub struct SignQueueDecisionRequest {
+    id: String,
+    reason: Option<String>, // rejections only
+}
+
+#[derive(Deserialize)]
+pub struct AirgapDecryptRequest {
+    ciphertext: String,
+    use_passphrase: Option<bool>, // session passphrase in the request instead of a one-time key
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct AirgapDecryptResponseRequest {
+    response: String,
+}
+
+#[derive(Deserialize)]
+pub struct AuditVerifyRequest {
+    key: Option<String>, // required checkpoint signer
+    log: Option<String>, // exported entries; empty = this profile's log
+}
+
+#[derive(Deserialize)]
+pub struct AuditExportRequest {
+    since: Option<u64>,
 }
 
 #[derive(Deserialize)]
@@ -70,12 +285,27 @@ pub struct ProcessQrDataRequest {
     scanned_data: String,
 }
 
+#[derive(Deserialize)]
+pub struct ScanActionRequest {
+    scan_id: String,
+    action: scan::ScanAction,
+}
+
+#[derive(Deserialize)]
+pub struct ScanDiscardRequest {
+    scan_id: String,
+}
+
 #[derive(Serialize)]
 pub struct ApiResponse<T: Serialize> {
     success: bool,
     data: Option<T>,
     error: Option<String>,
     qr_code: Option<String>, // Optionally include QR for results
+    #[serde(skip_serializing_if = "Option::is_none")]
+    notes: Option<Vec<String>>, // Extra explanations, e.g. which trust path made a key valid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    qr_frames: Option<Vec<String>>, // Multi-frame QR set (SVGs) for data too large or meant for printing
 }
 
 // --- Handlers ---
@@ -110,6 +340,7 @@ pub async fn api_status(State(_state): State<Arc<AppState>>) -> Result<Json<serd
          "success": true,
          "public_keys": public_keys,
          "secret_keys": secret_keys,
+         "policy": crate::policy::current().describe(),
          // "current_account": crate::CURRENT_ACCOUNT_NAME // If tracking needed
      })))
 }
@@ -124,17 +355,116 @@ fn json_response<T: Serialize>(
     let success = error.is_none();
     (
         if success { StatusCode::OK } else { StatusCode::BAD_REQUEST },
-        Json(ApiResponse { success, data, error, qr_code }),
+        Json(ApiResponse { success, data, error, qr_code, notes: None, qr_frames: None }),
+    )
+        .into_response()
+}
+
+// Same as json_response, with additional explanation lines
+fn json_response_with_notes<T: Serialize>(
+    data: Option<T>,
+    error: Option<String>,
+    qr_code: Option<String>,
+    notes: Vec<String>,
+) -> Response {
+    let success = error.is_none();
+    (
+        if success { StatusCode::OK } else { StatusCode::BAD_REQUEST },
+        Json(ApiResponse { success, data, error, qr_code, notes: Some(notes), qr_frames: None }),
+    )
+        .into_response()
+}
+
+// Successful response with every optional part (notes, multi-frame QR set)
+fn json_success<T: Serialize>(
+    data: T,
+    qr_code: Option<String>,
+    notes: Option<Vec<String>>,
+    qr_frames: Option<Vec<String>>,
+) -> Response {
+    (
+        StatusCode::OK,
+        Json(ApiResponse { success: true, data: Some(data), error: None, qr_code, notes, qr_frames }),
     )
         .into_response()
 }
 
+// Successful response carrying the data both as a single QR (if it fits) and as a printable frame set
+fn json_response_with_frames(data: String, notes: Option<Vec<String>>) -> Response {
+    match qr_utils::generate_qr_frames_svg(&data) {
+        Ok(frames) => {
+            let qr_code = qr_utils::generate_qr_svg(&data).ok();
+            json_success(data, qr_code, notes, Some(frames))
+        }
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+// Encrypted secret key export; the response must not be kept by the browser or a proxy
+fn secret_export_response(
+    key_id: &str,
+    subkeys: Option<&[String]>,
+    key_passphrase: Option<String>,
+    recipient: Option<&str>,
+    export_passphrase: Option<String>,
+) -> Response {
+    let key_passphrase = Zeroizing::new(key_passphrase.unwrap_or_default());
+    let result = gpg_ops::ExportProtection::choose(recipient, export_passphrase.map(Zeroizing::new))
+        .and_then(|protection| gpg_ops::export_secret_protected(key_id, subkeys, &key_passphrase, &protection));
+    let mut response = match result {
+        Ok(encrypted) => json_response_with_frames(
+            encrypted,
+            Some(vec!["Encrypted secret key export; it is not stored on this machine.".to_string()]),
+        ),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    };
+    response.headers_mut().insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    response
+}
+
+// Printable page holding secret key material, kept out of caches like secret exports
+fn secret_page_response(page: Result<String>) -> Response {
+    let mut response = page.map(Html).into_response();
+    response.headers_mut().insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    response
+}
+
+// Splits a textarea value into trimmed, non-empty lines
+fn non_empty_lines(value: Option<&str>) -> Vec<String> {
+    value
+        .unwrap_or("")
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// The signer from the form, else the configured default signer
+fn signer_or_default(signer: &str) -> Result<String> {
+    config::signer_or_default(Some(signer))
+        .ok_or_else(|| AppError::InvalidInput("No signer given and no default signer configured.".to_string()))
+}
+
+fn parse_revocation_reason(reason: &Option<String>) -> Result<gpg_ops::RevocationReason> {
+    gpg_ops::RevocationReason::parse(reason.as_deref().unwrap_or(""))
+}
+
 
 pub async fn api_export_key(
     State(_state): State<Arc<AppState>>,
     Form(payload): Form<ExportKeyRequest>,
 ) -> Response {
-    let result = gpg_ops::export_key(&payload.key_id, payload.secret.unwrap_or(false));
+    if payload.secret.unwrap_or(false) {
+        return secret_export_response(
+            &payload.key_id,
+            None,
+            payload.key_passphrase,
+            payload.recipient.as_deref(),
+            payload.export_passphrase,
+        );
+    }
+    let result = gpg_ops::export_key(&payload.key_id);
     match result {
         Ok(key_data) => {
             // Generate QR code for the exported key
@@ -148,7 +478,407 @@ pub async fn api_export_key(
                  }
             }
         }
-        Err(e) => json_response::<String>(None, Some(e.toString()), None),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+// Compact OPENPGP4FPR QR code for in-person key verification
+pub async fn api_fingerprint_qr(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<FingerprintQrRequest>,
+) -> Response {
+    let fingerprint = match gpg_ops::key_fingerprint(&payload.key_id) {
+        Ok(fpr) => fpr,
+        Err(e) => return json_response::<String>(None, Some(e.to_string()), None),
+    };
+    let uri = qr_utils::fingerprint_uri(&fingerprint);
+    match qr_utils::generate_qr_svg(&uri) {
+        Ok(svg) => json_response(Some(uri), None, Some(svg)),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+pub async fn api_certify_key(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<CertifyKeyRequest>,
+) -> Response {
+    let expires = match gpg_ops::parse_expiry(payload.expires.as_deref().unwrap_or("0")) {
+        Ok(expires) => expires,
+        Err(e) => return json_response::<String>(None, Some(e.to_string()), None),
+    };
+    let trust_level = match payload.trust_level.as_deref().unwrap_or("") {
+        "" | "none" => None,
+        "marginal" => Some(gpg_ops::TrustLevel::Marginal),
+        "full" => Some(gpg_ops::TrustLevel::Full),
+        other => return json_response::<String>(None, Some(format!("Invalid trust level: {}", other)), None),
+    };
+    let options = gpg_ops::CertifyOptions {
+        signer: config::signer_or_default(payload.signer_key_id.as_deref()),
+        user_ids: payload
+            .user_ids
+            .unwrap_or_default()
+            .lines()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect(),
+        local: payload.local.unwrap_or(false),
+        expires,
+        trust: trust_level.map(|level| gpg_ops::TrustSignature {
+            level,
+            depth: payload.trust_depth.unwrap_or(1),
+            domain: payload.trust_domain.filter(|s| !s.trim().is_empty()),
+        }),
+    };
+    match gpg_ops::certify_key(&payload.key_id, &options) {
+        Ok(summary) => json_response(Some(summary), None, None),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+pub async fn api_key_certifications(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<KeyCertificationsRequest>,
+) -> Response {
+    match gpg_ops::list_certifications(&payload.key_id) {
+        Ok(certifications) => json_response(Some(certifications), None, None),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+pub async fn api_key_trust(State(_state): State<Arc<AppState>>) -> Response {
+    let trust_model = gpg_ops::get_trust_model().unwrap_or_else(|e| format!("unknown ({})", e));
+    match gpg_ops::list_key_trust() {
+        Ok(keys) => json_response(Some(serde_json::json!({
+            "trust_model": trust_model,
+            "keys": keys,
+        })), None, None),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+pub async fn api_set_ownertrust(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<SetOwnertrustRequest>,
+) -> Response {
+    let result = gpg_ops::OwnerTrust::parse(&payload.trust)
+        .and_then(|trust| gpg_ops::set_owner_trust(&payload.key_id, trust));
+    match result {
+        Ok(summary) => json_response(Some(summary), None, None),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+pub async fn api_export_ownertrust(State(_state): State<Arc<AppState>>) -> Response {
+    match gpg_ops::export_ownertrust() {
+        Ok(ownertrust) => {
+            let qr = qr_utils::generate_qr_svg(&ownertrust).ok();
+            json_response(Some(ownertrust), None, qr)
+        }
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+pub async fn api_import_ownertrust(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<ImportOwnertrustRequest>,
+) -> Response {
+    match gpg_ops::import_ownertrust(&payload.ownertrust) {
+        Ok(summary) => json_response(Some(summary), None, None),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+pub async fn api_set_trust_model(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<TrustModelRequest>,
+) -> Response {
+    match gpg_ops::set_trust_model(payload.trust_model.trim()) {
+        Ok(summary) => json_response(Some(summary), None, None),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+pub async fn api_export_key_filtered(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<ExportFilteredRequest>,
+) -> Response {
+    let filter = gpg_ops::ExportFilter {
+        minimal: payload.minimal.unwrap_or(false),
+        clean: payload.clean.unwrap_or(false),
+        drop_user_ids: non_empty_lines(payload.drop_user_ids.as_deref()),
+        subkeys: non_empty_lines(payload.subkeys.as_deref()),
+    };
+    let key_data = match gpg_ops::export_key_filtered(&payload.key_id, &filter) {
+        Ok(key_data) => key_data,
+        Err(e) => return json_response::<String>(None, Some(e.to_string()), None),
+    };
+    let estimate = qr_utils::estimate_qr(&key_data);
+    let notes = vec![estimate.describe()];
+    if payload.estimate_only.unwrap_or(false) {
+        return json_response_with_notes(Some(estimate), None, None, notes);
+    }
+    if estimate.fits_single_qr {
+        let qr = qr_utils::generate_qr_svg(&key_data).ok();
+        return json_response_with_notes(Some(key_data), None, qr, notes);
+    }
+    match qr_utils::generate_qr_frames_svg(&key_data) {
+        Ok(frames) => json_success(key_data, None, Some(notes), Some(frames)),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+pub async fn api_export_secret_subkeys(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<ExportSecretSubkeysRequest>,
+) -> Response {
+    let subkeys = non_empty_lines(payload.subkeys.as_deref());
+    secret_export_response(
+        &payload.key_id,
+        Some(&subkeys),
+        payload.key_passphrase,
+        payload.recipient.as_deref(),
+        payload.export_passphrase,
+    )
+}
+
+pub async fn api_backup(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<BackupRequest>,
+) -> Response {
+    let passphrase = Zeroizing::new(payload.passphrase);
+    if *passphrase != payload.passphrase_confirm {
+        return json_response::<String>(None, Some("Passphrases do not match.".to_string()), None);
+    }
+    match backup::create_backup(&passphrase) {
+        Ok((archive, manifest)) => json_response_with_frames(archive, Some(vec![manifest.describe()])),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+pub async fn api_restore_backup(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<RestoreBackupRequest>,
+) -> Response {
+    let passphrase = Zeroizing::new(payload.passphrase);
+    match backup::restore_backup(&payload.archive, &passphrase, payload.validate_only.unwrap_or(false)) {
+        Ok(report) => {
+            let mut notes = vec![report.manifest.describe()];
+            notes.extend(report.problems.iter().map(|p| format!("Problem: {}", p)));
+            if !report.problems.is_empty() {
+                return json_response_with_notes::<String>(
+                    None,
+                    Some("Backup is incomplete, nothing was restored.".to_string()),
+                    None,
+                    notes,
+                );
+            }
+            if let Some(import) = &report.import {
+                notes.push(import.describe());
+            }
+            if report.restored {
+                notes.push(format!("{} revocation certificate(s) stored.", report.revocation_certs_stored));
+            } else {
+                notes.push("Backup is complete. Nothing was restored (validation only).".to_string());
+            }
+            json_success(report, None, Some(notes), None)
+        }
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+// Printable page, opened in its own tab by a plain form post
+pub async fn paper_backup_page(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<PaperBackupRequest>,
+) -> Response {
+    let key_passphrase = Zeroizing::new(payload.key_passphrase.unwrap_or_default());
+    let page = paperkey::PaperEncoding::parse(payload.format.as_deref().unwrap_or("base16"))
+        .and_then(|encoding| paperkey::create_paper_backup(&payload.key_id, encoding, &key_passphrase))
+        .and_then(|backup| backup.html());
+    secret_page_response(page)
+}
+
+pub async fn api_paper_restore(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<PaperRestoreRequest>,
+) -> Response {
+    let public_key = payload.public_key.as_deref().filter(|key| !key.trim().is_empty());
+    match paperkey::restore_paper_backup(&payload.paper_text, public_key, true) {
+        Ok(restored) => {
+            let report = restored.import.map(|import| import.describe()).unwrap_or_default();
+            json_response(Some(format!("Restored secret key {}.\n{}", restored.fingerprint, report)), None, None)
+        }
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+// Printable share cards, opened in their own tab by a plain form post
+pub async fn share_cards_page(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<ShareCardsRequest>,
+) -> Response {
+    let key_passphrase = Zeroizing::new(payload.key_passphrase.unwrap_or_default());
+    let archive = payload.archive.as_deref().filter(|a| !a.trim().is_empty());
+    let source = match (archive, payload.key_id.as_deref().filter(|k| !k.trim().is_empty())) {
+        (Some(archive), _) => shamir::SplitSource::BackupArchive(archive),
+        (None, Some(key_id)) => shamir::SplitSource::SecretKey(key_id, &key_passphrase),
+        (None, None) => {
+            return secret_page_response(Err(AppError::InvalidInput("Give a key ID or a backup archive.".to_string())))
+        }
+    };
+    let page = shamir::create_share_cards(source, payload.threshold, payload.shares).and_then(|cards| cards.html());
+    secret_page_response(page)
+}
+
+pub async fn api_combine_shares(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<CombineSharesRequest>,
+) -> Response {
+    let lines: Vec<String> = payload.shares.lines().map(str::to_string).collect();
+    let result = shamir::parse_share_lines(&lines).and_then(|shares| shamir::restore_from_shares(&shares, true));
+    match result {
+        // A restored backup archive is still encrypted and goes into the Restore Backup form
+        Ok(restored) if restored.kind == shamir::SecretKind::BackupArchive => {
+            json_response_with_notes(Some(restored.data.to_string()), None, None, vec![restored.describe()])
+        }
+        Ok(restored) => json_response(Some(restored.describe()), None, None),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+pub async fn api_revocation_cert(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<RevocationCertRequest>,
+) -> Response {
+    let result = parse_revocation_reason(&payload.reason).and_then(|reason| {
+        gpg_ops::generate_revocation_cert(&payload.key_id, reason, payload.description.as_deref().unwrap_or(""))
+    });
+    match result {
+        Ok(cert) => json_response_with_frames(cert, None),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+pub async fn api_stored_revocation_cert(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<StoredRevocationCertRequest>,
+) -> Response {
+    match gpg_ops::stored_revocation_cert(&payload.key_id) {
+        Ok(cert) => json_response_with_frames(cert, None),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+pub async fn api_revoke_key(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<RevokeRequest>,
+) -> Response {
+    let description = payload.description.as_deref().unwrap_or("");
+    let result = parse_revocation_reason(&payload.reason)
+        .and_then(|reason| gpg_ops::revoke_key(&payload.key_id, reason, description));
+    match result {
+        Ok(summary) => json_response(Some(summary), None, None),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+pub async fn api_revoke_subkey(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<RevokeRequest>,
+) -> Response {
+    let Some(subkey) = payload.subkey.as_deref().map(str::trim).filter(|s| !s.is_empty()) else {
+        return json_response::<String>(None, Some("Subkey fingerprint is required.".to_string()), None);
+    };
+    let description = payload.description.as_deref().unwrap_or("");
+    let result = parse_revocation_reason(&payload.reason)
+        .and_then(|reason| gpg_ops::revoke_subkey(&payload.key_id, subkey, reason, description));
+    match result {
+        Ok(summary) => json_response(Some(summary), None, None),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+pub async fn api_revoke_uid(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<RevokeRequest>,
+) -> Response {
+    let Some(user_id) = payload.user_id.as_deref().map(str::trim).filter(|s| !s.is_empty()) else {
+        return json_response::<String>(None, Some("User ID is required.".to_string()), None);
+    };
+    let description = payload.description.as_deref().unwrap_or("");
+    let result = parse_revocation_reason(&payload.reason)
+        .and_then(|reason| gpg_ops::revoke_user_id(&payload.key_id, user_id, reason, description));
+    match result {
+        Ok(summary) => json_response(Some(summary), None, None),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+pub async fn api_subkeys(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<SubkeysRequest>,
+) -> Response {
+    match gpg_ops::list_subkeys(&payload.key_id) {
+        Ok(subkeys) => json_response(Some(subkeys), None, None),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+pub async fn api_add_subkey(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<AddSubkeyRequest>,
+) -> Response {
+    let result = gpg_ops::SubkeyUsage::parse(&payload.usage).and_then(|usage| {
+        let expires = gpg_ops::parse_expiry(payload.expires.as_deref().unwrap_or("1y"))?;
+        gpg_ops::add_subkey(&payload.key_id, payload.algorithm.as_deref().unwrap_or(""), usage, expires)
+    });
+    match result {
+        Ok(summary) => json_response(Some(summary), None, None),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+pub async fn api_set_expiry(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<SetExpiryRequest>,
+) -> Response {
+    let subkeys = non_empty_lines(payload.subkeys.as_deref());
+    let result = gpg_ops::parse_expiry(&payload.expires)
+        .and_then(|expires| gpg_ops::set_expiry(&payload.key_id, &subkeys, expires));
+    match result {
+        Ok(summary) => json_response(Some(summary), None, None),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+pub async fn api_key_details(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<KeyDetailsRequest>,
+) -> Response {
+    match gpg_ops::key_details(&payload.key_id) {
+        Ok(details) => json_response(Some(details), None, None),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+pub async fn api_add_uid(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<UserIdRequest>,
+) -> Response {
+    match gpg_ops::add_user_id(&payload.key_id, &payload.user_id) {
+        Ok(summary) => json_response(Some(summary), None, None),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+pub async fn api_set_primary_uid(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<UserIdRequest>,
+) -> Response {
+    match gpg_ops::set_primary_user_id(&payload.key_id, payload.user_id.trim()) {
+        Ok(summary) => json_response(Some(summary), None, None),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
     }
 }
 
@@ -156,9 +886,19 @@ pub async fn api_import_key(
     State(_state): State<Arc<AppState>>,
     Form(payload): Form<ImportKeyRequest>,
 ) -> Response {
-     match gpg_ops::import_key(&payload.key_data) {
-          Ok(summary) => json_response(Some(summary), None, None),
-          Err(e) => json_response::<String>(None, Some(e.toString()), None),
+     let options = gpg_ops::ImportOptions {
+          keep_ownertrust: payload.keep_ownertrust.unwrap_or(false),
+          clean: payload.import_clean.unwrap_or(false),
+          restore: payload.restore.unwrap_or(false),
+          keep_user_ids: non_empty_lines(payload.keep_user_ids.as_deref()),
+          fingerprints: non_empty_lines(payload.fingerprints.as_deref()),
+     };
+     match gpg_ops::import_keys(&payload.key_data, &options) {
+          Ok(report) => {
+               let notes = vec![report.describe()];
+               json_response_with_notes(Some(report), None, None, notes)
+          }
+          Err(e) => json_response::<String>(None, Some(e.to_string()), None),
      }
 }
 
@@ -167,28 +907,36 @@ pub async fn api_encrypt(
     Form(payload): Form<EncryptRequest>,
 ) -> Response {
      // Basic parsing for comma-separated recipients
-     let recipients_vec: Vec<&str> = payload.recipients.split(',')
+     let mut recipients_vec: Vec<&str> = payload.recipients.split(',')
           .map(str::trim)
           .filter(|s| !s.is_empty())
           .collect();
+     let default_recipients = config::default_recipients();
+     if recipients_vec.is_empty() {
+          recipients_vec = default_recipients.iter().map(String::as_str).collect();
+     }
 
      if recipients_vec.is_empty() {
           return json_response::<String>(None, Some("No valid recipients provided.".to_string()), None);
      }
 
 
+     // Explain which trust path makes each recipient key valid (or why it is not)
+     let trust_notes = gpg_ops::explain_recipients(&recipients_vec)
+          .unwrap_or_else(|e| vec![format!("Trust explanation unavailable: {}", e)]);
+
      match gpg_ops::encrypt(&payload.plaintext, &recipients_vec) {
          Ok(ciphertext) => {
              let qr_result = qr_utils::generate_qr_svg(&ciphertext);
               match qr_result {
-                 Ok(svg) => json_response(Some(ciphertext), None, Some(svg)),
+                 Ok(svg) => json_response_with_notes(Some(ciphertext), None, Some(svg), trust_notes),
                  Err(e) => {
                       println!("QR Generation failed: {}", e);
-                      json_response(Some(ciphertext), Some("Encryption successful, but QR generation failed.".to_string()), None)
+                      json_response_with_notes(Some(ciphertext), Some("Encryption successful, but QR generation failed.".to_string()), None, trust_notes)
                  }
               }
          }
-         Err(e) => json_response::<String>(None, Some(e.toString()), None),
+         Err(e) => json_response_with_notes::<String>(None, Some(e.to_string()), None, trust_notes),
      }
 }
 
@@ -223,7 +971,9 @@ pub async fn api_sign(
      };
 
      // Pass None for passphrase for now
-     match gpg_ops::sign(&payload.plaintext, &payload.signer_key_id, mode, None) {
+     let result = signer_or_default(&payload.signer_key_id)
+          .and_then(|signer| gpg_ops::sign(&payload.plaintext, &signer, mode, None));
+     match result {
          Ok(signed_data) => {
               let qr_result = qr_utils::generate_qr_svg(&signed_data);
               match qr_result {
@@ -250,29 +1000,262 @@ pub async fn api_verify(
 }
 
 
+// Online side of air-gapped signing: the request QR is carried to the offline signer
+pub async fn api_airgap_sign_request(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<AirgapSignRequest>,
+) -> Response {
+    let input = if payload.hash_only.unwrap_or(false) {
+        airgap::SignInput::Digest(payload.content.as_bytes())
+    } else {
+        airgap::SignInput::Content(&payload.content)
+    };
+    let info = airgap::RequestInfo {
+        document: &payload.document,
+        requester: &payload.requester,
+        description: payload.description.as_deref().unwrap_or(""),
+    };
+    let result = airgap::SignRequestMode::parse(&payload.mode).and_then(|mode| {
+        let request = airgap::create_sign_request(input, &signer_or_default(&payload.signer)?, mode, info)?;
+        Ok((request.encode()?, request.describe()))
+    });
+    match result {
+        Ok((encoded, notes)) => json_response_with_frames(encoded, Some(notes)),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+// Checks a pasted signing response against the pending request and returns the signature
+pub async fn api_airgap_sign_response(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<AirgapSignResponseRequest>,
+) -> Response {
+    match airgap::check_sign_response(&payload.response, None) {
+        Ok(accepted) => json_response_with_notes(Some(accepted.signature), None, None, accepted.notes),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+// Offline signer: requests waiting for approval, with what to review for each
+pub async fn api_sign_queue(State(state): State<Arc<AppState>>) -> Response {
+    json_response(Some(state.sign_queue.list()), None, None)
+}
+
+pub async fn api_sign_queue_submit(
+    State(state): State<Arc<AppState>>,
+    Form(payload): Form<SignQueueSubmitRequest>,
+) -> Response {
+    match state.sign_queue.submit(&payload.request) {
+        Ok(entry) => json_response_with_notes(Some(entry.id), None, None, entry.review),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+// Explicit approval: only here does a queued request get signed
+pub async fn api_sign_queue_approve(
+    State(state): State<Arc<AppState>>,
+    Form(payload): Form<SignQueueDecisionRequest>,
+) -> Response {
+    match state.sign_queue.approve(&payload.id) {
+        Ok(response) => json_response_with_frames(response, None),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+pub async fn api_sign_queue_reject(
+    State(state): State<Arc<AppState>>,
+    Form(payload): Form<SignQueueDecisionRequest>,
+) -> Response {
+    match state.sign_queue.reject(&payload.id, payload.reason.as_deref().unwrap_or("")) {
+        Ok(()) => json_response(Some("Signing request rejected."), None, None),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+// Online side of air-gapped decryption: the session that opens the response stays in memory
+pub async fn api_airgap_decrypt_request(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<AirgapDecryptRequest>,
+) -> Response {
+    let description = payload.description.as_deref().unwrap_or("");
+    let result = airgap::create_decrypt_request(&payload.ciphertext, payload.use_passphrase.unwrap_or(false), description)
+        .and_then(|(request, session)| {
+            let encoded = request.encode()?;
+            airgap::remember_decrypt_session(session);
+            Ok((encoded, request.describe()))
+        });
+    match result {
+        Ok((encoded, notes)) => json_response_with_frames(encoded, Some(notes)),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+pub async fn api_airgap_decrypt_response(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<AirgapDecryptResponseRequest>,
+) -> Response {
+    match airgap::open_decrypt_response(&payload.response, None) {
+        Ok(plaintext) => json_response(Some(plaintext.to_string()), None, None),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+pub async fn api_audit_verify(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<AuditVerifyRequest>,
+) -> Response {
+    let key = payload.key.as_deref().filter(|key| !key.trim().is_empty());
+    let report = match payload.log.as_deref().filter(|log| !log.trim().is_empty()) {
+        Some(log) => audit::verify_text(log, key),
+        None => audit::verify_log(key),
+    };
+    match report {
+        Ok(report) => json_response(Some(report.describe()), None, None),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
+// Audit log entries as QR frames, to carry them off the air-gapped machine
+pub async fn api_audit_export(
+    State(_state): State<Arc<AppState>>,
+    Form(payload): Form<AuditExportRequest>,
+) -> Response {
+    match audit::export(payload.since.unwrap_or(1)) {
+        Ok(exported) => json_response_with_frames(exported, None),
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
+
 // Handler to process data received from client-side QR scan
 pub async fn api_process_qr_data(
-    State(_state): State<Arc<AppState>>,
+    State(state): State<Arc<AppState>>,
     Form(payload): Form<ProcessQrDataRequest>,
 ) -> Response {
      println!("Received data from QR Scan: {} bytes", payload.scanned_data.len());
-     // Here, decide what to do with the scanned data.
-     // For simplicity, just return it to the client, maybe identifying its type.
-     let data_type = if payload.scanned_data.contains("-----BEGIN PGP PUBLIC KEY BLOCK-----") {
-         "PGP Public Key"
-     } else if payload.scanned_data.contains("-----BEGIN PGP MESSAGE-----") {
-         "PGP Encrypted Message"
-     } else if payload.scanned_data.contains("-----BEGIN PGP SIGNED MESSAGE-----") {
-         "PGP Signed Message"
-     } else if payload.scanned_data.contains("-----BEGIN PGP SIGNATURE-----") {
-         "PGP Detached Signature"
-     } else {
-         "Unknown / Plain Text"
-     };
+     let scans = store_scans(&state, &payload.scanned_data);
 
+     // The first block is also returned at the top level for simple clients
+     let first = scans.first().cloned().unwrap_or_default();
      json_response(Some(serde_json::json!({
+         "scan_id": first["scan_id"],
          "received_data": payload.scanned_data,
-         "data_type": data_type,
-         "message": "Data received. Choose next action (e.g., Import Key, Decrypt Message)."
+         "data_type": first["data_type"],
+         "analysis": first["analysis"],
+         "scans": scans,
      })), None, None)
-}
\ No newline at end of file
+}
+
+// Analyses a payload server-side and keeps each OpenPGP block in it under a scan ID,
+// so the client only has to send back the ID and the chosen action.
+fn store_scans(state: &AppState, data: &str) -> Vec<serde_json::Value> {
+    store_scanned(state, scan::analyze_payload(data))
+}
+
+// Keeps analysed payloads server-side (collecting secret shares on the way) and describes them for the client
+fn store_scanned(state: &AppState, scanned: Vec<scan::ScannedPayload>) -> Vec<serde_json::Value> {
+    scanned
+        .into_iter()
+        .map(|mut scanned| {
+            state.scans.collect_share(&scanned.data, &mut scanned.analysis);
+            state.sign_queue.collect(&scanned.data, &mut scanned.analysis);
+            let scan_id = state.scans.insert(scanned.data.clone(), scanned.analysis.clone());
+            serde_json::json!({
+                "scan_id": scan_id,
+                "data": scanned.data,
+                "data_type": scanned.analysis.data_type,
+                "analysis": scanned.analysis,
+            })
+        })
+        .collect()
+}
+
+// Dry run of an import: the key data is held server-side and only imported once the
+// preview is confirmed through /api/scan_action (import_key), or dropped via /api/scan_discard.
+pub async fn api_import_preview(
+    State(state): State<Arc<AppState>>,
+    Form(payload): Form<ImportKeyRequest>,
+) -> Response {
+    let (previews, others): (Vec<serde_json::Value>, Vec<serde_json::Value>) = store_scans(&state, &payload.key_data)
+        .into_iter()
+        .partition(|scan| !scan["analysis"]["keys"].as_array().map(Vec::is_empty).unwrap_or(true));
+    for other in others {
+        state.scans.discard(other["scan_id"].as_str().unwrap_or(""));
+    }
+    if previews.is_empty() {
+        return json_response::<String>(None, Some("No importable keys found in the data.".to_string()), None);
+    }
+    let notes = previews
+        .iter()
+        .flat_map(|scan| scan["analysis"]["notes"].as_array().cloned().unwrap_or_default())
+        .filter_map(|note| note.as_str().map(str::to_string))
+        .collect();
+    json_response_with_notes(Some(serde_json::json!({ "import_previews": previews })), None, None, notes)
+}
+
+// Runs an action (import / decrypt / verify) on a previously scanned payload
+pub async fn api_scan_action(
+    State(state): State<Arc<AppState>>,
+    Form(payload): Form<ScanActionRequest>,
+) -> Response {
+     match state.scans.run_action(&payload.scan_id, payload.action) {
+         // The decryption response goes back to the online machine as QR
+         Ok(result) if payload.action == scan::ScanAction::AnswerDecryptRequest => json_response_with_frames(result, None),
+         Ok(result) => json_response(Some(result), None, None),
+         Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+     }
+}
+
+pub async fn api_scan_discard(
+    State(state): State<Arc<AppState>>,
+    Form(payload): Form<ScanDiscardRequest>,
+) -> Response {
+     if state.scans.discard(&payload.scan_id) {
+         json_response(Some("Scan discarded."), None, None)
+     } else {
+         json_response::<String>(None, Some("Unknown or expired scan ID.".to_string()), None)
+     }
+}
+
+// Handler for uploaded photos/scans of QR codes (one or more PNG/JPEG files).
+// All codes found in all images are decoded, multi-frame sets are reassembled.
+pub async fn api_scan_image(
+    State(state): State<Arc<AppState>>,
+    mut multipart: Multipart,
+) -> Response {
+    let mut raw_payloads = Vec::new();
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(e) => return json_response::<String>(None, Some(format!("Invalid upload: {}", e)), None),
+        };
+        let file_name = field.file_name().unwrap_or("upload").to_string();
+        let bytes = match field.bytes().await {
+            Ok(bytes) => bytes,
+            Err(e) => return json_response::<String>(None, Some(format!("Invalid upload: {}", e)), None),
+        };
+        if bytes.is_empty() {
+            continue;
+        }
+        match qr_decode::decode_image(&bytes) {
+            Ok(payloads) => raw_payloads.extend(payloads),
+            Err(e) => return json_response::<String>(None, Some(format!("{}: {}", file_name, e)), None),
+        }
+    }
+
+    if raw_payloads.is_empty() {
+        return json_response::<String>(None, Some("No image uploaded.".to_string()), None);
+    }
+    tracing::debug!("Decoded {} QR code(s) from uploaded image(s)", raw_payloads.len());
+
+    match scan::process_payloads(&raw_payloads) {
+        Ok(scanned) => {
+            let payloads = store_scanned(&state, scanned);
+            json_response(Some(serde_json::json!({
+                "qr_codes_found": raw_payloads.len(),
+                "payloads": payloads,
+            })), None, None)
+        }
+        Err(e) => json_response::<String>(None, Some(e.to_string()), None),
+    }
+}
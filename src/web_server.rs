@@ -1,15 +1,25 @@
+use crate::audit;
 use crate::cli::CliArgs; // Assuming CliArgs is defined elsewhere
+use crate::config::{self, ServerConfig};
 use crate::error::{AppError, Result};
+use crate::policy::{self, Operation};
+use crate::qr_utils::{self, QrOverrides};
+use crate::scan::ScanStore;
+use crate::sign_queue::SignQueue;
 use crate::web_handlers; // Define handlers in a separate file
 use axum::{
+    extract::{ConnectInfo, DefaultBodyLimit, Query, Request},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
     routing::{get, post},
-    Router, Server,
+    Router,
 };
 use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener};
 use std::sync::Arc;
 use tower_http::services::ServeDir;
+use tower_http::timeout::TimeoutLayer;
 use tower_http::trace::TraceLayer;
-use tracing::Level;
+use tracing_subscriber::EnvFilter;
 
 
 // Shared state for the web server
@@ -18,23 +28,105 @@ pub struct AppState {
     pub gpg_dir: Option<String>, // Pass gpg dir if overridden
                                   // Add other shared state if needed, e.g., Arc<Mutex<GpgContext>>
                                   // Be cautious with mutable shared state across requests.
+    pub scans: ScanStore, // Scanned payloads awaiting an action, keyed by scan ID
+    pub sign_queue: SignQueue, // Signing requests awaiting approval (offline signer)
 }
 
-pub async fn run_web_server(bind_ip: String, port: Option<u16>, gpg_dir: Option<String>) -> Result<()> {
-    let bind_addr: IpAddr = bind_ip
+// Image uploads for /api/scan_image: several full resolution phone photos at once
+// (axum's default limit of 2 MB rejects even a single one)
+const SCAN_UPLOAD_LIMIT: usize = 64 * 1024 * 1024;
+
+// Operation behind each endpoint, for the policy check in routing; endpoints that only read or
+// analyse are not listed (gpg_ops checks the operations they lead to). Scan actions are checked
+// per action in ScanStore::run_action.
+const ROUTE_OPERATIONS: &[(&str, Operation)] = &[
+    ("/api/export_key", Operation::Export), // secret=true is refused in gpg_ops
+    ("/api/export_key_filtered", Operation::Export),
+    ("/api/export_secret_subkeys", Operation::ExportSecret),
+    ("/api/fingerprint_qr", Operation::Export),
+    ("/api/certify_key", Operation::Certify),
+    ("/api/set_ownertrust", Operation::Trust),
+    ("/api/import_ownertrust", Operation::Trust),
+    ("/api/trust_model", Operation::Trust),
+    ("/api/revocation_cert", Operation::Revoke),
+    ("/api/stored_revocation_cert", Operation::Revoke),
+    ("/api/revoke_key", Operation::Revoke),
+    ("/api/revoke_subkey", Operation::Revoke),
+    ("/api/revoke_uid", Operation::Revoke),
+    ("/api/add_uid", Operation::EditKey),
+    ("/api/set_primary_uid", Operation::EditKey),
+    ("/api/add_subkey", Operation::EditKey),
+    ("/api/set_expiry", Operation::EditKey),
+    ("/api/backup", Operation::ExportSecret),
+    ("/api/restore_backup", Operation::Import),
+    ("/paper_backup", Operation::ExportSecret),
+    ("/api/paper_restore", Operation::Import),
+    ("/share_cards", Operation::ExportSecret),
+    ("/api/combine_shares", Operation::Import),
+    ("/api/sign_request", Operation::Sign),
+    ("/api/sign_response", Operation::Verify),
+    ("/api/sign_queue_submit", Operation::Sign),
+    ("/api/sign_queue_approve", Operation::Sign),
+    ("/api/decrypt_request", Operation::Decrypt),
+    ("/api/decrypt_response", Operation::Decrypt),
+    ("/api/import_key", Operation::Import),
+    ("/api/encrypt", Operation::Encrypt),
+    ("/api/decrypt", Operation::Decrypt),
+    ("/api/sign", Operation::Sign),
+    ("/api/verify", Operation::Verify),
+];
+
+// Refuses disabled endpoints, and endpoints whose operation the policy disables, before they
+// reach a handler
+async fn policy_gate(request: Request, next: Next) -> Response {
+    let path = request.uri().path();
+    if let Err(e) = policy::check_endpoint(path) {
+        return e.into_response();
+    }
+    if let Some((_, operation)) = ROUTE_OPERATIONS.iter().find(|(route, _)| *route == path) {
+        if let Err(e) = policy::check(*operation) {
+            return e.into_response();
+        }
+    }
+    next.run(request).await
+}
+
+// QR parameters in the query string of any request (e.g. ?ecc=high&module_size=8) apply to
+// the QR codes it renders, on top of the configured [qr] defaults
+async fn qr_params(request: Request, next: Next) -> Response {
+    let overrides = match Query::<QrOverrides>::try_from_uri(request.uri()) {
+        Ok(Query(overrides)) => overrides,
+        Err(e) => return AppError::InvalidInput(format!("QR parameters: {}", e.body_text())).into_response(),
+    };
+    if overrides.is_empty() {
+        return next.run(request).await;
+    }
+    match overrides.apply(&config::current().qr) {
+        Ok(settings) => qr_utils::REQUEST_SETTINGS.scope(settings, next.run(request)).await,
+        Err(e) => e.into_response(),
+    }
+}
+
+// Makes the client address available to the audit log while the request is handled
+async fn audit_client(ConnectInfo(client): ConnectInfo<SocketAddr>, request: Request, next: Next) -> Response {
+    audit::CLIENT.scope(client.to_string(), next.run(request)).await
+}
+
+pub async fn run_web_server(
+    server: ServerConfig,
+    gpg_dir: Option<String>,
+    audit_key: Option<String>,
+) -> Result<()> {
+    let bind_addr: IpAddr = server.bind.as_deref().unwrap_or("127.0.0.1")
         .parse()
         .map_err(|e| AppError::AddrParse(e))?;
 
-    let actual_port = port.map_or_else(find_available_port, |p| Ok(p))?;
+    let actual_port = server.port.map_or_else(find_available_port, |p| Ok(p))?;
 
     let addr = SocketAddr::new(bind_addr, actual_port);
 
-    // Initialize tracing (logging)
-    tracing_subscriber::fmt()
-        .with_max_level(Level::INFO) // Adjust log level as needed
-        .init();
-
-    tracing::info!("Starting web server on http://{}", addr);
+    let scheme = if server.tls.is_some() { "https" } else { "http" };
+    tracing::info!("Starting web server on {}://{}", scheme, addr);
     if bind_addr == Ipv4Addr::LOCALHOST || bind_addr == IpAddr::V6(std::net::Ipv6Addr::LOCALHOST) {
         tracing::info!("Server is bound to localhost - accessible only from this machine.");
     } else {
@@ -51,32 +143,118 @@ pub async fn run_web_server(bind_ip: String, port: Option<u16>, gpg_dir: Option<
     if let Some(ref dir) = gpg_dir {
          crate::gpg_ops::set_gpg_homedir(Some(dir.clone()))?;
     }
+    match &audit_key {
+        Some(key) => tracing::info!("Audit log checkpoints are signed with {}", key),
+        None => tracing::warn!("No --audit-key given: the audit log is hash-chained but not signed."),
+    }
+    audit::set_signing_key(audit_key);
+    for line in policy::current().describe() {
+        tracing::info!("Policy: {}", line);
+    }
 
 
-    let shared_state = Arc::new(AppState { gpg_dir });
+    let shared_state = Arc::new(AppState { gpg_dir, scans: ScanStore::new(), sign_queue: SignQueue::new() });
 
     // Define routes
     let app = Router::new()
         .route("/", get(web_handlers::root))
         .route("/api/status", get(web_handlers::api_status))
         .route("/api/export_key", post(web_handlers::api_export_key))
+        .route("/api/export_key_filtered", post(web_handlers::api_export_key_filtered))
+        .route("/api/export_secret_subkeys", post(web_handlers::api_export_secret_subkeys))
+        .route("/api/fingerprint_qr", post(web_handlers::api_fingerprint_qr))
+        .route("/api/certify_key", post(web_handlers::api_certify_key))
+        .route("/api/key_certifications", post(web_handlers::api_key_certifications))
+        .route("/api/key_trust", get(web_handlers::api_key_trust))
+        .route("/api/set_ownertrust", post(web_handlers::api_set_ownertrust))
+        .route("/api/export_ownertrust", post(web_handlers::api_export_ownertrust))
+        .route("/api/import_ownertrust", post(web_handlers::api_import_ownertrust))
+        .route("/api/trust_model", post(web_handlers::api_set_trust_model))
+        .route("/api/revocation_cert", post(web_handlers::api_revocation_cert))
+        .route("/api/stored_revocation_cert", post(web_handlers::api_stored_revocation_cert))
+        .route("/api/revoke_key", post(web_handlers::api_revoke_key))
+        .route("/api/revoke_subkey", post(web_handlers::api_revoke_subkey))
+        .route("/api/revoke_uid", post(web_handlers::api_revoke_uid))
+        .route("/api/key_details", post(web_handlers::api_key_details))
+        .route("/api/add_uid", post(web_handlers::api_add_uid))
+        .route("/api/set_primary_uid", post(web_handlers::api_set_primary_uid))
+        .route("/api/subkeys", post(web_handlers::api_subkeys))
+        .route("/api/add_subkey", post(web_handlers::api_add_subkey))
+        .route("/api/set_expiry", post(web_handlers::api_set_expiry))
+        .route("/api/import_preview", post(web_handlers::api_import_preview))
+        .route("/api/backup", post(web_handlers::api_backup))
+        .route("/api/restore_backup", post(web_handlers::api_restore_backup))
+        .route("/paper_backup", post(web_handlers::paper_backup_page))
+        .route("/api/paper_restore", post(web_handlers::api_paper_restore))
+        .route("/share_cards", post(web_handlers::share_cards_page))
+        .route("/api/combine_shares", post(web_handlers::api_combine_shares))
+        .route("/api/sign_request", post(web_handlers::api_airgap_sign_request))
+        .route("/api/sign_response", post(web_handlers::api_airgap_sign_response))
+        .route("/api/sign_queue", get(web_handlers::api_sign_queue))
+        .route("/api/sign_queue_submit", post(web_handlers::api_sign_queue_submit))
+        .route("/api/sign_queue_approve", post(web_handlers::api_sign_queue_approve))
+        .route("/api/sign_queue_reject", post(web_handlers::api_sign_queue_reject))
+        .route("/api/decrypt_request", post(web_handlers::api_airgap_decrypt_request))
+        .route("/api/decrypt_response", post(web_handlers::api_airgap_decrypt_response))
+        .route("/api/audit_verify", post(web_handlers::api_audit_verify))
+        .route("/api/audit_export", post(web_handlers::api_audit_export))
         .route("/api/import_key", post(web_handlers::api_import_key))
         .route("/api/encrypt", post(web_handlers::api_encrypt))
         .route("/api/decrypt", post(web_handlers::api_decrypt))
         .route("/api/sign", post(web_handlers::api_sign))
         .route("/api/verify", post(web_handlers::api_verify))
         .route("/api/process_qr_data", post(web_handlers::api_process_qr_data))
+        .route(
+            "/api/scan_image",
+            post(web_handlers::api_scan_image).layer(DefaultBodyLimit::max(SCAN_UPLOAD_LIMIT)),
+        )
+        .route("/api/scan_action", post(web_handlers::api_scan_action))
+        .route("/api/scan_discard", post(web_handlers::api_scan_discard))
         // Serve static files (CSS, JS)
         .nest_service("/static", ServeDir::new("static"))
         .with_state(shared_state)
+        .layer(middleware::from_fn(policy_gate))
+        .layer(middleware::from_fn(qr_params))
+        .layer(middleware::from_fn(audit_client))
         .layer(TraceLayer::new_for_http()); // Add request logging
+    let app = match config::timeouts().request() {
+        Some(limit) => app.layer(TimeoutLayer::new(limit)),
+        None => app,
+    };
 
     // Run the server
-    Server::bind(&addr)
-        .serve(app.into_make_service())
-        .await
-        .map_err(|e| AppError::WebHyper(e))?; // Use hyper::Error conversion
+    match &server.tls {
+        Some(tls) => {
+            let rustls = axum_server::tls_rustls::RustlsConfig::from_pem_file(&tls.cert, &tls.key)
+                .await
+                .map_err(|e| AppError::Config(format!("Cannot load TLS certificate or key: {}", e)))?;
+            axum_server::bind_rustls(addr, rustls)
+                .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+                .await?;
+        }
+        None => {
+            axum_server::bind(addr)
+                .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+                .await?;
+        }
+    }
+
+    Ok(())
+}
 
+// Log filter and destination from the [log] table (validated when the config was loaded).
+// Without a file logs go to stderr, keeping stdout for command output (e.g. exported keys).
+pub fn init_logging() -> Result<()> {
+    let log = config::current().log;
+    let filter = EnvFilter::try_new(&log.level).map_err(|e| AppError::Config(format!("Invalid log level: {}", e)))?;
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+    match &log.file {
+        Some(path) => {
+            let file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+            subscriber.with_ansi(false).with_writer(std::sync::Mutex::new(file)).init();
+        }
+        None => subscriber.with_writer(std::io::stderr).init(),
+    }
     Ok(())
 }
 
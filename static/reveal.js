@@ -0,0 +1,8 @@
+// Hold-to-reveal for the blurred QR codes of printable pages (mouse, touch and pen)
+document.addEventListener('DOMContentLoaded', () => {
+    ['pointerdown', 'pointerup', 'pointerleave', 'pointercancel'].forEach((type) => {
+        document.querySelectorAll('.hold-to-reveal').forEach((element) => {
+            element.addEventListener(type, () => element.classList.toggle('revealed', type === 'pointerdown'));
+        });
+    });
+});
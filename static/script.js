@@ -16,8 +16,17 @@ document.addEventListener('DOMContentLoaded', () => {
     const importScannedKeyBtn = document.getElementById('import-scanned-key-btn');
     const decryptScannedMsgBtn = document.getElementById('decrypt-scanned-msg-btn');
     const verifyScannedMsgBtn = document.getElementById('verify-scanned-msg-btn');
+    const certifyScannedKeyBtn = document.getElementById('certify-scanned-key-btn');
+    const applyScannedRevocationBtn = document.getElementById('apply-scanned-revocation-btn');
+    const combineScannedSharesBtn = document.getElementById('combine-scanned-shares-btn');
+    const checkScannedSignResponseBtn = document.getElementById('check-scanned-sign-response-btn');
+    const answerScannedDecryptRequestBtn = document.getElementById('answer-scanned-decrypt-request-btn');
+    const revealScannedDecryptRequestBtn = document.getElementById('reveal-scanned-decrypt-request-btn');
+    const openScannedDecryptResponseBtn = document.getElementById('open-scanned-decrypt-response-btn');
     let html5QrCode = null; // Store the scanner instance
     let scannedQrData = ''; // Store the latest scanned data
+    let scannedScanId = null; // Server-side ID of the latest scan
+    let scannedAnalysis = null; // Server-side analysis of the latest scan
 
 
     // Function to update status and key lists
@@ -31,8 +40,12 @@ document.addEventListener('DOMContentLoaded', () => {
             const data = await response.json();
             if (data.success) {
                 statusElement.textContent = 'Ready'; // Update as needed
+                if (data.policy && data.policy.length) {
+                    statusElement.textContent += ' (' + data.policy.join(' ') + ')';
+                }
                 publicKeysList.innerHTML = data.public_keys.map(key => `<li>${escapeHtml(key)}</li>`).join('');
                 secretKeysList.innerHTML = data.secret_keys.map(key => `<li>${escapeHtml(key)}</li>`).join('');
+                refreshSignQueue(); // scans and submitted requests may have queued new entries
             } else {
                 statusElement.textContent = 'Error loading status';
                 console.error('Status API Error:', data.error);
@@ -58,6 +71,9 @@ document.addEventListener('DOMContentLoaded', () => {
                       if (errData.error) {
                            errorMsg = `Error: ${errData.error}`;
                       }
+                      if (errData.notes && errData.notes.length > 0) {
+                           errorMsg += '\n' + errData.notes.join('\n');
+                      }
                  } catch (e) { /* Ignore parsing error */ }
                 throw new Error(errorMsg);
             }
@@ -73,6 +89,9 @@ document.addEventListener('DOMContentLoaded', () => {
                 } else {
                      resultOutput.innerHTML += `<p>Completed.</p>`;
                 }
+                if (data.notes && data.notes.length > 0) {
+                     resultOutput.innerHTML += `<h4>Details:</h4><pre>${escapeHtml(data.notes.join('\n'))}</pre>`;
+                }
 
                 if (data.qr_code) {
                     qrCodeOutput.innerHTML = `<h3>QR Code for Transfer:</h3>`;
@@ -81,16 +100,53 @@ document.addEventListener('DOMContentLoaded', () => {
                     qrCodeOutput.innerHTML += `<p>Scan this QR code with the other device.</p>`;
 
                 }
+                if (data.qr_frames && data.qr_frames.length > 0) {
+                    // Printable frame set: each frame carries its index, so any scan order works
+                    const frames = data.qr_frames.map((svg, i) =>
+                        `<div class="qr-frame"><p>Frame ${i + 1} of ${data.qr_frames.length}</p>${svg}</div>`).join('');
+                    qrCodeOutput.innerHTML += `<h3>Printable QR Set:</h3>${frames}<p>Print this page and keep it offline. Scan all frames to restore.</p>`;
+                }
+                return data;
             } else {
                 resultOutput.innerHTML = `<p class="error">Error: ${escapeHtml(data.error || 'Unknown error')}</p>`;
             }
         } catch (error) {
             console.error('API Handling Error:', error);
-            resultOutput.innerHTML = `<p class="error">Error: ${escapeHtml(error.message)}</p>`;
+            resultOutput.innerHTML = `<p class="error" style="white-space: pre-wrap;">${escapeHtml(error.message)}</p>`;
         }
     }
 
 
+    // QR code settings panel: filled-in fields become query parameters of each request
+    const qrSettingsForm = document.getElementById('qr-settings-form');
+    function qrQuery() {
+        if (!qrSettingsForm) {
+            return '';
+        }
+        const params = new URLSearchParams();
+        for (const [name, value] of new FormData(qrSettingsForm)) {
+            if (value.trim() !== '') {
+                params.append(name, value.trim());
+            }
+        }
+        const query = params.toString();
+        return query ? `?${query}` : '';
+    }
+    if (qrSettingsForm) {
+        qrSettingsForm.addEventListener('submit', (event) => event.preventDefault());
+    }
+    // Printed pages open in a new tab as plain form posts
+    document.querySelectorAll('form[target="_blank"]').forEach((form) => {
+        const action = form.getAttribute('action');
+        form.addEventListener('submit', () => {
+            form.action = action + qrQuery();
+            if ('holdToReveal' in form.dataset) {
+                // Passphrases are cleared once the browser has taken the form data
+                setTimeout(() => form.querySelectorAll('input[type="password"]').forEach((input) => { input.value = ''; }), 0);
+            }
+        });
+    });
+
     // Generic form submission handler
     function handleFormSubmit(formId, endpoint) {
         const form = document.getElementById(formId);
@@ -100,13 +156,31 @@ document.addEventListener('DOMContentLoaded', () => {
                 statusElement.textContent = 'Processing...';
                 resultOutput.innerHTML = '<p>Working...</p>'; // Indicate activity
                 qrCodeOutput.innerHTML = '';
+                // Secret key QR codes stay blurred unless held down
+                const secret = form.elements.namedItem('secret');
+                qrCodeOutput.classList.toggle('hold-to-reveal', 'holdToReveal' in form.dataset && (!secret || secret.checked));
+                qrCodeOutput.classList.remove('revealed');
 
                 const formData = new FormData(form);
-                const response = await fetch(endpoint, {
+                if ('holdToReveal' in form.dataset) {
+                    // Passphrases are not kept in the page once sent
+                    form.querySelectorAll('input[type="password"]').forEach((input) => { input.value = ''; });
+                }
+                const response = await fetch(endpoint + qrQuery(), {
                     method: 'POST',
-                    body: new URLSearchParams(formData) // Standard form encoding
+                    body: new URLSearchParams(formData), // Standard form encoding
+                    cache: 'no-store'
                 });
-                await handleApiResponse(response);
+                const data = await handleApiResponse(response);
+                if (data && form.dataset.download && typeof data.data === 'string') {
+                    // Offer the result as a file as well (e.g. a backup archive)
+                    const url = URL.createObjectURL(new Blob([data.data], { type: 'text/plain' }));
+                    const link = document.createElement('a');
+                    link.href = url;
+                    link.download = form.dataset.download;
+                    link.textContent = `Download ${form.dataset.download}`;
+                    resultOutput.prepend(link);
+                }
                 statusElement.textContent = 'Ready';
                 updateStatus(); // Refresh key lists after potential changes
             });
@@ -115,12 +189,61 @@ document.addEventListener('DOMContentLoaded', () => {
         }
     }
 
+    // Hold-to-reveal for blurred QR codes (mouse, touch and pen)
+    ['pointerdown', 'pointerup', 'pointerleave', 'pointercancel'].forEach((type) => {
+        qrCodeOutput.addEventListener(type, () => {
+            if (qrCodeOutput.classList.contains('hold-to-reveal')) {
+                qrCodeOutput.classList.toggle('revealed', type === 'pointerdown');
+            }
+        });
+    });
+
+    // Load a backup file into the restore form
+    const restoreFile = document.getElementById('restore-backup-file');
+    if (restoreFile) {
+        restoreFile.addEventListener('change', async () => {
+            if (restoreFile.files.length > 0) {
+                document.getElementById('restore-backup-archive').value = await restoreFile.files[0].text();
+            }
+        });
+    }
+
     // Setup form handlers
     handleFormSubmit('export-form', '/api/export_key');
+    handleFormSubmit('export-filtered-form', '/api/export_key_filtered');
+    handleFormSubmit('export-secret-subkeys-form', '/api/export_secret_subkeys');
+    handleFormSubmit('fingerprint-qr-form', '/api/fingerprint_qr');
+    handleFormSubmit('certify-form', '/api/certify_key');
+    handleFormSubmit('ownertrust-form', '/api/set_ownertrust');
+    handleFormSubmit('trust-model-form', '/api/trust_model');
+    handleFormSubmit('export-ownertrust-form', '/api/export_ownertrust');
+    handleFormSubmit('import-ownertrust-form', '/api/import_ownertrust');
+    handleFormSubmit('key-details-form', '/api/key_details');
+    handleFormSubmit('add-uid-form', '/api/add_uid');
+    handleFormSubmit('set-primary-uid-form', '/api/set_primary_uid');
+    handleFormSubmit('subkeys-form', '/api/subkeys');
+    handleFormSubmit('add-subkey-form', '/api/add_subkey');
+    handleFormSubmit('set-expiry-form', '/api/set_expiry');
+    handleFormSubmit('revocation-cert-form', '/api/revocation_cert');
+    handleFormSubmit('stored-revocation-cert-form', '/api/stored_revocation_cert');
+    handleFormSubmit('revoke-key-form', '/api/revoke_key');
+    handleFormSubmit('revoke-subkey-form', '/api/revoke_subkey');
+    handleFormSubmit('revoke-uid-form', '/api/revoke_uid');
+    handleFormSubmit('backup-form', '/api/backup');
+    handleFormSubmit('restore-backup-form', '/api/restore_backup');
+    handleFormSubmit('paper-restore-form', '/api/paper_restore');
+    handleFormSubmit('combine-shares-form', '/api/combine_shares');
     handleFormSubmit('import-form', '/api/import_key');
     handleFormSubmit('encrypt-form', '/api/encrypt');
     handleFormSubmit('decrypt-form', '/api/decrypt');
+    handleFormSubmit('decrypt-request-form', '/api/decrypt_request');
+    handleFormSubmit('decrypt-response-form', '/api/decrypt_response');
+    handleFormSubmit('audit-verify-form', '/api/audit_verify');
+    handleFormSubmit('audit-export-form', '/api/audit_export');
     handleFormSubmit('sign-form', '/api/sign');
+    handleFormSubmit('sign-request-form', '/api/sign_request');
+    handleFormSubmit('sign-response-form', '/api/sign_response');
+    handleFormSubmit('sign-queue-submit-form', '/api/sign_queue_submit');
     handleFormSubmit('verify-form', '/api/verify');
 
 
@@ -147,6 +270,13 @@ document.addEventListener('DOMContentLoaded', () => {
          importScannedKeyBtn.style.display = 'none';
          decryptScannedMsgBtn.style.display = 'none';
          verifyScannedMsgBtn.style.display = 'none';
+         certifyScannedKeyBtn.style.display = 'none';
+         applyScannedRevocationBtn.style.display = 'none';
+         combineScannedSharesBtn.style.display = 'none';
+         checkScannedSignResponseBtn.style.display = 'none';
+         answerScannedDecryptRequestBtn.style.display = 'none';
+         revealScannedDecryptRequestBtn.style.display = 'none';
+         openScannedDecryptResponseBtn.style.display = 'none';
 
          try {
               const response = await fetch('/api/process_qr_data', {
@@ -156,17 +286,9 @@ document.addEventListener('DOMContentLoaded', () => {
               });
               const result = await response.json();
               if (result.success && result.data) {
-                   scannedDataType.textContent = result.data.data_type || 'Unknown';
-                   // Show relevant action buttons based on detected type
-                   const type = result.data.data_type;
-                   if (type === 'PGP Public Key') {
-                        importScannedKeyBtn.style.display = 'inline-block';
-                   } else if (type === 'PGP Encrypted Message') {
-                        decryptScannedMsgBtn.style.display = 'inline-block';
-                   } else if (type === 'PGP Signed Message' || type === 'PGP Detached Signature') {
-                        // Verification might need more complex UI if detached
-                        verifyScannedMsgBtn.style.display = 'inline-block';
-                   }
+                   scannedScanId = result.data.scan_id;
+                   showScannedDataActions(result.data.analysis);
+                   refreshSignQueue();
               } else {
                    scannedDataType.textContent = 'Analysis Failed';
                    scannedDataDisplay.textContent = `Error: ${result.error || 'Unknown'}`;
@@ -179,6 +301,99 @@ document.addEventListener('DOMContentLoaded', () => {
     }
 
 
+    // Show the action buttons the server reported as possible for this scan
+    function showScannedDataActions(analysis) {
+         scannedAnalysis = analysis;
+         scannedDataType.textContent = analysis.data_type || 'Unknown';
+         const details = [];
+         (analysis.keys || []).forEach(key => details.push(`Key: ${key.fingerprint} ${key.user_ids.join(', ')}${key.in_keyring ? ' (in keyring)' : ''}${key.has_secret ? ' (contains secret key)' : ''}`));
+         (analysis.recipients || []).forEach(r => details.push(`Encrypted to: ${r.key_id} ${r.secret_key_uid || '(no secret key)'}`));
+         if (analysis.fingerprint) {
+              details.push(`Fingerprint: ${analysis.fingerprint}`);
+         }
+         (analysis.notes || []).forEach(note => details.push(`Note: ${note}`));
+         if (details.length > 0) {
+              scannedDataType.textContent += ' | ' + details.join(' | ');
+         }
+         const actions = analysis.actions || [];
+         if (actions.includes('import_key')) {
+              importScannedKeyBtn.style.display = 'inline-block';
+         }
+         if (actions.includes('decrypt')) {
+              decryptScannedMsgBtn.style.display = 'inline-block';
+         }
+         if (actions.includes('verify')) {
+              verifyScannedMsgBtn.style.display = 'inline-block';
+         }
+         if (actions.includes('certify_key')) {
+              certifyScannedKeyBtn.style.display = 'inline-block';
+         }
+         if (actions.includes('apply_revocation')) {
+              applyScannedRevocationBtn.style.display = 'inline-block';
+         }
+         if (actions.includes('combine_shares')) {
+              combineScannedSharesBtn.style.display = 'inline-block';
+         }
+         if (actions.includes('check_sign_response')) {
+              checkScannedSignResponseBtn.style.display = 'inline-block';
+         }
+         if (actions.includes('answer_decrypt_request')) {
+              answerScannedDecryptRequestBtn.style.display = 'inline-block';
+         }
+         if (actions.includes('reveal_decrypt_request')) {
+              revealScannedDecryptRequestBtn.style.display = 'inline-block';
+         }
+         if (actions.includes('open_decrypt_response')) {
+              openScannedDecryptResponseBtn.style.display = 'inline-block';
+         }
+    }
+
+    // Server-side decoding of uploaded photos/scans
+    const scanImageForm = document.getElementById('scan-image-form');
+    if (scanImageForm) {
+         scanImageForm.addEventListener('submit', async (event) => {
+              event.preventDefault();
+              qrResultElement.textContent = 'Decoding image(s)...';
+              importScannedKeyBtn.style.display = 'none';
+              decryptScannedMsgBtn.style.display = 'none';
+              verifyScannedMsgBtn.style.display = 'none';
+              certifyScannedKeyBtn.style.display = 'none';
+              applyScannedRevocationBtn.style.display = 'none';
+              combineScannedSharesBtn.style.display = 'none';
+              checkScannedSignResponseBtn.style.display = 'none';
+              answerScannedDecryptRequestBtn.style.display = 'none';
+              revealScannedDecryptRequestBtn.style.display = 'none';
+              openScannedDecryptResponseBtn.style.display = 'none';
+              try {
+                   const response = await fetch('/api/scan_image', {
+                        method: 'POST',
+                        body: new FormData(scanImageForm) // multipart, keeps the file bytes
+                   });
+                   const result = await response.json();
+                   if (result.success && result.data && result.data.payloads.length > 0) {
+                        const payloads = result.data.payloads;
+                        qrResultElement.textContent = `Decoded ${result.data.qr_codes_found} QR code(s) into ${payloads.length} payload(s).`;
+                        // Several share cards in one photo: show the share that completed its set
+                        const shown = payloads.find(p => (p.analysis.actions || []).includes('combine_shares')) || payloads[0];
+                        if (payloads.length > 1) {
+                             console.warn('Multiple payloads decoded, showing one of them.', payloads);
+                        }
+                        scannedQrData = shown.data;
+                        scannedScanId = shown.scan_id;
+                        scannedDataDisplay.textContent = scannedQrData;
+                        showScannedDataActions(shown.analysis);
+                        refreshSignQueue();
+                   } else {
+                        qrResultElement.textContent = `Error: ${result.error || 'No QR code found'}`;
+                   }
+              } catch (error) {
+                   console.error('Error decoding image:', error);
+                   qrResultElement.textContent = `Failed to decode image: ${error.message}`;
+              }
+         });
+    }
+
+
     function startScanning() {
         if (!html5QrCode) {
              html5QrCode = new Html5Qrcode("qr-reader");
@@ -221,49 +436,161 @@ document.addEventListener('DOMContentLoaded', () => {
     stopScanBtn.addEventListener('click', stopScanning);
 
      // --- Action button handlers for scanned data ---
-     importScannedKeyBtn.addEventListener('click', () => {
-         if (scannedQrData) {
-              // Populate the import form and submit it
-              const importTextArea = document.getElementById('import-key-data');
-              const importForm = document.getElementById('import-form');
-              if (importTextArea && importForm) {
-                   importTextArea.value = scannedQrData;
-                   importForm.dispatchEvent(new Event('submit')); // Trigger submission
-                   // Optionally clear scanned data display after action
-                   // scannedDataDisplay.textContent = '';
-                   // scannedDataType.textContent = 'N/A';
-                   // importScannedKeyBtn.style.display = 'none';
-              }
-          } else {
-              alert("No scanned data available to import.");
+     // The data stays on the server; only the scan ID and the action are sent.
+     async function runScanAction(action) {
+          if (!scannedScanId) {
+               alert("No scanned data available.");
+               return;
           }
-     });
+          statusElement.textContent = 'Processing...';
+          const response = await fetch('/api/scan_action' + qrQuery(), {
+               method: 'POST',
+               headers: { 'Content-Type': 'application/x-www-form-urlencoded' },
+               body: new URLSearchParams({ scan_id: scannedScanId, action: action })
+          });
+          await handleApiResponse(response);
+          statusElement.textContent = 'Ready';
+          updateStatus(); // Refresh key lists after potential changes
+     }
 
-     decryptScannedMsgBtn.addEventListener('click', () => {
-          if (scannedQrData) {
-               const decryptTextArea = document.getElementById('decrypt-ciphertext');
-               const decryptForm = document.getElementById('decrypt-form');
-               if (decryptTextArea && decryptForm) {
-                    decryptTextArea.value = scannedQrData;
-                    decryptForm.dispatchEvent(new Event('submit'));
-               }
-          } else {
-              alert("No scanned data available to decrypt.");
+     // Signing queue: each entry shows what would be signed; only Approve signs it
+     const signQueueEntries = document.getElementById('sign-queue-entries');
+     async function postSignQueueDecision(url, params) {
+          statusElement.textContent = 'Processing...';
+          const response = await fetch(url + qrQuery(), {
+               method: 'POST',
+               headers: { 'Content-Type': 'application/x-www-form-urlencoded' },
+               body: new URLSearchParams(params)
+          });
+          await handleApiResponse(response);
+          statusElement.textContent = 'Ready';
+          refreshSignQueue();
+     }
+     async function refreshSignQueue() {
+          if (!signQueueEntries) {
+               return;
           }
-     });
+          const response = await fetch('/api/sign_queue');
+          const result = await response.json().catch(() => null);
+          signQueueEntries.innerHTML = '';
+          if (!result || !result.success) {
+               return;
+          }
+          if (result.data.length === 0) {
+               signQueueEntries.innerHTML = '<p>No pending signing requests.</p>';
+          }
+          result.data.forEach(entry => {
+               const item = document.createElement('div');
+               item.innerHTML = `<h4>${escapeHtml(entry.request.document)} (requested by ${escapeHtml(entry.request.requester)})</h4>` +
+                    `<p>Key: ${escapeHtml(entry.signing_key)}</p>` +
+                    `<pre>${escapeHtml(entry.review.join('\n'))}</pre>`;
+               const approveBtn = document.createElement('button');
+               approveBtn.type = 'button';
+               approveBtn.textContent = 'Approve And Sign';
+               const rejectBtn = document.createElement('button');
+               rejectBtn.type = 'button';
+               rejectBtn.textContent = 'Reject';
+               approveBtn.addEventListener('click', () => {
+                    if (confirm(`Sign ${entry.request.document} with ${entry.signing_key}?`)) {
+                         postSignQueueDecision('/api/sign_queue_approve', { id: entry.id });
+                    }
+               });
+               rejectBtn.addEventListener('click', () => {
+                    const reason = prompt('Reason for rejecting this request:');
+                    if (reason !== null) {
+                         postSignQueueDecision('/api/sign_queue_reject', { id: entry.id, reason: reason });
+                    }
+               });
+               item.appendChild(approveBtn);
+               item.appendChild(rejectBtn);
+               signQueueEntries.appendChild(item);
+          });
+     }
+     const signQueueRefreshBtn = document.getElementById('sign-queue-refresh-btn');
+     if (signQueueRefreshBtn) {
+          signQueueRefreshBtn.addEventListener('click', refreshSignQueue);
+     }
 
-      verifyScannedMsgBtn.addEventListener('click', () => {
-          if (scannedQrData) {
-               const verifyTextArea = document.getElementById('verify-signed-data');
-               const verifyForm = document.getElementById('verify-form');
-               if (verifyTextArea && verifyForm) {
-                    verifyTextArea.value = scannedQrData;
-                    verifyForm.dispatchEvent(new Event('submit'));
+     // Import preview: nothing is imported until one of the previews is confirmed
+     const importPreviewBtn = document.getElementById('import-preview-btn');
+     const importPreviewOutput = document.getElementById('import-preview-output');
+     if (importPreviewBtn) {
+          importPreviewBtn.addEventListener('click', async () => {
+               const form = document.getElementById('import-form');
+               importPreviewOutput.innerHTML = '';
+               statusElement.textContent = 'Processing...';
+               const response = await fetch('/api/import_preview', {
+                    method: 'POST',
+                    body: new URLSearchParams(new FormData(form))
+               });
+               await handleApiResponse(response.clone());
+               statusElement.textContent = 'Ready';
+               const result = await response.json().catch(() => null);
+               if (!result || !result.success) {
+                    return;
                }
-          } else {
-               alert("No scanned data available to verify.");
+               result.data.import_previews.forEach(preview => {
+                    const entry = document.createElement('div');
+                    const keys = preview.analysis.keys.map(key =>
+                         `${key.fingerprint} ${key.user_ids.join(', ')}` +
+                         `${key.subkeys.slice(1).map(sk => `\n    sub ${sk.key_id} ${sk.algorithm} [${sk.usage}]${sk.has_secret ? ' (secret)' : ''}`).join('')}` +
+                         `${key.new_secret ? '\n    CONTAINS SECRET KEY MATERIAL' : ''}`).join('\n');
+                    entry.innerHTML = `<pre>${escapeHtml(keys)}</pre>`;
+                    const confirmBtn = document.createElement('button');
+                    confirmBtn.type = 'button';
+                    confirmBtn.textContent = 'Confirm Import';
+                    const discardBtn = document.createElement('button');
+                    discardBtn.type = 'button';
+                    discardBtn.textContent = 'Discard';
+                    confirmBtn.addEventListener('click', async () => {
+                         const response = await fetch('/api/scan_action', {
+                              method: 'POST',
+                              headers: { 'Content-Type': 'application/x-www-form-urlencoded' },
+                              body: new URLSearchParams({ scan_id: preview.scan_id, action: 'import_key' })
+                         });
+                         await handleApiResponse(response);
+                         entry.remove();
+                         updateStatus();
+                    });
+                    discardBtn.addEventListener('click', async () => {
+                         await fetch('/api/scan_discard', {
+                              method: 'POST',
+                              headers: { 'Content-Type': 'application/x-www-form-urlencoded' },
+                              body: new URLSearchParams({ scan_id: preview.scan_id })
+                         });
+                         entry.remove();
+                    });
+                    entry.appendChild(confirmBtn);
+                    entry.appendChild(discardBtn);
+                    importPreviewOutput.appendChild(entry);
+               });
+          });
+     }
+
+     importScannedKeyBtn.addEventListener('click', () => {
+          // Scanned keys come from an unverified source: show what will change first
+          const changes = (scannedAnalysis && scannedAnalysis.notes || []).filter(note => note.startsWith('Import preview: '));
+          if (changes.length === 0 || confirm(changes.join('\n') + '\n\nImport?')) {
+               runScanAction('import_key');
+          }
+     });
+     decryptScannedMsgBtn.addEventListener('click', () => runScanAction('decrypt'));
+     verifyScannedMsgBtn.addEventListener('click', () => runScanAction('verify'));
+     certifyScannedKeyBtn.addEventListener('click', () => runScanAction('certify_key'));
+     applyScannedRevocationBtn.addEventListener('click', () => {
+          if (confirm('Applying a revocation certificate cannot be undone. Continue?')) {
+               runScanAction('apply_revocation');
+          }
+     });
+     combineScannedSharesBtn.addEventListener('click', () => runScanAction('combine_shares'));
+     checkScannedSignResponseBtn.addEventListener('click', () => runScanAction('check_sign_response'));
+     answerScannedDecryptRequestBtn.addEventListener('click', () => runScanAction('answer_decrypt_request'));
+     revealScannedDecryptRequestBtn.addEventListener('click', () => {
+          if (confirm('The plaintext will be shown on this screen. Continue?')) {
+               runScanAction('reveal_decrypt_request');
           }
      });
+     openScannedDecryptResponseBtn.addEventListener('click', () => runScanAction('open_decrypt_response'));
 
 
     // --- Utility ---